@@ -0,0 +1,240 @@
+//! The kv backend talks to a reference key-value server keeping the keys in
+//! memory, in the process or standalone (`iotest kv-server`), so the latency
+//! of the network and the framing can be told from the latency of a storage.
+//!
+//! Every request is a frame of the op (1 write, 2 read, 3 delete, 4 size),
+//! then the key and the value, each after its length as a big-endian u32.
+//! Every response is a frame of the status (0 ok, 1 not found, 2 error), then
+//! the value (or the size as a big-endian u64, or the error) after its length.
+//! A connection serves its requests one by one, over TLS if the client and
+//! the server are given a `TlsConfig` and a certificate. A handler keeps its
+//! connection unless told not to reuse it, and connects again after one
+//! breaks.
+
+use std::{collections::BTreeMap, net::SocketAddr, process, sync::{atomic::{AtomicU64, Ordering}, Arc}, time::{Duration, Instant}};
+
+use async_trait::async_trait;
+use tokio::{io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufStream}, net::{self, TcpStream}, sync::Mutex};
+use tokio_rustls::TlsConnector;
+
+use iotest_core::{self as tester, Capability, Error, Metric, PhaseTimer, Result, TlsConfig};
+
+use protocol::{read_frame, write_frame, OP_DELETE, OP_READ, OP_SIZE, OP_WRITE, STATUS_NOT_FOUND, STATUS_OK};
+
+pub use server::KvServer;
+
+mod protocol;
+mod server;
+
+/// KvClient talks to a kv server, by a connection of every handler.
+pub struct KvClient {
+    /// The host and the port of the server, resolved on every connect.
+    addr: String,
+    /// The server was started in the process.
+    in_process: bool,
+    tls: Option<(TlsConfig, TlsConnector)>,
+    /// Keep a connection for all the requests of a handler.
+    reuse: bool,
+    auto_increment: AtomicU64,
+}
+
+impl KvClient {
+    /// Talk to the server at the address, e.g. `kv.internal:7878`.
+    pub fn new(addr: &str) -> Self {
+        Self { addr: addr.to_string(), in_process: false, tls: None, reuse: true, auto_increment: AtomicU64::new(0) }
+    }
+
+    /// Start a server in the process on a local port, and talk to it. Needs
+    /// a tokio runtime.
+    pub fn in_process() -> Result<Self> {
+        let addr = KvServer::start(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        Ok(Self { in_process: true, ..Self::new(&addr.to_string()) })
+    }
+
+    /// Connect for every request rather than once for all of a handler, to
+    /// measure the connection churn.
+    pub fn with_reuse(mut self, reuse: bool) -> Self {
+        self.reuse = reuse;
+        self
+    }
+
+    /// Connect to the server over TLS.
+    pub fn with_tls(mut self, tls: TlsConfig) -> Result<Self> {
+        let connector = tls.connector()?;
+        self.tls = Some((tls, connector));
+        Ok(self)
+    }
+}
+
+impl tester::TestClient for KvClient {
+    fn name(&self) -> &'static str {
+        "kv"
+    }
+
+    fn init(&self) {
+        println!("INIT KV CLIENT");
+        println!("  ADDR:          {}", self.addr);
+        if let Some((tls, _)) = &self.tls {
+            println!("  TLS:           {}", tls.describe());
+        }
+        if !self.reuse {
+            println!("  REUSE:         no, a connection per request");
+        }
+    }
+
+    fn gen_unique_key(&self) -> String {
+        let idx = self.auto_increment.fetch_add(1, Ordering::Relaxed);
+        format!("iotest-{}-{}", process::id(), idx)
+    }
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        let mut details = BTreeMap::new();
+        details.insert("addr".to_string(), self.addr.clone());
+        details.insert("reuse".to_string(), self.reuse.to_string());
+        details.insert("in_process".to_string(), self.in_process.to_string());
+        if let Some((tls, _)) = &self.tls {
+            details.insert("tls".to_string(), tls.describe());
+        }
+        details
+    }
+
+    fn handler(&self, ctx: &tester::HandlerContext) -> Arc<dyn tester::TestClientHandler> {
+        Arc::new(KvClientHandler {
+            addr: self.addr.clone(),
+            tls: self.tls.clone(),
+            reuse: self.reuse,
+            conn: Mutex::new(None),
+            phases: PhaseTimer::new(ctx.connection_phases),
+        })
+    }
+}
+
+/// Conn is a connection to the server, over TLS or not.
+trait Conn: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Conn for T {}
+
+pub struct KvClientHandler {
+    addr: String,
+    tls: Option<(TlsConfig, TlsConnector)>,
+    reuse: bool,
+    /// Connected by the setup, and again by a request after it broke or if
+    /// not reused.
+    conn: Mutex<Option<BufStream<Box<dyn Conn>>>>,
+    phases: PhaseTimer,
+}
+
+impl KvClientHandler {
+    /// Connect to the server, timing every phase.
+    async fn connect(&self) -> Result<BufStream<Box<dyn Conn>>> {
+        let start = Instant::now();
+        let addr = net::lookup_host(&self.addr).await
+            .map_err(|err| Error::from_io_error(&format!("resolve {}", self.addr), err))?
+            .next()
+            .ok_or_else(|| Error::connection(format!("resolve {}: no address", self.addr)))?;
+        self.phases.record(Metric::Dns, start.elapsed());
+
+        let start = Instant::now();
+        let stream = TcpStream::connect(addr).await
+            .map_err(|err| Error::from_io_error(&format!("connect {}", self.addr), err))?;
+        let _ = stream.set_nodelay(true);
+        self.phases.record(Metric::Connect, start.elapsed());
+
+        let stream: Box<dyn Conn> = match &self.tls {
+            Some((tls, connector)) => {
+                let start = Instant::now();
+                let host = self.addr.rsplit_once(':').map_or(self.addr.as_str(), |(host, _)| host);
+                let stream = tls.handshake(connector, host, stream).await?;
+                self.phases.record(Metric::TlsHandshake, start.elapsed());
+                Box::new(stream)
+            }
+            None => Box::new(stream),
+        };
+        Ok(BufStream::new(stream))
+    }
+
+    /// Issue the request and wait for its response, returning the value of
+    /// an ok one.
+    async fn call(&self, op: u8, name: &str, key: &str, value: &str) -> Result<Vec<u8>> {
+        let mut conn = self.conn.lock().await;
+        if conn.is_none() {
+            *conn = Some(self.connect().await?);
+        }
+        let stream = conn.as_mut().unwrap();
+        let start = Instant::now();
+        let response = match write_frame(stream, op, &[key.as_bytes(), value.as_bytes()]).await {
+            Ok(()) => match stream.fill_buf().await {
+                Ok(_) => {
+                    self.phases.record(Metric::FirstByte, start.elapsed());
+                    read_frame(stream, 1).await
+                }
+                Err(err) => Err(err),
+            },
+            Err(err) => Err(err),
+        };
+        if !self.reuse {
+            *conn = None;
+        }
+        // A frame half sent or received leaves nothing to go on with.
+        let response = response.map_err(|err| {
+            *conn = None;
+            Error::from_io_error(&format!("{} {}", name, key), err)
+        })?;
+        let Some((status, mut parts)) = response else {
+            *conn = None;
+            return Err(Error::connection(format!("{} {}: server closed the connection", name, key)));
+        };
+        let answer = parts.pop().unwrap_or_default();
+        match status {
+            STATUS_OK => Ok(answer),
+            STATUS_NOT_FOUND => Err(Error::not_found(format!("{} {}: not found", name, key))),
+            _ => Err(Error::new(format!("{} {}: {}", name, key, String::from_utf8_lossy(&answer)))),
+        }
+    }
+}
+
+#[async_trait]
+impl tester::TestClientHandler for KvClientHandler {
+    fn capabilities(&self) -> tester::Capabilities {
+        [Capability::Delete, Capability::Stat].into_iter().collect()
+    }
+
+    async fn setup(&self) -> Result<()> {
+        if self.reuse {
+            *self.conn.lock().await = Some(self.connect().await?);
+        }
+        Ok(())
+    }
+
+    async fn teardown(&self) -> Result<()> {
+        self.conn.lock().await.take();
+        Ok(())
+    }
+
+    fn take_phases(&self) -> Vec<(Metric, Duration)> {
+        self.phases.take()
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        self.call(OP_WRITE, "write", key, value).await?;
+        Ok(())
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        let value = self.call(OP_READ, "read", key, "").await?;
+        String::from_utf8(value)
+            .map_err(|err| Error::new(format!("read {}: {}", key, err)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.call(OP_DELETE, "delete", key, "").await?;
+        Ok(())
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        let size = self.call(OP_SIZE, "size", key, "").await?;
+        let size: [u8; 8] = size.try_into()
+            .map_err(|_| Error::new(format!("size {}: malformed response", key)))?;
+        Ok(u64::from_be_bytes(size))
+    }
+}
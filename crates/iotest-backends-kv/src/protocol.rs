@@ -0,0 +1,47 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const OP_WRITE: u8 = 1;
+pub const OP_READ: u8 = 2;
+pub const OP_DELETE: u8 = 3;
+pub const OP_SIZE: u8 = 4;
+
+pub const STATUS_OK: u8 = 0;
+pub const STATUS_NOT_FOUND: u8 = 1;
+pub const STATUS_ERROR: u8 = 2;
+
+/// The longest part of a frame, so a bad frame can't make either side
+/// allocate without end.
+const MAX_PART_LEN: u32 = 1 << 30;
+
+/// Write a frame of the tag and the parts, each after its length.
+pub async fn write_frame<W: AsyncWrite + Unpin>(w: &mut W, tag: u8, parts: &[&[u8]]) -> io::Result<()> {
+    w.write_u8(tag).await?;
+    for part in parts {
+        w.write_u32(part.len() as u32).await?;
+        w.write_all(part).await?;
+    }
+    w.flush().await
+}
+
+/// Read a frame of the tag and so many parts, none if the stream ends before
+/// it.
+pub async fn read_frame<R: AsyncRead + Unpin>(r: &mut R, parts: usize) -> io::Result<Option<(u8, Vec<Vec<u8>>)>> {
+    let tag = match r.read_u8().await {
+        Ok(tag) => tag,
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut read = Vec::with_capacity(parts);
+    for _ in 0..parts {
+        let len = r.read_u32().await?;
+        if len > MAX_PART_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("frame part of {} bytes", len)));
+        }
+        let mut part = vec![0; len as usize];
+        r.read_exact(&mut part).await?;
+        read.push(part);
+    }
+    Ok(Some((tag, read)))
+}
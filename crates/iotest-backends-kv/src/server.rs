@@ -0,0 +1,100 @@
+use std::{collections::HashMap, io, net::{self, SocketAddr}, sync::{Arc, Mutex, PoisonError}};
+
+use tokio::{io::{AsyncRead, AsyncWrite, BufStream}, net::TcpListener};
+use tokio_rustls::TlsAcceptor;
+
+use iotest_core::{Error, Result};
+
+use super::protocol::{read_frame, write_frame, OP_DELETE, OP_READ, OP_SIZE, OP_WRITE, STATUS_ERROR, STATUS_NOT_FOUND, STATUS_OK};
+
+type Store = Arc<Mutex<HashMap<Vec<u8>, Vec<u8>>>>;
+
+/// KvServer keeps the keys in memory and serves them over TCP, as fast as a
+/// storage gets, so what a client measures of it is the network and the
+/// framing.
+pub struct KvServer {
+    listener: TcpListener,
+    store: Store,
+    tls: Option<TlsAcceptor>,
+}
+
+impl KvServer {
+    /// Listen on the address. Needs a tokio runtime.
+    pub fn bind(addr: SocketAddr) -> Result<Self> {
+        let listener = net::TcpListener::bind(addr)
+            .and_then(|listener| listener.set_nonblocking(true).map(|()| listener))
+            .and_then(TcpListener::from_std)
+            .map_err(|err| Error::from_io_error(&format!("bind {}", addr), err))?;
+        Ok(Self { listener, store: Store::default(), tls: None })
+    }
+
+    /// Serve over TLS, e.g. by `iotest_core::tls_acceptor`.
+    pub fn with_tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.listener.local_addr()
+            .map_err(|err| Error::from_io_error("get local addr", err))
+    }
+
+    /// Listen on the address and serve in the background, returning where.
+    pub fn start(addr: SocketAddr) -> Result<SocketAddr> {
+        let server = Self::bind(addr)?;
+        let addr = server.local_addr()?;
+        tokio::spawn(server.serve());
+        Ok(addr)
+    }
+
+    /// Serve every connection until the listener fails.
+    pub async fn serve(self) -> Result<()> {
+        loop {
+            let (stream, _) = self.listener.accept().await
+                .map_err(|err| Error::from_io_error("accept", err))?;
+            let _ = stream.set_nodelay(true);
+            let store = self.store.clone();
+            match self.tls.clone() {
+                Some(acceptor) => {
+                    tokio::spawn(async move {
+                        serve_conn(acceptor.accept(stream).await?, store).await
+                    });
+                }
+                None => {
+                    tokio::spawn(serve_conn(stream, store));
+                }
+            }
+        }
+    }
+}
+
+/// Answer the requests of a connection one by one, until it closes.
+async fn serve_conn<S: AsyncRead + AsyncWrite + Unpin>(stream: S, store: Store) -> io::Result<()> {
+    let mut stream = BufStream::new(stream);
+    while let Some((op, parts)) = read_frame(&mut stream, 2).await? {
+        let [key, value]: [Vec<u8>; 2] = parts.try_into().unwrap();
+        let mut store = store.lock().unwrap_or_else(PoisonError::into_inner);
+        let (status, answer) = match op {
+            OP_WRITE => {
+                store.insert(key, value);
+                (STATUS_OK, vec![])
+            }
+            OP_READ => match store.get(&key) {
+                Some(value) => (STATUS_OK, value.clone()),
+                None => (STATUS_NOT_FOUND, vec![]),
+            },
+            OP_DELETE => match store.remove(&key) {
+                Some(_) => (STATUS_OK, vec![]),
+                None => (STATUS_NOT_FOUND, vec![]),
+            },
+            OP_SIZE => match store.get(&key) {
+                Some(value) => (STATUS_OK, (value.len() as u64).to_be_bytes().to_vec()),
+                None => (STATUS_NOT_FOUND, vec![]),
+            },
+            _ => (STATUS_ERROR, format!("unknown op {}", op).into_bytes()),
+        };
+        drop(store);
+        write_frame(&mut stream, status, &[&answer]).await?;
+    }
+    Ok(())
+}
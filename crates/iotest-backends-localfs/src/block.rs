@@ -0,0 +1,388 @@
+use std::{collections::{BTreeMap, HashMap}, fmt, fs::{self, create_dir_all, File}, io, str::FromStr, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, OnceLock, PoisonError}};
+
+use async_trait::async_trait;
+use tokio::{sync::Semaphore, task};
+
+use iotest_core::{self as tester, Error, Result};
+
+use super::{describe_dir, sync::{allocate, evict, punch_hole, SyncMode, CAN_EVICT, CAN_PUNCH_HOLE}};
+
+/// The name of the file the block client writes to, under the prefix.
+const FILE_NAME: &str = "iotest.blocks";
+
+/// The alignment of IO sizes.
+const ALIGN: u64 = 4096;
+
+/// Pattern is the order a block client writes the slots of its file in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pattern {
+    /// One slot after another, wrapping around at the end of the file.
+    #[default]
+    Sequential,
+    /// The slots in a scattered order, still each once before wrapping
+    /// around, so keys in flight never share a slot.
+    Random,
+}
+
+impl Pattern {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Pattern::Sequential => "sequential",
+            Pattern::Random => "random",
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sequential" => Ok(Pattern::Sequential),
+            "random" => Ok(Pattern::Random),
+            _ => Err(format!("unknown pattern {}, expected sequential or random", s)),
+        }
+    }
+}
+
+/// LocalFsBlockClient writes every key at an offset of one preallocated
+/// file instead of as a file of its own, like fio does. The file is split
+/// into slots of the IO size, which must hold a value (see `io_size_for`),
+/// visited sequentially or at random.
+///
+/// The slots wrap around, so the file should hold more than the keys alive
+/// at once: a key overwritten by a later one reads as not found.
+pub struct LocalFsBlockClient {
+    prefix: String,
+    file_size: u64,
+    io_size: u64,
+    pattern: Pattern,
+    sync_mode: SyncMode,
+    /// Bounds the IOs in flight, if given.
+    queue_depth: Option<usize>,
+    auto_increment: AtomicU64,
+    blocks: Arc<Blocks>,
+}
+
+impl LocalFsBlockClient {
+    /// Write keys into a file of the size under the directory, in slots of
+    /// the IO size.
+    pub fn new(prefix: &str, file_size: u64, io_size: u64) -> Self {
+        let mut prefix = prefix.to_string();
+        if !prefix.ends_with(std::path::is_separator) {
+            prefix.push(std::path::MAIN_SEPARATOR);
+        }
+        let io_size = io_size.max(1);
+        println!("INIT CLIENT");
+        println!("  PREFIX:        {}", prefix);
+        println!("  FILE SIZE:     {}", file_size);
+        println!("  IO SIZE:       {}", io_size);
+        Self {
+            blocks: Arc::new(Blocks::new(format!("{}{}", prefix, FILE_NAME), io_size, SyncMode::None, None)),
+            prefix,
+            file_size,
+            io_size,
+            pattern: Pattern::Sequential,
+            sync_mode: SyncMode::None,
+            queue_depth: None,
+            auto_increment: AtomicU64::new(0),
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: Pattern) -> Self {
+        println!("  PATTERN:       {}", pattern);
+        self.pattern = pattern;
+        self
+    }
+
+    /// Make every write as durable as the mode before it returns.
+    pub fn with_sync_mode(mut self, sync_mode: SyncMode) -> Self {
+        println!("  SYNC MODE:     {}", sync_mode);
+        self.sync_mode = sync_mode;
+        self.blocks = Arc::new(Blocks::new(self.blocks.path.clone(), self.io_size, sync_mode, self.queue_depth));
+        self
+    }
+
+    /// Keep at most so many IOs in flight, the rest queue.
+    pub fn with_queue_depth(mut self, queue_depth: usize) -> Self {
+        println!("  QUEUE DEPTH:   {}", queue_depth);
+        self.queue_depth = Some(queue_depth.max(1));
+        self.blocks = Arc::new(Blocks::new(self.blocks.path.clone(), self.io_size, self.sync_mode, self.queue_depth));
+        self
+    }
+
+    /// The smallest aligned IO size holding the values of the payload
+    /// length, which come with a header, the key and block headers.
+    pub fn io_size_for(payload_len: usize) -> u64 {
+        let payload_len = payload_len as u64;
+        let value_len = payload_len + 64 + (payload_len / ALIGN + 1) * 24;
+        value_len.div_ceil(ALIGN) * ALIGN
+    }
+
+    fn slots(&self) -> u64 {
+        (self.file_size / self.io_size).max(1)
+    }
+
+    /// The slot of the `idx`-th key.
+    fn slot(&self, idx: u64) -> u64 {
+        let slots = self.slots();
+        match self.pattern {
+            Pattern::Sequential => idx % slots,
+            // A stride coprime with the count of slots visits all of them
+            // before coming back, in an order far from sequential.
+            Pattern::Random => (idx % slots) * coprime_stride(slots) % slots,
+        }
+    }
+}
+
+impl tester::TestClient for LocalFsBlockClient {
+    fn name(&self) -> &'static str {
+        "localfs"
+    }
+
+    /// Create the file and allocate all of it, so writes don't grow it.
+    fn init(&self) {
+        create_dir_all(&self.prefix).expect("create prefix");
+        let file = self.blocks.open().expect("open block file");
+        allocate(file, self.file_size).expect("preallocate block file");
+    }
+
+    fn gen_unique_key(&self) -> String {
+        let idx = self.auto_increment.fetch_add(1, Ordering::Relaxed);
+        format!("{}@{}", idx, self.slot(idx) * self.io_size)
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        match fs::remove_file(&self.blocks.path) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                return Err(Error::from_io_error(&format!("delete {}", self.blocks.path), err));
+            }
+            _ => (),
+        }
+        let _ = fs::remove_dir(&self.prefix);
+        Ok(())
+    }
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        let mut details = describe_dir(&self.prefix, self.sync_mode);
+        details.insert("file_size".to_string(), self.file_size.to_string());
+        details.insert("io_size".to_string(), self.io_size.to_string());
+        details.insert("pattern".to_string(), self.pattern.to_string());
+        if let Some(queue_depth) = self.queue_depth {
+            details.insert("queue_depth".to_string(), queue_depth.to_string());
+        }
+        details
+    }
+
+    fn handler(&self, _ctx: &tester::HandlerContext) -> Arc<dyn tester::TestClientHandler> {
+        Arc::new(LocalFsBlockClientHandler { blocks: self.blocks.clone() })
+    }
+}
+
+/// Blocks is the file the keys are written in, shared by the handlers, and
+/// where every key is in it.
+struct Blocks {
+    path: String,
+    io_size: u64,
+    file: OnceLock<File>,
+    sync_mode: SyncMode,
+    queue: Option<Semaphore>,
+    /// The key and the length of the value in every written slot, by offset.
+    index: Mutex<HashMap<u64, (String, usize)>>,
+}
+
+impl Blocks {
+    fn new(path: String, io_size: u64, sync_mode: SyncMode, queue_depth: Option<usize>) -> Self {
+        Self {
+            path,
+            io_size,
+            file: OnceLock::new(),
+            sync_mode,
+            queue: queue_depth.map(Semaphore::new),
+            index: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn open(&self) -> io::Result<&File> {
+        if let Some(file) = self.file.get() {
+            return Ok(file);
+        }
+        let file = self.sync_mode.open_options().read(true).open(&self.path)?;
+        Ok(self.file.get_or_init(|| file))
+    }
+
+    /// Run the IO on the blocking pool, queued by the depth if any.
+    async fn io<T, F>(self: &Arc<Self>, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Blocks) -> Result<T> + Send + 'static,
+    {
+        let _permit = match &self.queue {
+            Some(queue) => Some(queue.acquire().await.map_err(|_| Error::new("queue closed"))?),
+            None => None,
+        };
+        let blocks = self.clone();
+        task::spawn_blocking(move || f(&blocks)).await
+            .map_err(|err| Error::new(format!("io panicked: {}", err)))?
+    }
+
+    fn lookup(&self, key: &str) -> Result<(u64, usize)> {
+        let offset = offset(key)?;
+        let index = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+        match index.get(&offset) {
+            Some((written, len)) if written == key => Ok((offset, *len)),
+            _ => Err(Error::not_found(format!("read {}: not found", key))),
+        }
+    }
+}
+
+pub struct LocalFsBlockClientHandler {
+    blocks: Arc<Blocks>,
+}
+
+#[async_trait]
+impl tester::TestClientHandler for LocalFsBlockClientHandler {
+    fn capabilities(&self) -> tester::Capabilities {
+        let mut capabilities = vec![tester::Capability::Delete, tester::Capability::Stat];
+        if CAN_EVICT {
+            capabilities.push(tester::Capability::Evict);
+        }
+        if CAN_PUNCH_HOLE {
+            capabilities.push(tester::Capability::PunchHole);
+        }
+        capabilities.into_iter().collect()
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        if value.len() as u64 > self.blocks.io_size {
+            return Err(Error::new(format!("write {}: {} bytes don't fit the IO size {}", key, value.len(), self.blocks.io_size)));
+        }
+        let (key, value) = (key.to_string(), value.to_string());
+        self.blocks.io(move |blocks| {
+            let offset = offset(&key)?;
+            let file = blocks.open().map_err(|err| Error::from_io_error(&format!("open {}", blocks.path), err))?;
+            write_at(file, value.as_bytes(), offset)
+                .map_err(|err| Error::from_io_error(&format!("write {}", key), err))?;
+            blocks.sync_mode.sync_positional(file).map_err(|err| Error::from_io_error(&format!("{} {}", blocks.sync_mode, key), err))?;
+            blocks.index.lock().unwrap_or_else(PoisonError::into_inner).insert(offset, (key, value.len()));
+            Ok(())
+        }).await
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        let key = key.to_string();
+        self.blocks.io(move |blocks| {
+            let (offset, len) = blocks.lookup(&key)?;
+            let file = blocks.open().map_err(|err| Error::from_io_error(&format!("open {}", blocks.path), err))?;
+            let mut buf = vec![0; len];
+            read_at(file, &mut buf, offset)
+                .map_err(|err| Error::from_io_error(&format!("read {}", key), err))?;
+            String::from_utf8(buf).map_err(|err| Error::new(format!("read {}: {}", key, err)))
+        }).await
+    }
+
+    /// Forget the key, its slot is left as is.
+    async fn delete(&self, key: &str) -> Result<()> {
+        let offset = offset(key)?;
+        let mut index = self.blocks.index.lock().unwrap_or_else(PoisonError::into_inner);
+        match index.get(&offset) {
+            Some((written, _)) if written == key => {
+                index.remove(&offset);
+                Ok(())
+            }
+            _ => Err(Error::not_found(format!("delete {}: not found", key))),
+        }
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        self.blocks.lookup(key).map(|(_, len)| len as u64)
+    }
+
+    /// Drop the slot of the key from the page cache.
+    async fn evict(&self, key: &str) -> Result<()> {
+        let key = key.to_string();
+        self.blocks.io(move |blocks| {
+            let (offset, _) = blocks.lookup(&key)?;
+            let file = blocks.open().map_err(|err| Error::from_io_error(&format!("open {}", blocks.path), err))?;
+            evict(file, offset, blocks.io_size)
+                .map_err(|err| Error::from_io_error(&format!("evict {}", key), err))
+        }).await
+    }
+
+    /// Punch the hole in the slot of the key, within its value.
+    async fn punch_hole(&self, key: &str, hole_offset: u64, len: u64) -> Result<()> {
+        let key = key.to_string();
+        self.blocks.io(move |blocks| {
+            let (offset, value_len) = blocks.lookup(&key)?;
+            if hole_offset + len > value_len as u64 {
+                return Err(Error::new(format!("punch_hole {}: {}..{} out of {} bytes", key, hole_offset, hole_offset + len, value_len)));
+            }
+            let file = blocks.open().map_err(|err| Error::from_io_error(&format!("open {}", blocks.path), err))?;
+            punch_hole(file, offset + hole_offset, len)
+                .and_then(|()| blocks.sync_mode.sync_positional(file))
+                .map_err(|err| Error::from_io_error(&format!("punch_hole {}", key), err))
+        }).await
+    }
+}
+
+/// The offset of the slot a key is written in, after the `@`.
+fn offset(key: &str) -> Result<u64> {
+    key.rsplit_once('@')
+        .and_then(|(_, offset)| offset.parse().ok())
+        .ok_or_else(|| Error::new(format!("{}: not a key of a block file", key)))
+}
+
+/// A stride about 0.618 of the count, the golden ratio, coprime with it.
+fn coprime_stride(count: u64) -> u64 {
+    let mut stride = ((count as f64 * 0.618) as u64).max(1);
+    while gcd(stride, count) != 1 {
+        stride += 1;
+    }
+    stride
+}
+
+fn gcd(a: u64, b: u64) -> u64 {
+    if b == 0 { a } else { gcd(b, a % b) }
+}
+
+#[cfg(unix)]
+pub fn write_at(file: &File, buf: &[u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buf, offset)
+}
+
+#[cfg(unix)]
+pub fn read_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+#[cfg(windows)]
+pub fn write_at(file: &File, mut buf: &[u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        let written = std::os::windows::fs::FileExt::seek_write(file, buf, offset)?;
+        if written == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        buf = &buf[written..];
+        offset += written as u64;
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn read_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        let read = std::os::windows::fs::FileExt::seek_read(file, buf, offset)?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        buf = &mut buf[read..];
+        offset += read as u64;
+    }
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use std::fmt;
+
+/// FanOut spreads keys over a tree of directories named by a hash of the
+/// key, e.g. `ab/cd/<KEY>`, so millions of files don't pile up in one
+/// directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FanOut {
+    /// How deep the tree is.
+    pub levels: u32,
+    /// How many directories every level has.
+    pub width: u32,
+}
+
+impl FanOut {
+    pub fn new(levels: u32, width: u32) -> Self {
+        Self { levels, width: width.max(1) }
+    }
+
+    /// The directories the name goes in, each with a trailing separator,
+    /// e.g. `ab/cd/`.
+    pub fn dirs(&self, name: &str) -> String {
+        let mut hash = fnv1a(name.as_bytes());
+        let digits = self.digits();
+        let mut dirs = String::new();
+        for _ in 0..self.levels {
+            let dir = hash % self.width as u64;
+            hash /= self.width as u64;
+            dirs += &format!("{:0digits$x}{}", dir, std::path::MAIN_SEPARATOR, digits = digits);
+        }
+        dirs
+    }
+
+    /// The hex digits of the last directory of a level.
+    fn digits(&self) -> usize {
+        format!("{:x}", self.width - 1).len()
+    }
+}
+
+impl fmt::Display for FanOut {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} levels of {}", self.levels, self.width)
+    }
+}
+
+/// The FNV-1a hash, stable across runs and platforms unlike the hasher of
+/// `std`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
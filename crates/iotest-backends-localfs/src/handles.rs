@@ -0,0 +1,65 @@
+use std::{collections::HashMap, fs::File, io, sync::{Arc, Mutex, PoisonError}};
+
+use super::{block::{read_at, write_at}, sync::{allocate, SyncMode}};
+
+/// Handles keeps the files of keys open, up to a count, to write and read
+/// them at offsets instead of opening them for every operation, which takes
+/// the opens and closes out of the latencies. It is shared by all handlers.
+pub struct Handles {
+    sync_mode: SyncMode,
+    /// Allocate the file to the length of the value before writing it.
+    allocate: bool,
+    max_open: usize,
+    files: Mutex<HashMap<String, Arc<File>>>,
+}
+
+impl Handles {
+    pub fn new(sync_mode: SyncMode, allocate: bool, max_open: usize) -> Self {
+        Self { sync_mode, allocate, max_open: max_open.max(1), files: Mutex::new(HashMap::new()) }
+    }
+
+    /// The open file of the key, opened (and created if asked) unless it is
+    /// already. A file is closed to make room when too many are open.
+    pub fn get(&self, key: &str, create: bool) -> io::Result<Arc<File>> {
+        if let Some(file) = self.files.lock().unwrap_or_else(PoisonError::into_inner).get(key) {
+            return Ok(file.clone());
+        }
+        let mut options = self.sync_mode.open_options();
+        options.read(true).create(create);
+        let file = Arc::new(options.open(key)?);
+        let mut files = self.files.lock().unwrap_or_else(PoisonError::into_inner);
+        if files.len() >= self.max_open {
+            if let Some(closed) = files.keys().next().cloned() {
+                files.remove(&closed);
+            }
+        }
+        Ok(files.entry(key.to_string()).or_insert(file).clone())
+    }
+
+    /// Close the file of the key, if open.
+    pub fn close(&self, key: &str) {
+        self.files.lock().unwrap_or_else(PoisonError::into_inner).remove(key);
+    }
+
+    /// Write the value over the file of the key, cutting what is left of a
+    /// longer one.
+    pub fn write(&self, key: &str, value: &str) -> io::Result<()> {
+        let file = self.get(key, true)?;
+        let len = value.len() as u64;
+        if self.allocate {
+            allocate(&file, len)?;
+        }
+        write_at(&file, value.as_bytes(), 0)?;
+        if file.metadata()?.len() > len {
+            file.set_len(len)?;
+        }
+        self.sync_mode.sync_positional(&file)
+    }
+
+    pub fn read(&self, key: &str) -> io::Result<String> {
+        let file = self.get(key, false)?;
+        let mut buf = vec![0; file.metadata()?.len() as usize];
+        read_at(&file, &mut buf, 0)?;
+        String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
@@ -0,0 +1,503 @@
+//! The localfs backend writes every key as a file under a directory.
+
+use std::{collections::BTreeMap, env, fs::{self, create_dir_all}, io, path::{self, Path, MAIN_SEPARATOR}, process, sync::{atomic::{AtomicU64, Ordering}, Arc, OnceLock}};
+
+use async_trait::async_trait;
+use tokio::{fs::{metadata, read_dir, remove_file, File, OpenOptions}, io::{AsyncReadExt, AsyncWriteExt}, task};
+
+use iotest_core::{self as tester, Error, Result};
+
+use handles::Handles;
+use mount::Mount;
+use sync::{allocate, copy, evict, punch_hole, CAN_EVICT, CAN_PUNCH_HOLE};
+use xattr::CAN_XATTR;
+
+pub use block::{LocalFsBlockClient, LocalFsBlockClientHandler, Pattern};
+pub use fanout::FanOut;
+pub use sync::SyncMode;
+
+mod block;
+mod fanout;
+mod handles;
+mod mount;
+mod sync;
+mod xattr;
+
+pub struct LocalFsClient {
+    prefix: String,
+    sync_mode: SyncMode,
+    fallocate: bool,
+    /// The count and the length of the files created for the first keys at
+    /// the init.
+    preallocate: Option<(u64, u64)>,
+    fan_out: Option<FanOut>,
+    /// Keep up to so many files open, shared by the handlers.
+    keep_open: Option<usize>,
+    handles: OnceLock<Arc<Handles>>,
+    auto_increment: AtomicU64,
+}
+
+impl LocalFsClient {
+    pub fn new() -> Self {
+        Self::with_prefix(&default_prefix())
+    }
+
+    /// Write keys under the directory.
+    pub fn with_prefix(prefix: &str) -> Self {
+        let mut prefix = prefix.to_string();
+        if !prefix.ends_with(path::is_separator) {
+            prefix.push(MAIN_SEPARATOR);
+        }
+        println!("INIT CLIENT");
+        println!("  PREFIX:        {}", prefix);
+        Self {
+            prefix,
+            sync_mode: SyncMode::None,
+            fallocate: false,
+            preallocate: None,
+            fan_out: None,
+            keep_open: None,
+            handles: OnceLock::new(),
+            auto_increment: AtomicU64::new(0),
+        }
+    }
+
+    /// Make every write as durable as the mode before it returns.
+    pub fn with_sync_mode(mut self, sync_mode: SyncMode) -> Self {
+        println!("  SYNC MODE:     {}", sync_mode);
+        self.sync_mode = sync_mode;
+        self
+    }
+
+    /// Allocate every file to the length of its value before writing it, so
+    /// the write itself doesn't allocate.
+    pub fn with_fallocate(mut self, fallocate: bool) -> Self {
+        println!("  FALLOCATE:     {}", fallocate);
+        self.fallocate = fallocate;
+        self
+    }
+
+    /// Create the files of the first `count` keys at the init, allocated to
+    /// `len` bytes, so writing them overwrites instead of allocating. Only
+    /// keys out of namespaces are preallocated.
+    pub fn with_preallocate(mut self, count: u64, len: u64) -> Self {
+        println!("  PREALLOCATE:   {} files of {} bytes", count, len);
+        self.preallocate = Some((count, len));
+        self
+    }
+
+    /// Spread the keys over a tree of directories, `levels` deep and `width`
+    /// wide, created as keys are written into them.
+    pub fn with_fan_out(mut self, fan_out: FanOut) -> Self {
+        println!("  FAN OUT:       {}", fan_out);
+        self.fan_out = Some(fan_out);
+        self
+    }
+
+    /// Keep the files of up to `max_open` keys open, writing and reading
+    /// them at offsets, instead of opening them for every operation, to
+    /// measure the data path without the opens and closes.
+    pub fn with_keep_open(mut self, max_open: usize) -> Self {
+        println!("  KEEP OPEN:     {} files", max_open);
+        self.keep_open = Some(max_open);
+        self
+    }
+
+    /// The path of the key of the name, in its directory of the fan-out.
+    fn key(&self, name: &str) -> String {
+        match self.fan_out {
+            Some(fan_out) => format!("{}{}{}", self.prefix, fan_out.dirs(name), name),
+            None => format!("{}{}", self.prefix, name),
+        }
+    }
+
+    fn init(&self) {
+        // Mkdir if the preifx directory is not existing.
+        let prefix = Path::new(&self.prefix);
+        if !prefix.exists() {
+            create_dir_all(prefix).unwrap();
+        }
+        if let Some((count, len)) = self.preallocate {
+            for idx in 0..count {
+                let path = self.key(&idx.to_string());
+                if let Some(dir) = Path::new(&path).parent() {
+                    create_dir_all(dir).unwrap_or_else(|err| panic!("create {}: {}", dir.display(), err));
+                }
+                let file = self.sync_mode.open_options().open(&path)
+                    .unwrap_or_else(|err| panic!("create {}: {}", path, err));
+                allocate(&file, len).unwrap_or_else(|err| panic!("allocate {}: {}", path, err));
+            }
+        }
+    }
+}
+
+impl tester::TestClient for LocalFsClient {
+    fn name(&self) -> &'static str {
+        "localfs"
+    }
+
+    fn init(&self) {
+        self.init()
+    }
+
+    fn gen_unique_key(&self) -> String {
+        let idx = self.auto_increment.fetch_add(1, Ordering::Relaxed);
+        self.key(&idx.to_string())
+    }
+
+    /// Keys of a namespace are files with its name as a prefix, in the same
+    /// directory.
+    fn gen_namespaced_key(&self, namespace: &str) -> String {
+        let idx = self.auto_increment.fetch_add(1, Ordering::Relaxed);
+        self.key(&format!("{}-{}", namespace, idx))
+    }
+
+    /// Remove the keys of this client, and the prefix directory if nothing
+    /// else is left in it. Other files under the prefix are kept.
+    fn cleanup(&self) -> Result<()> {
+        let preallocated = self.preallocate.map_or(0, |(count, _)| count);
+        let written = self.auto_increment.load(Ordering::Relaxed).max(preallocated);
+        let levels = self.fan_out.map_or(0, |fan_out| fan_out.levels);
+        remove_keys(Path::new(&self.prefix), levels, written)?;
+        let _ = fs::remove_dir(&self.prefix);
+        Ok(())
+    }
+
+    /// The directory, and the filesystem and mount options it is on, which
+    /// latencies depend on as much as on the disk.
+    fn describe(&self) -> BTreeMap<String, String> {
+        let mut details = describe_dir(&self.prefix, self.sync_mode);
+        details.insert("fallocate".to_string(), self.fallocate.to_string());
+        if let Some((count, len)) = self.preallocate {
+            details.insert("preallocate".to_string(), format!("{} files of {} bytes", count, len));
+        }
+        if let Some(fan_out) = self.fan_out {
+            details.insert("fan_out".to_string(), fan_out.to_string());
+        }
+        if let Some(max_open) = self.keep_open {
+            details.insert("keep_open".to_string(), max_open.to_string());
+        }
+        details
+    }
+
+    fn handler(&self, _ctx: &tester::HandlerContext) -> Arc<dyn tester::TestClientHandler> {
+        Arc::new(LocalFsClientHandler {
+            prefix: self.prefix.clone(),
+            sync_mode: self.sync_mode,
+            fan_out: self.fan_out.is_some(),
+            handles: self.keep_open.map(|max_open| {
+                self.handles.get_or_init(|| Arc::new(Handles::new(self.sync_mode, self.fallocate, max_open))).clone()
+            }),
+            // A preallocated file must not be truncated before it is written.
+            overwrite: self.fallocate || self.preallocate.is_some(),
+        })
+    }
+}
+
+pub struct LocalFsClientHandler {
+    /// The directory the keys are under.
+    prefix: String,
+    sync_mode: SyncMode,
+    /// Keys are in directories of the fan-out, created by the first write
+    /// in them.
+    fan_out: bool,
+    /// The open files, if kept open.
+    handles: Option<Arc<Handles>>,
+    /// Allocate files and overwrite them in place, instead of truncating
+    /// them.
+    overwrite: bool,
+}
+
+impl LocalFsClientHandler {
+    /// Create the directory of the key in the fan-out.
+    async fn create_dir(&self, key: &str) -> Result<()> {
+        if let Some(dir) = Path::new(key).parent() {
+            tokio::fs::create_dir_all(dir).await
+                .map_err(|err| Error::from_io_error(&format!("create {}", dir.display()), err))?;
+        }
+        Ok(())
+    }
+
+    /// Write the value over the file of the key kept open.
+    async fn write_handle(handles: &Arc<Handles>, key: &str, value: &str) -> io::Result<()> {
+        let (handles, key, value) = (handles.clone(), key.to_string(), value.to_string());
+        task::spawn_blocking(move || handles.write(&key, &value)).await?
+    }
+
+    /// Open the file to write the value of the length in.
+    async fn create(&self, key: &str, len: u64) -> io::Result<File> {
+        if self.overwrite {
+            self.open_allocated(key, len).await
+        } else {
+            self.sync_mode.create(key).await
+        }
+    }
+
+    /// Open the file, allocated to the length, without truncating it.
+    async fn open_allocated(&self, key: &str, len: u64) -> io::Result<File> {
+        let options = self.sync_mode.open_options();
+        let path = key.to_string();
+        let file = task::spawn_blocking(move || {
+            let file = options.open(&path)?;
+            allocate(&file, len)?;
+            Ok::<_, io::Error>(file)
+        }).await??;
+        Ok(File::from_std(file))
+    }
+}
+
+#[async_trait]
+impl tester::TestClientHandler for LocalFsClientHandler {
+    fn capabilities(&self) -> tester::Capabilities {
+        tester::Capability::ALL.into_iter()
+            .filter(|capability| match capability {
+                tester::Capability::Evict => CAN_EVICT,
+                tester::Capability::Xattr => CAN_XATTR,
+                tester::Capability::PunchHole => CAN_PUNCH_HOLE,
+                tester::Capability::Delete
+                | tester::Capability::Stat
+                | tester::Capability::List
+                | tester::Capability::Append
+                | tester::Capability::Copy => true,
+                // Files keep no versions.
+                _ => false,
+            })
+            .collect()
+    }
+
+    /// The blocks allocated to the files under the directory, as `du`
+    /// counts them.
+    async fn space_used(&self) -> Result<Option<u64>> {
+        let prefix = self.prefix.clone();
+        task::spawn_blocking(move || disk_usage(Path::new(&prefix)))
+            .await
+            .map_err(|err| Error::new(format!("du {}: {}", self.prefix, err)))?
+            .map(Some)
+            .map_err(|err| Error::from_io_error(&format!("du {}", self.prefix), err))
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        if let Some(handles) = &self.handles {
+            let written = match Self::write_handle(handles, key, value).await {
+                Err(err) if err.kind() == io::ErrorKind::NotFound && self.fan_out => {
+                    self.create_dir(key).await?;
+                    Self::write_handle(handles, key, value).await
+                }
+                written => written,
+            };
+            return written.map_err(|err| Error::from_io_error(&format!("write {}", key), err));
+        }
+        let len = value.len() as u64;
+        let mut file = match self.create(key, len).await {
+            Err(err) if err.kind() == io::ErrorKind::NotFound && self.fan_out => {
+                self.create_dir(key).await?;
+                self.create(key, len).await
+            }
+            file => file,
+        }.map_err(|err| Error::from_io_error(&format!("create {}", key), err))?;
+        file.write_all(value.as_bytes()).await
+            .map_err(|err| Error::from_io_error(&format!("write {}", key), err))?;
+        if self.overwrite {
+            // Cut what is left of a longer file.
+            file.set_len(len).await
+                .map_err(|err| Error::from_io_error(&format!("truncate {}", key), err))?;
+        }
+        self.sync_mode.sync(&mut file).await
+            .map_err(|err| Error::from_io_error(&format!("{} {}", self.sync_mode, key), err))?;
+        Ok(())
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        if let Some(handles) = &self.handles {
+            let (handles, path) = (handles.clone(), key.to_string());
+            return task::spawn_blocking(move || handles.read(&path)).await
+                .map_err(|err| Error::new(format!("read {}: {}", key, err)))?
+                .map_err(|err| Error::from_io_error(&format!("read {}", key), err));
+        }
+        let mut file = File::open(key).await
+            .map_err(|err| Error::from_io_error(&format!("open {}", key), err))?;
+        let mut result = String::new();
+        file.read_to_string(&mut result).await
+            .map_err(|err| Error::from_io_error(&format!("read {}", key), err))?;
+        Ok(result)
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        if let Some(handles) = &self.handles {
+            handles.close(key);
+        }
+        remove_file(key).await
+            .map_err(|err| Error::from_io_error(&format!("delete {}", key), err))?;
+        Ok(())
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        let metadata = metadata(key).await
+            .map_err(|err| Error::from_io_error(&format!("stat {}", key), err))?;
+        Ok(metadata.len())
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        // Keys are paths, so list the directory and match the file names.
+        // Keep the separator of the prefix, `/` or `\` on Windows.
+        let (dir, name_prefix) = match prefix.rfind(path::is_separator) {
+            Some(idx) => (&prefix[..=idx], &prefix[idx + 1..]),
+            None => ("", prefix),
+        };
+        let read_dir_path = if dir.is_empty() { "." } else { dir };
+        let mut entries = read_dir(read_dir_path).await
+            .map_err(|err| Error::from_io_error(&format!("list {}", read_dir_path), err))?;
+        let mut keys = vec![];
+        while let Some(entry) = entries.next_entry().await
+            .map_err(|err| Error::from_io_error(&format!("list {}", read_dir_path), err))? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with(name_prefix) {
+                keys.push(format!("{}{}", dir, name));
+            }
+        }
+        Ok(keys)
+    }
+
+    async fn append(&self, key: &str, value: &str) -> Result<()> {
+        let mut file = OpenOptions::new().append(true).open(key).await
+            .map_err(|err| Error::from_io_error(&format!("open {}", key), err))?;
+        file.write_all(value.as_bytes()).await
+            .map_err(|err| Error::from_io_error(&format!("append {}", key), err))?;
+        self.sync_mode.sync(&mut file).await
+            .map_err(|err| Error::from_io_error(&format!("{} {}", self.sync_mode, key), err))?;
+        Ok(())
+    }
+
+    async fn evict(&self, key: &str) -> Result<()> {
+        let path = key.to_string();
+        task::spawn_blocking(move || {
+            let file = fs::File::open(&path)?;
+            let len = file.metadata()?.len();
+            evict(&file, 0, len)
+        }).await
+            .map_err(|err| Error::new(format!("evict {}: {}", key, err)))?
+            .map_err(|err| Error::from_io_error(&format!("evict {}", key), err))
+    }
+
+    async fn set_xattr(&self, key: &str, name: &str, value: &str) -> Result<()> {
+        let (path, name, value) = (key.to_string(), name.to_string(), value.to_string());
+        task::spawn_blocking(move || xattr::set(&path, &name, &value)).await
+            .map_err(|err| Error::new(format!("set_xattr {}: {}", key, err)))?
+            .map_err(|err| Error::from_io_error(&format!("set_xattr {}", key), err))
+    }
+
+    async fn get_xattr(&self, key: &str, name: &str) -> Result<String> {
+        let (path, name) = (key.to_string(), name.to_string());
+        task::spawn_blocking(move || xattr::get(&path, &name)).await
+            .map_err(|err| Error::new(format!("get_xattr {}: {}", key, err)))?
+            .map_err(|err| Error::from_io_error(&format!("get_xattr {}", key), err))
+    }
+
+    async fn remove_xattr(&self, key: &str, name: &str) -> Result<()> {
+        let (path, name) = (key.to_string(), name.to_string());
+        task::spawn_blocking(move || xattr::remove(&path, &name)).await
+            .map_err(|err| Error::new(format!("remove_xattr {}: {}", key, err)))?
+            .map_err(|err| Error::from_io_error(&format!("remove_xattr {}", key), err))
+    }
+
+    async fn punch_hole(&self, key: &str, offset: u64, len: u64) -> Result<()> {
+        let (path, sync_mode) = (key.to_string(), self.sync_mode);
+        task::spawn_blocking(move || {
+            let file = sync_mode.open_options().open(&path)?;
+            punch_hole(&file, offset, len)?;
+            sync_mode.sync_positional(&file)
+        }).await
+            .map_err(|err| Error::new(format!("punch_hole {}: {}", key, err)))?
+            .map_err(|err| Error::from_io_error(&format!("punch_hole {}", key), err))
+    }
+
+    async fn copy(&self, key: &str, to: &str) -> Result<()> {
+        let (from, to, sync_mode) = (key.to_string(), to.to_string(), self.sync_mode);
+        task::spawn_blocking(move || copy(&from, &to, sync_mode)).await
+            .map_err(|err| Error::new(format!("copy {}: {}", key, err)))?
+            .map_err(|err| Error::from_io_error(&format!("copy {}", key), err))
+    }
+}
+
+/// The directory written to by default, `<TEMP>/iotest_<PID>`.
+pub fn default_prefix() -> String {
+    env::temp_dir().join(format!("iotest_{}", process::id())).to_string_lossy().into_owned()
+}
+
+/// Remove the keys below `written` in the directory, and in the directories
+/// of the fan-out `levels` deep under it, which are removed once empty.
+fn remove_keys(dir: &Path, levels: u32, written: u64) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(Error::from_io_error(&format!("list {}", dir.display()), err)),
+    };
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::from_io_error(&format!("list {}", dir.display()), err))?;
+        if levels > 0 && entry.file_type().map_or(false, |file_type| file_type.is_dir()) {
+            remove_keys(&entry.path(), levels - 1, written)?;
+            let _ = fs::remove_dir(entry.path());
+            continue;
+        }
+        // Namespaced keys are `<NAMESPACE>-<IDX>`, and copies of keys have a
+        // suffix.
+        let ours = entry.file_name().to_str()
+            .map(|name| name.strip_suffix(tester::COPY_SUFFIX).unwrap_or(name))
+            .map(|name| name.rsplit_once('-').map_or(name, |(_, idx)| idx))
+            .and_then(|idx| idx.parse::<u64>().ok())
+            .map_or(false, |idx| idx < written);
+        if ours {
+            fs::remove_file(entry.path())
+                .map_err(|err| Error::from_io_error(&format!("delete {}", entry.path().display()), err))?;
+        }
+    }
+    Ok(())
+}
+
+/// The bytes allocated to the files under the directory and the directories
+/// under it. Files deleted meanwhile are skipped.
+fn disk_usage(dir: &Path) -> io::Result<u64> {
+    let mut used = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err),
+        };
+        used += if metadata.is_dir() {
+            match disk_usage(&entry.path()) {
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                du => du?,
+            }
+        } else {
+            allocated(&metadata)
+        };
+    }
+    Ok(used)
+}
+
+#[cfg(unix)]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::blocks(metadata) * 512
+}
+
+#[cfg(not(unix))]
+fn allocated(metadata: &fs::Metadata) -> u64 {
+    metadata.len()
+}
+
+/// The directory, its sync mode, and the filesystem and mount options it is
+/// on.
+fn describe_dir(prefix: &str, sync_mode: SyncMode) -> BTreeMap<String, String> {
+    let mut details = BTreeMap::new();
+    details.insert("dir".to_string(), prefix.to_string());
+    details.insert("sync_mode".to_string(), sync_mode.to_string());
+    if let Some(mount) = Mount::of(Path::new(prefix)) {
+        details.insert("filesystem".to_string(), mount.fs_type);
+        details.insert("mount_point".to_string(), mount.mount_point.display().to_string());
+        details.insert("mount_source".to_string(), mount.source);
+        details.insert("mount_options".to_string(), mount.options);
+    }
+    details
+}
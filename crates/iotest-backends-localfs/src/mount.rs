@@ -0,0 +1,72 @@
+use std::{fs, path::{Path, PathBuf}};
+
+/// Mount is the filesystem a directory is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mount {
+    pub mount_point: PathBuf,
+    pub fs_type: String,
+    pub source: String,
+    /// The options of the mount and of the filesystem, e.g.
+    /// `rw,noatime,...`.
+    pub options: String,
+}
+
+impl Mount {
+    /// The mount the path is on, told by `/proc/self/mountinfo`, so only on
+    /// Linux.
+    pub fn of(path: &Path) -> Option<Mount> {
+        let path = fs::canonicalize(path).ok()?;
+        let mountinfo = fs::read_to_string("/proc/self/mountinfo").ok()?;
+        // The last mount of the longest mount point covering the path wins,
+        // as it shadows the others.
+        mountinfo.lines()
+            .filter_map(parse_line)
+            .filter(|mount| path.starts_with(&mount.mount_point))
+            .fold(None, |found: Option<Mount>, mount| match found {
+                Some(found) if found.mount_point.as_os_str().len() > mount.mount_point.as_os_str().len() => Some(found),
+                _ => Some(mount),
+            })
+    }
+}
+
+/// Parse a line of mountinfo, e.g.
+/// `36 35 98:0 / /mnt rw,noatime master:1 - ext3 /dev/root rw,errors=continue`.
+fn parse_line(line: &str) -> Option<Mount> {
+    let (mount, fs) = line.split_once(" - ")?;
+    let mount: Vec<_> = mount.split(' ').collect();
+    let fs: Vec<_> = fs.split(' ').collect();
+    let (mount_point, mount_options) = (mount.get(4)?, mount.get(5)?);
+    let (fs_type, source, super_options) = (fs.first()?, fs.get(1)?, fs.get(2).unwrap_or(&""));
+    let mut options = mount_options.to_string();
+    if !super_options.is_empty() {
+        options += ",";
+        options += super_options;
+    }
+    Some(Mount {
+        mount_point: PathBuf::from(unescape(mount_point)),
+        fs_type: fs_type.to_string(),
+        source: unescape(source),
+        options,
+    })
+}
+
+/// Undo the octal escapes of spaces and such, e.g. `\040`.
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::new();
+    let mut rest = field;
+    while let Some(idx) = rest.find('\\') {
+        unescaped += &rest[..idx];
+        let code = rest.get(idx + 1..idx + 4).and_then(|code| u8::from_str_radix(code, 8).ok());
+        match code {
+            Some(code) => {
+                unescaped.push(code as char);
+                rest = &rest[idx + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[idx + 1..];
+            }
+        }
+    }
+    unescaped + rest
+}
@@ -0,0 +1,187 @@
+use std::{fmt, fs, io, str::FromStr};
+
+use tokio::{fs::{File, OpenOptions}, io::AsyncWriteExt};
+
+/// SyncMode is how durable a write is when it returns, from handed to the
+/// page cache to on the disk. The latencies between them are the cost of
+/// durability.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Return once the write is handed to the file.
+    #[default]
+    None,
+    /// Flush the file, so the write reached the page cache.
+    Flush,
+    /// `fdatasync` the file: the data is on the disk, but maybe not all
+    /// metadata.
+    Fdatasync,
+    /// `fsync` the file: the data and the metadata are on the disk.
+    Fsync,
+    /// Open the file with `O_DSYNC` (write-through on Windows), so every
+    /// write is synced as it goes.
+    ODsync,
+}
+
+impl SyncMode {
+    pub const ALL: [SyncMode; 5] = [SyncMode::None, SyncMode::Flush, SyncMode::Fdatasync, SyncMode::Fsync, SyncMode::ODsync];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SyncMode::None => "none",
+            SyncMode::Flush => "flush",
+            SyncMode::Fdatasync => "fdatasync",
+            SyncMode::Fsync => "fsync",
+            SyncMode::ODsync => "o_dsync",
+        }
+    }
+
+    /// The options to open a file to write in the mode, creating it.
+    pub fn open_options(&self) -> fs::OpenOptions {
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create(true);
+        if *self == SyncMode::ODsync {
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_DSYNC);
+            // FILE_FLAG_WRITE_THROUGH
+            #[cfg(windows)]
+            std::os::windows::fs::OpenOptionsExt::custom_flags(&mut options, 0x8000_0000);
+        }
+        options
+    }
+
+    /// Open the file to write, truncating it.
+    pub async fn create(&self, path: &str) -> io::Result<File> {
+        let mut options = self.open_options();
+        options.truncate(true);
+        OpenOptions::from(options).open(path).await
+    }
+
+    /// Make the written file as durable as the mode asks.
+    pub async fn sync(&self, file: &mut File) -> io::Result<()> {
+        match self {
+            SyncMode::None => Ok(()),
+            // The write may still be on its way to the file until flushed.
+            SyncMode::Flush | SyncMode::ODsync => file.flush().await,
+            SyncMode::Fdatasync => file.sync_data().await,
+            SyncMode::Fsync => file.sync_all().await,
+        }
+    }
+
+    /// Make the file written at offsets as durable as the mode asks.
+    pub fn sync_positional(&self, file: &fs::File) -> io::Result<()> {
+        match self {
+            SyncMode::Fdatasync => file.sync_data(),
+            SyncMode::Fsync => file.sync_all(),
+            // Positional writes go straight to the page cache.
+            SyncMode::None | SyncMode::Flush | SyncMode::ODsync => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for SyncMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for SyncMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        SyncMode::ALL.into_iter()
+            .find(|mode| mode.name() == s)
+            .ok_or_else(|| {
+                let names: Vec<_> = SyncMode::ALL.iter().map(SyncMode::name).collect();
+                format!("unknown sync mode {}, expected one of {}", s, names.join(", "))
+            })
+    }
+}
+
+/// Allocate the blocks of the first `len` bytes of the file, growing it if
+/// shorter, so writes in them neither allocate nor fill holes. Only Linux has
+/// `fallocate`; elsewhere the file is only sized.
+pub fn allocate(file: &fs::File, len: u64) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let ret = unsafe { libc::posix_fallocate(file.as_raw_fd(), 0, len as libc::off_t) };
+        if ret == 0 {
+            return Ok(());
+        }
+    }
+    if file.metadata()?.len() < len {
+        file.set_len(len)?;
+    }
+    Ok(())
+}
+
+/// Whether `evict` can drop pages from the page cache here.
+pub const CAN_EVICT: bool = cfg!(target_os = "linux");
+
+/// Drop `len` bytes of the file from `offset` out of the page cache, writing
+/// them back first since dirty pages are kept. Only on Linux, by
+/// `posix_fadvise`.
+pub fn evict(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        file.sync_data()?;
+        let ret = unsafe { libc::posix_fadvise(file.as_raw_fd(), offset as libc::off_t, len as libc::off_t, libc::POSIX_FADV_DONTNEED) };
+        if ret != 0 {
+            return Err(io::Error::from_raw_os_error(ret));
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (file, offset, len);
+        Err(io::Error::new(io::ErrorKind::Unsupported, "evicting from the page cache is only supported on Linux"))
+    }
+}
+
+/// Whether `punch_hole` can deallocate ranges of files here.
+pub const CAN_PUNCH_HOLE: bool = cfg!(target_os = "linux");
+
+/// Deallocate `len` bytes of the file from `offset`, keeping its size, so the
+/// range reads as zeros. Only on Linux, by `fallocate` with
+/// `FALLOC_FL_PUNCH_HOLE`.
+pub fn punch_hole(file: &fs::File, offset: u64, len: u64) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        let mode = libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE;
+        let ret = unsafe { libc::fallocate(file.as_raw_fd(), mode, offset as libc::off_t, len as libc::off_t) };
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (file, offset, len);
+        Err(io::Error::new(io::ErrorKind::Unsupported, "punching holes is only supported on Linux"))
+    }
+}
+
+/// Copy the file, by a clone sharing its blocks where the filesystem can
+/// (`FICLONE` on Linux, e.g. on Btrfs and XFS), else by `copy_file_range`
+/// or by reading and writing, as `fs::copy` does.
+pub fn copy(from: &str, to: &str, sync_mode: SyncMode) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::io::AsRawFd;
+        const FICLONE: libc::c_ulong = 0x4004_9409;
+        let source = fs::File::open(from)?;
+        let mut options = sync_mode.open_options();
+        options.truncate(true);
+        let target = options.open(to)?;
+        if unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) } == 0 {
+            return sync_mode.sync_positional(&target);
+        }
+    }
+    fs::copy(from, to)?;
+    match sync_mode {
+        SyncMode::None | SyncMode::Flush => Ok(()),
+        _ => fs::File::open(to)?.sync_all(),
+    }
+}
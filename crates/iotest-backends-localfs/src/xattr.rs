@@ -0,0 +1,69 @@
+use std::io;
+
+/// Whether files have extended attributes here. Only Linux is supported.
+pub const CAN_XATTR: bool = cfg!(target_os = "linux");
+
+#[cfg(target_os = "linux")]
+fn c_string(s: &str) -> io::Result<std::ffi::CString> {
+    std::ffi::CString::new(s).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+/// Set the extended attribute of the file, by `setxattr`.
+#[cfg(target_os = "linux")]
+pub fn set(path: &str, name: &str, value: &str) -> io::Result<()> {
+    let (path, name) = (c_string(path)?, c_string(name)?);
+    let ret = unsafe { libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Get the extended attribute of the file, by `getxattr`, asking for its size
+/// first.
+#[cfg(target_os = "linux")]
+pub fn get(path: &str, name: &str) -> io::Result<String> {
+    let (path, name) = (c_string(path)?, c_string(name)?);
+    let len = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut buf = vec![0u8; len as usize];
+    let len = unsafe { libc::getxattr(path.as_ptr(), name.as_ptr(), buf.as_mut_ptr().cast(), buf.len()) };
+    if len < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    buf.truncate(len as usize);
+    String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Remove the extended attribute of the file, by `removexattr`.
+#[cfg(target_os = "linux")]
+pub fn remove(path: &str, name: &str) -> io::Result<()> {
+    let (path, name) = (c_string(path)?, c_string(name)?);
+    let ret = unsafe { libc::removexattr(path.as_ptr(), name.as_ptr()) };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn unsupported() -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, "extended attributes are only supported on Linux")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set(_path: &str, _name: &str, _value: &str) -> io::Result<()> {
+    Err(unsupported())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn get(_path: &str, _name: &str) -> io::Result<String> {
+    Err(unsupported())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn remove(_path: &str, _name: &str) -> io::Result<()> {
+    Err(unsupported())
+}
@@ -0,0 +1,287 @@
+//! The plugin protocol lets a backend be implemented in any language, as a
+//! program iotest spawns and talks with over its stdin and stdout.
+//!
+//! Every message is a JSON object on its own line. iotest sends requests:
+//!
+//! ```text
+//! {"id":1,"op":"write","key":"iotest-42-0","value":"..."}
+//! {"id":2,"op":"read","key":"iotest-42-0"}
+//! {"id":3,"op":"delete","key":"iotest-42-0"}
+//! {"id":4,"op":"size","key":"iotest-42-0"}
+//! {"id":5,"op":"list","key":"iotest-42-"}
+//! {"id":6,"op":"append","key":"iotest-42-0","value":"..."}
+//! {"id":7,"op":"capabilities","key":""}
+//! {"id":8,"op":"evict","key":"iotest-42-0"}
+//! {"id":9,"op":"copy","key":"iotest-42-0","value":"iotest-42-0.copy"}
+//! {"id":10,"op":"write_versioned","key":"iotest-42-0","value":"..."}
+//! {"id":11,"op":"read_version","key":"iotest-42-0","value":"3"}
+//! ```
+//!
+//! and the plugin answers every request with a response of the same id, in
+//! any order, since requests are issued concurrently:
+//!
+//! ```text
+//! {"id":1}
+//! {"id":2,"value":"..."}
+//! {"id":4,"size":1048576}
+//! {"id":5,"keys":["iotest-42-0","iotest-42-1"]}
+//! {"id":7,"capabilities":["delete","stat","list"]}
+//! {"id":10,"version":"3"}
+//! {"id":3,"error":"no such key","kind":"not_found"}
+//! ```
+//!
+//! An error may have a `kind` of `not_found`, `throttled` or `connection`, so
+//! iotest can tell a missing key from a failure and retry by kind; errors
+//! without it are of no particular kind. Reading a missing key must fail with
+//! `not_found`, or the reads after deletes count as failed.
+//!
+//! `capabilities` names the operations the plugin supports besides `write`
+//! and `read`, out of `delete`, `stat` (for `size`), `list`, `append`,
+//! `evict` (dropping a key from the caches before it is read back, for cold
+//! reads), `copy` (copying the key to the key in `value`, e.g. by a copy
+//! on the server) and `versions` (`write_versioned` answering the id of the
+//! version written, and `read_version` reading the version of the id in
+//! `value`); the others are skipped. A plugin answering it with an error is taken to support
+//! `delete`, `stat` and `list`, as all plugins had to before. Anything the
+//! plugin writes to stderr goes to iotest's stderr.
+
+use std::{collections::{BTreeMap, HashMap}, process::{self, Stdio}, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, OnceLock, PoisonError}};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{io::{AsyncBufReadExt, AsyncWriteExt, BufReader}, process::{Child, ChildStdin}, sync::{oneshot, Mutex as AsyncMutex}};
+
+use iotest_core::{self as tester, Capability, Error, Result};
+
+#[derive(Serialize)]
+struct Request<'a> {
+    id: u64,
+    op: &'a str,
+    key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    id: u64,
+    value: Option<String>,
+    size: Option<u64>,
+    keys: Option<Vec<String>>,
+    capabilities: Option<Vec<String>>,
+    /// The id of the version written.
+    version: Option<String>,
+    error: Option<String>,
+    /// The kind of the error, if any.
+    kind: Option<String>,
+}
+
+/// PluginClient runs a plugin program and issues operations to it.
+pub struct PluginClient {
+    conn: Arc<Connection>,
+    auto_increment: AtomicU64,
+    /// What the plugin was spawned with, to record in the report.
+    details: BTreeMap<String, String>,
+}
+
+/// Connection is the pipes to a running plugin, multiplexing requests by id.
+struct Connection {
+    /// Kept to kill the plugin when iotest is done.
+    _child: Child,
+    stdin: AsyncMutex<ChildStdin>,
+    next_id: AtomicU64,
+    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Response>>>>,
+    /// Asked by the first handler set up.
+    capabilities: OnceLock<tester::Capabilities>,
+}
+
+impl PluginClient {
+    /// Spawn the command by the shell as the plugin.
+    pub fn spawn(command: &str) -> Result<Self> {
+        Self::spawn_with_env(command, &[])
+    }
+
+    /// Spawn the command by the shell as the plugin, with the environment
+    /// variables, e.g. where the storage it talks to listens.
+    pub fn spawn_with_env(command: &str, env: &[(&str, &str)]) -> Result<Self> {
+        println!("INIT PLUGIN CLIENT");
+        println!("  COMMAND:       {}", tester::redact(command));
+        let mut child = tester::shell(command)
+            .envs(env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| Error::from_io_error(&format!("spawn {}", command), err))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // Route every response to the request waiting for it.
+        let pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Response>>>> = Arc::default();
+        let routes = pending.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<Response>(&line) {
+                    Ok(response) => {
+                        let sender = routes.lock().unwrap_or_else(PoisonError::into_inner).remove(&response.id);
+                        if let Some(sender) = sender {
+                            let _ = sender.send(response);
+                        }
+                    }
+                    Err(err) => eprintln!("PLUGIN: malformed response: {}: {}", err, line),
+                }
+            }
+            // The plugin exited, fail whatever is still waiting.
+            routes.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        });
+
+        Ok(Self {
+            conn: Arc::new(Connection {
+                _child: child,
+                stdin: AsyncMutex::new(stdin),
+                next_id: AtomicU64::new(1),
+                pending,
+                capabilities: OnceLock::new(),
+            }),
+            auto_increment: AtomicU64::new(0),
+            details: BTreeMap::new(),
+        })
+    }
+
+    /// Record a detail of how the plugin runs in the report, e.g. the proxy
+    /// it goes through.
+    pub fn with_detail(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.details.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+impl Connection {
+    async fn call(&self, op: &str, key: &str, value: Option<&str>) -> Result<Response> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut line = serde_json::to_string(&Request { id, op, key, value })
+            .map_err(|err| Error::new(format!("{} {}: serialize request: {}", op, key, err)).with_source(err))?;
+        line.push('\n');
+
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner).insert(id, sender);
+        let written = self.stdin.lock().await.write_all(line.as_bytes()).await;
+        if let Err(err) = written {
+            self.pending.lock().unwrap_or_else(PoisonError::into_inner).remove(&id);
+            return Err(Error::from_io_error(&format!("{} {}: write to plugin", op, key), err));
+        }
+        let response = receiver.await
+            .map_err(|_| Error::connection(format!("{} {}: plugin exited", op, key)))?;
+        let Some(err) = &response.error else {
+            return Ok(response);
+        };
+        let msg = format!("{} {}: {}", op, key, err);
+        Err(match response.kind.as_deref() {
+            Some("not_found") => Error::not_found(msg),
+            Some("throttled") => Error::throttled(msg),
+            Some("connection") => Error::connection(msg),
+            _ => Error::new(msg),
+        })
+    }
+}
+
+impl tester::TestClient for PluginClient {
+    fn name(&self) -> &'static str {
+        "plugin"
+    }
+
+    fn init(&self) {}
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        self.details.clone()
+    }
+
+    fn gen_unique_key(&self) -> String {
+        let idx = self.auto_increment.fetch_add(1, Ordering::Relaxed);
+        format!("iotest-{}-{}", process::id(), idx)
+    }
+
+    fn handler(&self, _ctx: &tester::HandlerContext) -> Arc<dyn tester::TestClientHandler> {
+        Arc::new(PluginClientHandler { conn: self.conn.clone() })
+    }
+}
+
+pub struct PluginClientHandler {
+    conn: Arc<Connection>,
+}
+
+#[async_trait]
+impl tester::TestClientHandler for PluginClientHandler {
+    fn capabilities(&self) -> tester::Capabilities {
+        self.conn.capabilities.get().copied().unwrap_or_else(legacy_capabilities)
+    }
+
+    async fn setup(&self) -> Result<()> {
+        if self.conn.capabilities.get().is_none() {
+            let capabilities = match self.conn.call("capabilities", "", None).await {
+                Ok(response) => response.capabilities.unwrap_or_default().iter()
+                    .filter_map(|name| Capability::ALL.into_iter().find(|capability| capability.name() == name))
+                    .collect(),
+                Err(_) => legacy_capabilities(),
+            };
+            let _ = self.conn.capabilities.set(capabilities);
+        }
+        Ok(())
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.call("write", key, Some(value)).await?;
+        Ok(())
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        self.conn.call("read", key, None).await?.value
+            .ok_or_else(|| Error::new(format!("read {}: no value in response", key)))
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.conn.call("delete", key, None).await?;
+        Ok(())
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        self.conn.call("size", key, None).await?.size
+            .ok_or_else(|| Error::new(format!("size {}: no size in response", key)))
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        self.conn.call("list", prefix, None).await?.keys
+            .ok_or_else(|| Error::new(format!("list {}: no keys in response", prefix)))
+    }
+
+    async fn append(&self, key: &str, value: &str) -> Result<()> {
+        self.conn.call("append", key, Some(value)).await?;
+        Ok(())
+    }
+
+    async fn evict(&self, key: &str) -> Result<()> {
+        self.conn.call("evict", key, None).await?;
+        Ok(())
+    }
+
+    async fn copy(&self, key: &str, to: &str) -> Result<()> {
+        self.conn.call("copy", key, Some(to)).await?;
+        Ok(())
+    }
+
+    async fn write_versioned(&self, key: &str, value: &str) -> Result<String> {
+        self.conn.call("write_versioned", key, Some(value)).await?.version
+            .ok_or_else(|| Error::new(format!("write_versioned {}: no version in response", key)))
+    }
+
+    async fn read_version(&self, key: &str, version: &str) -> Result<String> {
+        self.conn.call("read_version", key, Some(version)).await?.value
+            .ok_or_else(|| Error::new(format!("read_version {}: no value in response", key)))
+    }
+}
+
+/// What plugins support if they don't answer `capabilities`.
+fn legacy_capabilities() -> tester::Capabilities {
+    [Capability::Delete, Capability::Stat, Capability::List].into_iter().collect()
+}
@@ -0,0 +1,1273 @@
+use std::{env, net::SocketAddr, path::PathBuf, time::Duration};
+
+use clap::{builder::PossibleValuesParser, Args, Parser, Subcommand};
+
+use iotest_core::{fault::FaultConfig, network::NetworkConfig, ErrorClass, Hook, Objective, Options, RetryPolicy, Settings, Tenant};
+
+use crate::{backends, config::ConfigFile};
+
+const DEFAULT_HISTORY_DB: &str = "iotest-history.db";
+const DEFAULT_RESULTS_DIR: &str = "results";
+
+/// The sync modes of localfs, by name.
+const LOCALFS_SYNC_MODES: [&str; 5] = ["none", "flush", "fdatasync", "fsync", "o_dsync"];
+
+const EXIT_CODES: &str = "\
+Exit codes of run:
+  0    success
+  1    SLO violation
+  2    excessive errors
+  3    harness failure
+  4    cancelled by an interrupt
+  130  interrupted twice, exited without cleaning up";
+
+/// Benchmark and verify a storage backend.
+#[derive(Parser)]
+#[command(name = "iotest", version, args_conflicts_with_subcommands = true, after_help = EXIT_CODES)]
+pub struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Without a subcommand, run a test.
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+impl Cli {
+    /// The command to run, `run` if none is given.
+    pub fn command(self) -> Command {
+        self.command.unwrap_or(Command::Run(self.run))
+    }
+}
+
+/// Command is what iotest is asked to do by the command line.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run a test against a backend.
+    Run(RunArgs),
+
+    /// List the backends a test can run against.
+    ListBackends,
+
+    /// Print the summary of a test written by `run --summary`.
+    Report(ReportArgs),
+
+    /// Compare the summaries of two tests, step by step.
+    Compare(CompareArgs),
+
+    /// Show the runs appended to the results database.
+    History(HistoryArgs),
+
+    /// Probe the practical limits of a backend.
+    Probe(ProbeArgs),
+
+    /// Test that acknowledged writes survive a crash.
+    CrashTest(CrashArgs),
+
+    /// Remove the data left by runs which didn't clean up, e.g. killed ones.
+    Clean(CleanArgs),
+
+    /// Serve a REST API to start and stop runs, stream their progress and
+    /// fetch their results, and a web UI browsing the results database.
+    Serve(ServeArgs),
+
+    /// Run a test on a schedule, appending it to the results database and
+    /// alerting on regressions, to monitor a storage continuously.
+    Daemon(DaemonArgs),
+
+    /// Serve the reference key-value store of the kv backend, keeping the
+    /// keys in memory.
+    #[cfg(feature = "kv")]
+    KvServer(KvServerArgs),
+
+    /// The child process of `crash-test`, not for users.
+    #[command(hide = true)]
+    CrashWriter(CrashWriterArgs),
+}
+
+/// RunArgs is the arguments of `iotest run`.
+#[derive(Args)]
+pub struct RunArgs {
+    /// Read the options from the TOML file. Flags override the file.
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// The backends to test [default: localfs]. Given several, the same
+    /// workload runs against each of them, and they are compared.
+    #[arg(long = "backend", value_name = "BACKEND", value_delimiter = ',', value_parser = PossibleValuesParser::new(backends::builtin().names()))]
+    pub backends: Option<Vec<String>>,
+
+    /// The directory localfs writes to, e.g. on a NFS share or a NVMe mount
+    /// [default: <TEMP>/iotest_<PID>/].
+    #[arg(long, visible_alias = "localfs-dir", value_name = "DIR")]
+    pub localfs_prefix: Option<String>,
+
+    /// How durable localfs makes every write before it returns, of none,
+    /// flush, fdatasync, fsync and o_dsync [default: none]. Given several,
+    /// localfs is tested in every mode, and they are compared.
+    #[arg(long, value_name = "MODES", value_delimiter = ',', value_parser = PossibleValuesParser::new(LOCALFS_SYNC_MODES))]
+    pub localfs_sync: Option<Vec<String>>,
+
+    /// Allocate every localfs file to its final size before writing it, to
+    /// measure writes without the allocation.
+    #[arg(long)]
+    pub localfs_fallocate: bool,
+
+    /// Create and allocate the files of the first N localfs keys up front,
+    /// so writing them overwrites instead of allocating.
+    #[arg(long, value_name = "N")]
+    pub localfs_preallocate: Option<u64>,
+
+    /// Spread localfs files over directories N wide, named by a hash of the
+    /// key, e.g. `ab/cd/<KEY>`, instead of writing all in one directory.
+    #[arg(long, value_name = "N")]
+    pub localfs_fan_out: Option<u32>,
+
+    /// How deep the directories of `--localfs-fan-out` go [default: 2].
+    #[arg(long, value_name = "N", requires = "localfs_fan_out")]
+    pub localfs_fan_out_levels: Option<u32>,
+
+    /// Keep up to N localfs files open, writing and reading them at offsets
+    /// instead of opening them for every operation, to measure without the
+    /// opens and closes.
+    #[arg(long, value_name = "N")]
+    pub localfs_keep_open: Option<usize>,
+
+    /// Write keys at sequential or random offsets of one preallocated file
+    /// instead of as files of their own, like fio.
+    #[arg(long, value_name = "PATTERN", value_parser = PossibleValuesParser::new(["sequential", "random"]))]
+    pub localfs_pattern: Option<String>,
+
+    /// The size of the file of `--localfs-pattern` [default: 1G].
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub localfs_file_size: Option<usize>,
+
+    /// The size of every IO of `--localfs-pattern`, which must hold a value
+    /// [default: the payload length with its headers, aligned to 4K].
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub localfs_io_size: Option<usize>,
+
+    /// The most IOs of `--localfs-pattern` in flight, the rest queue
+    /// [default: unbounded].
+    #[arg(long, value_name = "N")]
+    pub localfs_queue_depth: Option<usize>,
+
+    /// The command of the plugin backend, run by the shell.
+    #[arg(long, value_name = "COMMAND")]
+    pub plugin_command: Option<String>,
+
+    /// The address of the kv server, e.g. one run by `iotest kv-server` on
+    /// another host [default: a server in the process].
+    #[arg(long, value_name = "HOST:PORT")]
+    pub kv_addr: Option<String>,
+
+    /// Connect to a network backend over TLS, trusting the web PKI roots
+    /// unless `--tls-ca` is given. Implied by the other `--tls-*` flags.
+    #[arg(long)]
+    pub tls: bool,
+
+    /// A PEM bundle of the CAs to trust, e.g. of a self-signed server.
+    #[arg(long, value_name = "PATH")]
+    pub tls_ca: Option<PathBuf>,
+
+    /// A PEM certificate chain to authenticate as a client with, and its key.
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Accept any certificate of the server, without verifying it.
+    #[arg(long)]
+    pub tls_insecure: bool,
+
+    /// The name to verify the server as [default: the host connected to].
+    #[arg(long, value_name = "NAME")]
+    pub tls_server_name: Option<String>,
+
+    /// A credential the backend authenticates with, e.g. `password`, resolved
+    /// from `IOTEST_<NAME>`, the credentials file, the instance metadata or a
+    /// prompt, in this order, unless given by `--backend-opt`. Can be given
+    /// many times. A plugin gets it as `IOTEST_<NAME>`.
+    #[arg(long = "credential", value_name = "NAME")]
+    pub credentials: Vec<String>,
+
+    /// The file of the credentials, of `[profile]` sections of
+    /// `name = value` lines [default: ~/.config/iotest/credentials].
+    #[arg(long, value_name = "PATH")]
+    pub credentials_file: Option<PathBuf>,
+
+    /// The profile of the credentials file [default: $IOTEST_PROFILE or
+    /// default].
+    #[arg(long, value_name = "NAME")]
+    pub credentials_profile: Option<String>,
+
+    /// Fetch the credentials of the role of the cloud instance from its
+    /// metadata service.
+    #[arg(long)]
+    pub credentials_imds: bool,
+
+    /// Ask for the credentials found nowhere else on the terminal.
+    #[arg(long)]
+    pub credentials_prompt: bool,
+
+    /// An endpoint of the backend, e.g. a region, as `NAME=HOST:PORT`. Can be
+    /// given many times, to run the same workload against every endpoint in
+    /// turn and compare their latencies. The kv backend connects to it, and
+    /// a plugin gets it as `IOTEST_ENDPOINT` and `IOTEST_REGION`.
+    #[arg(long = "endpoint", value_name = "NAME=ADDR", value_parser = parse_pair)]
+    pub endpoints: Vec<(String, String)>,
+
+    /// The HTTP(S) or SOCKS proxy to reach the storage of an HTTP-based
+    /// backend and the webhooks through, e.g. `http://proxy:3128` or
+    /// `socks5h://proxy:1080` [default: $HTTPS_PROXY, $ALL_PROXY or
+    /// $HTTP_PROXY]. A plugin gets it as `HTTP_PROXY`, `HTTPS_PROXY` and
+    /// `ALL_PROXY`.
+    #[arg(long, value_name = "URL")]
+    pub proxy: Option<String>,
+
+    /// The hosts to reach directly rather than by the proxy, comma
+    /// separated, like `NO_PROXY` [default: $NO_PROXY].
+    #[arg(long, value_name = "HOSTS")]
+    pub no_proxy: Option<String>,
+
+    /// A setting of the backend, e.g. an endpoint or a credential. Can be
+    /// given many times. Settings of `env:NAME` are environment variables of
+    /// the plugin.
+    #[arg(long = "backend-opt", value_name = "KEY=VALUE", value_parser = parse_pair)]
+    pub backend_opts: Vec<(String, String)>,
+
+    /// Start the storage a plugin talks to in a container before the test,
+    /// wait until it listens, and remove it after.
+    #[arg(long, value_name = "RUNTIME", value_parser = ["docker"], requires = "provision_service")]
+    pub provision: Option<String>,
+
+    /// The storage to provision, of redis, minio and postgres. The plugin
+    /// gets its address as `IOTEST_BACKEND_ADDR`, and its credentials as the
+    /// container does, e.g. `POSTGRES_PASSWORD`.
+    #[arg(long, value_name = "SERVICE", value_parser = PossibleValuesParser::new(crate::provision::SERVICES))]
+    pub provision_service: Option<String>,
+
+    /// The image of the service to provision [default: by the service].
+    #[arg(long, value_name = "IMAGE")]
+    pub provision_image: Option<String>,
+
+    /// The length of the value written to every key [default: 16777216].
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub payload_len: Option<usize>,
+
+    /// Draw the length of every value between the payload length and BYTES,
+    /// log-uniformly, and report the latencies by size class.
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub payload_len_max: Option<usize>,
+
+    /// Seed the randomness, so runs with the same seed issue the same
+    /// operations [default: random, printed].
+    #[arg(long, value_name = "SEED")]
+    pub seed: Option<u64>,
+
+    /// The QPS of every step, in order [default: 5,10,20,50,100,200,500,1000].
+    #[arg(long = "qps", value_name = "QPS", value_delimiter = ',')]
+    pub qps_steps: Option<Vec<u64>>,
+
+    /// Sweep the payload lengths, e.g. `4K,64K,1M,16M`, a step per length at
+    /// the first QPS, and chart the latency and bandwidth by length.
+    #[arg(long, value_name = "BYTES", value_delimiter = ',', value_parser = parse_len)]
+    pub size_sweep: Option<Vec<usize>>,
+
+    /// Sweep closed-loop workers 1, 2, 4, ... up to N instead of the QPS, a
+    /// step per count, every worker issuing its next request once its last
+    /// one finished. Charts the throughput and the p99 by workers, and
+    /// reports the knee where the backend saturates.
+    #[arg(long, value_name = "N", conflicts_with_all = ["size_sweep", "calibrate"])]
+    pub concurrency_sweep: Option<usize>,
+
+    /// How long every step lasts [default: 30].
+    #[arg(long = "duration-secs", value_name = "SECS", value_parser = parse_secs)]
+    pub step_duration: Option<Duration>,
+
+    /// Warm up at the first QPS for so long before measuring.
+    #[arg(long = "warm-up-secs", value_name = "SECS", value_parser = parse_secs)]
+    pub warm_up: Option<Duration>,
+
+    /// Run so many independent key namespaces concurrently, each at the QPS
+    /// of the step, like applications sharing the backend [default: 1].
+    #[arg(long, value_name = "N")]
+    pub namespaces: Option<usize>,
+
+    /// Run the tenant in a namespace of its own, at its own QPS, payload
+    /// length and reads of every key, those of the step if not given.
+    /// Repeat for every tenant.
+    #[arg(long = "tenant", value_name = "NAME[:qps=N,len=BYTES,reads=N]", value_parser = parse_tenant, conflicts_with = "namespaces")]
+    pub tenants: Vec<Tenant>,
+
+    /// Tag the ratio of requests as high priority, issued by handlers of
+    /// their own which may hint it to the storage, and report their latency
+    /// apart from the bulk.
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub priority_ratio: Option<f64>,
+
+    /// How many handlers (e.g. connections) requests of a step share
+    /// [default: 1].
+    #[arg(long, value_name = "N")]
+    pub pool_size: Option<usize>,
+
+    /// The worker threads of the runtime issuing requests [default: the
+    /// number of CPUs, or of `--cpus`].
+    #[arg(long, value_name = "N")]
+    pub worker_threads: Option<usize>,
+
+    /// The max threads of the blocking pool, e.g. for file IO [default: 512].
+    #[arg(long, value_name = "N")]
+    pub max_blocking_threads: Option<usize>,
+
+    /// Pin the threads of the runtime to the CPUs, one each in turn, so they
+    /// don't compete with the backend elsewhere, e.g. `--cpus 0,1`.
+    #[arg(long, value_name = "CPUS", value_delimiter = ',')]
+    pub cpus: Option<Vec<usize>>,
+
+    /// Non-interactive mode: no progress bar, write the summary and exit with a
+    /// meaningful code.
+    #[arg(long)]
+    pub ci: bool,
+
+    /// Put the charts, the summary and a manifest of every backend's run into
+    /// a directory of its own under this one, named
+    /// `<backend>-<timestamp>[-<label>]`, so runs never clobber each other
+    /// [default: results].
+    #[arg(long, value_name = "DIR")]
+    pub results_dir: Option<PathBuf>,
+
+    /// A name for the run, appended to the names of its directories.
+    #[arg(long, value_name = "NAME")]
+    pub label: Option<String>,
+
+    /// Write the summary as JSON to the path. Defaults to `iotest-summary.json`
+    /// in CI mode, and to `summary.json` in the run directory otherwise.
+    #[arg(long, value_name = "PATH")]
+    pub summary: Option<PathBuf>,
+
+    /// Checkpoint the run to the path after every finished step, and resume
+    /// an interrupted run from there, with the same flags. The data of an
+    /// interrupted run is kept to be resumed.
+    #[arg(long, value_name = "PATH")]
+    pub checkpoint: Option<PathBuf>,
+
+    /// Write every operation of every request to the path as CSV, with its
+    /// timestamp, key, length, latency and outcome.
+    #[arg(long, value_name = "PATH")]
+    pub request_log: Option<PathBuf>,
+
+    /// Write every request and the per-second aggregates as Parquet files into
+    /// the directory. Needs iotest built with the `parquet` feature.
+    #[arg(long, value_name = "DIR")]
+    pub parquet_dir: Option<PathBuf>,
+
+    /// Also write the charts as an interactive HTML page, `report.html` in
+    /// the images directory, to zoom into the tails.
+    #[arg(long)]
+    pub html_charts: bool,
+
+    /// Profile the CPU of iotest through every step, writing a flamegraph
+    /// per step into `profiles/` in the run directory, to tell whether iotest
+    /// itself keeps up at high rates. Needs iotest built with the `profile`
+    /// feature.
+    #[arg(long)]
+    pub profile: bool,
+
+    /// Where to draw the charts [default: `images/` in the run directory].
+    #[arg(long, value_name = "DIR")]
+    pub images_dir: Option<PathBuf>,
+
+    /// Cancel operations taking longer than this, counting them as timeouts.
+    #[arg(long = "timeout-ms", value_name = "MS", value_parser = parse_millis)]
+    pub timeout: Option<Duration>,
+
+    /// Retry failed operations up to so many times [default: 2 if any retry
+    /// option is given].
+    #[arg(long, value_name = "N")]
+    pub retries: Option<u32>,
+
+    /// The backoff before the first retry, doubled for every retry after
+    /// [default: 10].
+    #[arg(long = "retry-backoff-ms", value_name = "MS", value_parser = parse_millis)]
+    pub retry_backoff: Option<Duration>,
+
+    /// The max backoff between retries [default: 1000].
+    #[arg(long = "retry-max-backoff-ms", value_name = "MS", value_parser = parse_millis)]
+    pub retry_max_backoff: Option<Duration>,
+
+    /// Randomly shorten backoffs by up to the ratio [default: 0.5].
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub retry_jitter: Option<f64>,
+
+    /// The error classes to retry, of not_found, timeout, throttled,
+    /// connection, io and other [default: all but not_found].
+    #[arg(long, value_name = "CLASSES", value_delimiter = ',', value_parser = parse_error_class)]
+    pub retry_on: Option<Vec<ErrorClass>>,
+
+    /// Skip verifying the length and checksum of read values.
+    #[arg(long)]
+    pub no_verify: bool,
+
+    /// Check the stored size of every written key, counting partial writes.
+    #[arg(long)]
+    pub check_size: bool,
+
+    /// Evict every written key from the caches of the backend before reading
+    /// it, so reads measure the device rather than RAM.
+    #[arg(long)]
+    pub cold_reads: bool,
+
+    /// Drop the page cache before every step (root required).
+    #[arg(long)]
+    pub drop_caches: bool,
+
+    /// Drop the page cache before every step by running the command instead,
+    /// e.g. a helper allowed by sudo.
+    #[arg(long, value_name = "COMMAND")]
+    pub drop_caches_command: Option<String>,
+
+    /// Run every backend twice, cold then warm, and compare the reads side by
+    /// side. The cold run evicts keys before reading them, and drops the page
+    /// cache if asked; the warm one reads them straight after the write.
+    #[arg(long)]
+    pub cold_warm: bool,
+
+    /// Set an extended attribute of BYTES on every written key, then get and
+    /// remove it, measuring the latencies of each.
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub xattr_len: Option<usize>,
+
+    /// Punch a hole of up to BYTES in the middle of every read key, then read
+    /// it back over the hole, measuring both.
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub hole_len: Option<usize>,
+
+    /// Copy every read key to another one, by a clone or a server-side copy
+    /// where the backend can, measuring the copy.
+    #[arg(long)]
+    pub copy: bool,
+
+    /// Write every key as a version and read the version back by its id
+    /// after overwriting the key, measuring the versioned read (as
+    /// read_version) against the reads of the latest, where the backend
+    /// keeps versions.
+    #[arg(long)]
+    pub versioned: bool,
+
+    /// Measure the phases of connecting to a network backend as metrics of
+    /// their own: dns, connect, tls_handshake and first_byte. The kv backend
+    /// connects for every request with `--backend-opt reuse=false`.
+    #[arg(long)]
+    pub connection_phases: bool,
+
+    /// Sample the space the data takes on the storage every MS milliseconds,
+    /// and chart it next to the bytes written and live. The localfs backend
+    /// counts the blocks allocated to its files, like `du`.
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub space_interval: Option<Duration>,
+
+    /// Check the health of the backend every MS milliseconds in parallel
+    /// with the load, for its availability and the outages through the test.
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub health_interval: Option<Duration>,
+
+    /// Probe how long written keys take to be readable, giving up after MS
+    /// milliseconds.
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub probe_visibility: Option<Duration>,
+
+    /// Read keys on another handler (e.g. another connection) than the one
+    /// which wrote them, with a pool of 2 handlers at least. With
+    /// `--probe-visibility`, measures how long writes take to be visible
+    /// beyond their connection.
+    #[arg(long)]
+    pub cross_read: bool,
+
+    /// Probe how long deleted keys stay readable, for up to MS milliseconds.
+    #[arg(long, value_name = "MS", value_parser = parse_millis)]
+    pub probe_delete_visibility: Option<Duration>,
+
+    /// Let requests write and read a pool of N shared keys, reporting reads
+    /// going back in versions.
+    #[arg(long, value_name = "N")]
+    pub shared_keys: Option<usize>,
+
+    /// Keep the ratio of keys until the end, then re-read and validate all of
+    /// them.
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub scrub: Option<f64>,
+
+    /// Only write while measuring, then read back, verify and delete all keys
+    /// written after every step.
+    #[arg(long)]
+    pub verify_at_end: bool,
+
+    /// Measure the overhead of iotest itself for so long at every QPS before
+    /// the test, against a backend doing nothing, and report it.
+    #[arg(long = "calibrate-secs", value_name = "SECS", value_parser = parse_secs)]
+    pub calibrate: Option<Duration>,
+
+    /// Subtract the calibrated overhead from the p50 and p99 latencies.
+    #[arg(long, requires = "calibrate")]
+    pub subtract_overhead: bool,
+
+    /// Keep the data the test wrote instead of removing it at the end.
+    #[arg(long)]
+    pub keep_data: bool,
+
+    /// Max ratio of failed operations per step [default: 0].
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub max_error_rate: Option<f64>,
+
+    /// Max p99 latency of every operation.
+    #[arg(long = "slo-p99-ms", value_name = "MS", value_parser = parse_millis)]
+    pub slo_p99: Option<Duration>,
+
+    /// Report seconds whose p99 is over FACTOR (e.g. 10) times the median of
+    /// the operation as stalls, like compactions or GC pauses.
+    #[arg(long, value_name = "FACTOR")]
+    pub stall_factor: Option<f64>,
+
+    /// The seconds in a row a stall lasts at least [default: 3].
+    #[arg(long, value_name = "SECS")]
+    pub stall_secs: Option<u64>,
+
+    /// An objective every step must keep, e.g. `write.p99 < 20ms` or
+    /// `error_rate < 0.1%`. Can be given many times. Breaking one fails the
+    /// run with a nonzero exit status.
+    #[arg(long = "slo", value_name = "OBJECTIVE")]
+    pub objectives: Vec<Objective>,
+
+    /// Count a p99 latency so many percent above the mean of the runs before
+    /// in the results database, of the same backend, QPS and metric, as a
+    /// regression.
+    #[arg(long, value_name = "PERCENT")]
+    pub regression: Option<f64>,
+
+    /// POST a notification carrying the summary as JSON to the URL when the
+    /// test of a backend finishes, e.g. a Slack incoming webhook.
+    #[arg(long = "webhook", value_name = "URL")]
+    pub webhooks: Vec<String>,
+
+    /// Only notify the webhooks when the SLO is violated, the errors are
+    /// excessive or a latency regressed.
+    #[arg(long)]
+    pub webhook_only_violations: bool,
+
+    /// Run COMMAND by the shell at SECS seconds into the test, marked on the time
+    /// series chart.
+    #[arg(long = "hook", value_name = "SECS:COMMAND", value_parser = parse_hook)]
+    pub hooks: Vec<Hook>,
+
+    /// Stream per-second metrics as server-sent events on the address.
+    #[arg(long, value_name = "ADDR")]
+    pub live: Option<SocketAddr>,
+
+    /// Export Prometheus metrics on the address.
+    #[arg(long, value_name = "ADDR")]
+    pub prometheus: Option<SocketAddr>,
+
+    /// Where to write the Grafana dashboard for the Prometheus metrics
+    /// [default: iotest-grafana-dashboard.json].
+    #[arg(long, value_name = "PATH")]
+    pub grafana_dashboard: Option<PathBuf>,
+
+    /// Append the run to the results database at the path (e.g.
+    /// iotest-history.db).
+    #[arg(long, value_name = "PATH")]
+    pub history_db: Option<PathBuf>,
+
+    /// Inject MS milliseconds of latency into every operation.
+    #[arg(long = "fault-latency-ms", value_name = "MS", value_parser = parse_millis)]
+    pub fault_latency: Option<Duration>,
+
+    /// Inject up to MS milliseconds of random latency more.
+    #[arg(long = "fault-jitter-ms", value_name = "MS", value_parser = parse_millis)]
+    pub fault_jitter: Option<Duration>,
+
+    /// Fail the ratio of operations.
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub fault_error_rate: Option<f64>,
+
+    /// Store only a half of the value in the ratio of writes.
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub fault_partial_write_rate: Option<f64>,
+
+    /// Simulate a network of the round trip time in milliseconds between
+    /// iotest and the backend, e.g. to another region.
+    #[arg(long = "net-rtt-ms", value_name = "MS", value_parser = parse_millis)]
+    pub net_rtt: Option<Duration>,
+
+    /// Delay each way of the simulated network by up to MS milliseconds more.
+    #[arg(long = "net-jitter-ms", value_name = "MS", value_parser = parse_millis)]
+    pub net_jitter: Option<Duration>,
+
+    /// Carry so many bytes per second each way over the simulated network,
+    /// e.g. `100M`, shared by all requests.
+    #[arg(long, value_name = "BYTES", value_parser = parse_len)]
+    pub net_bandwidth: Option<usize>,
+
+    /// Delay the ratio of operations by a retransmission, as if they lost a
+    /// packet.
+    #[arg(long, value_name = "RATIO", value_parser = parse_ratio)]
+    pub net_loss_rate: Option<f64>,
+
+    /// Compress values on the client before writing them, and decompress
+    /// them after reading, measuring the time spent on it as compress and
+    /// decompress. Needs iotest built with the `compression` feature.
+    #[arg(long, value_name = "CODEC", value_parser = PossibleValuesParser::new(["zstd", "lz4"]))]
+    pub compression: Option<String>,
+
+    /// The level of `--compression`: 1 to 22 for zstd, 1 to 12 for lz4
+    /// [default: the codec's].
+    #[arg(long, value_name = "LEVEL", requires = "compression")]
+    pub compression_level: Option<i32>,
+}
+
+impl RunArgs {
+    /// The backends to test, in order.
+    pub fn backends(&self) -> Vec<String> {
+        self.backends.clone()
+            .unwrap_or_else(|| backends::builtin().default_name().into_iter().map(String::from).collect())
+    }
+
+    /// The backends to test, in order, localfs once per sync mode.
+    pub fn targets(&self) -> Vec<Target> {
+        let mut targets = vec![];
+        for backend in self.backends() {
+            let settings = self.settings();
+            match &self.localfs_sync {
+                Some(modes) if backend == "localfs" && modes.len() > 1 => {
+                    for mode in modes {
+                        let mut settings = settings.clone();
+                        settings.insert("sync".to_string(), mode.clone());
+                        targets.push(Target { label: format!("{}-{}", backend, mode), backend: backend.clone(), settings, cold: None });
+                    }
+                }
+                _ => targets.push(Target { label: backend.clone(), backend, settings, cold: None }),
+            }
+        }
+        let targets = self.by_endpoint(targets);
+        if !self.cold_warm {
+            return targets;
+        }
+        // Every target cold then warm, in pairs.
+        let mut by_cache = vec![];
+        for target in targets {
+            for (state, cold) in [("cold", true), ("warm", false)] {
+                by_cache.push(Target { label: format!("{}-{}", target.label, state), cold: Some(cold), ..target.clone() });
+            }
+        }
+        by_cache
+    }
+
+    /// Every target against every endpoint, if any.
+    fn by_endpoint(&self, targets: Vec<Target>) -> Vec<Target> {
+        if self.endpoints.is_empty() {
+            return targets;
+        }
+        let mut by_endpoint = vec![];
+        for target in targets {
+            for (name, addr) in &self.endpoints {
+                let mut settings = target.settings.clone();
+                settings.insert("addr".to_string(), addr.clone());
+                settings.insert("region".to_string(), name.clone());
+                settings.insert("env:IOTEST_ENDPOINT".to_string(), addr.clone());
+                settings.insert("env:IOTEST_REGION".to_string(), name.clone());
+                by_endpoint.push(Target { label: format!("{}-{}", target.label, name), backend: target.backend.clone(), settings, cold: None });
+            }
+        }
+        by_endpoint
+    }
+
+    /// The settings to create the backend client from.
+    pub fn settings(&self) -> Settings {
+        let mut settings = Settings::new();
+        if let Some(prefix) = &self.localfs_prefix {
+            settings.insert("prefix".to_string(), prefix.clone());
+        }
+        if let Some([mode]) = self.localfs_sync.as_deref() {
+            settings.insert("sync".to_string(), mode.clone());
+        }
+        if self.localfs_fallocate {
+            settings.insert("fallocate".to_string(), "true".to_string());
+        }
+        if let Some(count) = self.localfs_preallocate {
+            settings.insert("preallocate".to_string(), count.to_string());
+        }
+        if let Some(fan_out) = self.localfs_fan_out {
+            settings.insert("fan_out".to_string(), fan_out.to_string());
+        }
+        if let Some(levels) = self.localfs_fan_out_levels {
+            settings.insert("fan_out_levels".to_string(), levels.to_string());
+        }
+        if let Some(max_open) = self.localfs_keep_open {
+            settings.insert("keep_open".to_string(), max_open.to_string());
+        }
+        if let Some(pattern) = &self.localfs_pattern {
+            settings.insert("pattern".to_string(), pattern.clone());
+        }
+        if self.localfs_pattern.is_some() || self.localfs_preallocate.is_some() {
+            // Sized by the longest value written.
+            let payload_len = self.size_sweep.iter().flatten().copied().max()
+                .or(self.payload_len_max)
+                .or(self.payload_len)
+                .unwrap_or(Options::default().payload_len);
+            settings.insert("payload_len".to_string(), payload_len.to_string());
+        }
+        if let Some(file_size) = self.localfs_file_size {
+            settings.insert("file_size".to_string(), file_size.to_string());
+        }
+        if let Some(io_size) = self.localfs_io_size {
+            settings.insert("io_size".to_string(), io_size.to_string());
+        }
+        if let Some(queue_depth) = self.localfs_queue_depth {
+            settings.insert("queue_depth".to_string(), queue_depth.to_string());
+        }
+        if let Some(command) = &self.plugin_command {
+            settings.insert("command".to_string(), command.clone());
+        }
+        if let Some(addr) = &self.kv_addr {
+            settings.insert("addr".to_string(), addr.clone());
+        }
+        if self.tls {
+            settings.insert("tls".to_string(), "true".to_string());
+        }
+        if let Some(path) = &self.tls_ca {
+            settings.insert("tls_ca".to_string(), path.display().to_string());
+        }
+        if let (Some(cert), Some(key)) = (&self.tls_cert, &self.tls_key) {
+            settings.insert("tls_cert".to_string(), cert.display().to_string());
+            settings.insert("tls_key".to_string(), key.display().to_string());
+        }
+        if self.tls_insecure {
+            settings.insert("tls_insecure".to_string(), "true".to_string());
+        }
+        if let Some(name) = &self.tls_server_name {
+            settings.insert("tls_server_name".to_string(), name.clone());
+        }
+        if !self.credentials.is_empty() {
+            settings.insert("credentials".to_string(), self.credentials.join(","));
+        }
+        if let Some(path) = &self.credentials_file {
+            settings.insert("credentials_file".to_string(), path.display().to_string());
+        }
+        if let Some(profile) = &self.credentials_profile {
+            settings.insert("credentials_profile".to_string(), profile.clone());
+        }
+        if self.credentials_imds {
+            settings.insert("credentials_imds".to_string(), "true".to_string());
+        }
+        if self.credentials_prompt {
+            settings.insert("credentials_prompt".to_string(), "true".to_string());
+        }
+        if let Some(url) = &self.proxy {
+            settings.insert("proxy".to_string(), url.clone());
+        }
+        if let Some(hosts) = &self.no_proxy {
+            settings.insert("no_proxy".to_string(), hosts.clone());
+        }
+        settings.extend(self.backend_opts.iter().cloned());
+        settings
+    }
+
+    /// Fill the options not given by flags from the config file.
+    pub fn merge(&mut self, file: ConfigFile) -> Result<(), String> {
+        let ConfigFile { backend, workload, load, runtime, slo, output } = file;
+        if let Some(name) = &backend.name {
+            if !backends::builtin().names().contains(&name.as_str()) {
+                return Err(format!("backend.name: unknown backend: {}", name));
+            }
+        }
+        self.backends = self.backends.take().or(backend.name.map(|name| vec![name]));
+        self.localfs_prefix = self.localfs_prefix.take().or(backend.prefix);
+        if let Some(mode) = backend.sync.iter().flatten().find(|mode| !LOCALFS_SYNC_MODES.contains(&mode.as_str())) {
+            return Err(format!("backend.sync: unknown sync mode: {}", mode));
+        }
+        self.localfs_sync = self.localfs_sync.take().or(backend.sync);
+        self.localfs_fallocate |= backend.fallocate.unwrap_or(false);
+        self.localfs_preallocate = self.localfs_preallocate.or(backend.preallocate);
+        self.localfs_fan_out = self.localfs_fan_out.or(backend.fan_out);
+        self.localfs_fan_out_levels = self.localfs_fan_out_levels.or(backend.fan_out_levels);
+        self.localfs_keep_open = self.localfs_keep_open.or(backend.keep_open);
+        if let Some(pattern) = &backend.pattern {
+            if pattern != "sequential" && pattern != "random" {
+                return Err(format!("backend.pattern: unknown pattern: {}", pattern));
+            }
+        }
+        self.localfs_pattern = self.localfs_pattern.take().or(backend.pattern);
+        self.localfs_file_size = self.localfs_file_size.or(backend.file_size);
+        self.localfs_io_size = self.localfs_io_size.or(backend.io_size);
+        self.localfs_queue_depth = self.localfs_queue_depth.or(backend.queue_depth);
+        self.plugin_command = self.plugin_command.take().or(backend.command);
+        self.kv_addr = self.kv_addr.take().or(backend.addr);
+        if self.endpoints.is_empty() {
+            self.endpoints = backend.endpoints.into_iter().collect();
+        }
+        self.tls |= backend.tls.unwrap_or(false);
+        self.tls_ca = self.tls_ca.take().or(backend.tls_ca);
+        if self.tls_cert.is_none() {
+            self.tls_cert = backend.tls_cert;
+            self.tls_key = backend.tls_key;
+        }
+        self.tls_insecure |= backend.tls_insecure.unwrap_or(false);
+        self.tls_server_name = self.tls_server_name.take().or(backend.tls_server_name);
+        let mut credentials = backend.credentials.unwrap_or_default();
+        credentials.append(&mut self.credentials);
+        self.credentials = credentials;
+        self.credentials_file = self.credentials_file.take().or(backend.credentials_file);
+        self.credentials_profile = self.credentials_profile.take().or(backend.credentials_profile);
+        self.credentials_imds |= backend.credentials_imds.unwrap_or(false);
+        self.credentials_prompt |= backend.credentials_prompt.unwrap_or(false);
+        self.proxy = self.proxy.take().or(backend.proxy);
+        self.no_proxy = self.no_proxy.take().or(backend.no_proxy);
+        // Flags come last, so they override the file.
+        let mut backend_opts: Vec<_> = backend.settings.into_iter().collect();
+        backend_opts.append(&mut self.backend_opts);
+        self.backend_opts = backend_opts;
+
+        self.payload_len = self.payload_len.or(workload.payload_len);
+        self.payload_len_max = self.payload_len_max.or(workload.payload_len_max);
+        self.seed = self.seed.or(workload.seed);
+        self.no_verify |= workload.verify == Some(false);
+        self.check_size |= workload.check_size.unwrap_or(false);
+        self.cold_reads |= workload.cold_reads.unwrap_or(false);
+        self.xattr_len = self.xattr_len.or(workload.xattr_len);
+        self.hole_len = self.hole_len.or(workload.hole_len);
+        self.copy |= workload.copy.unwrap_or(false);
+        self.versioned |= workload.versioned.unwrap_or(false);
+        self.connection_phases |= workload.connection_phases.unwrap_or(false);
+        self.drop_caches |= workload.drop_caches.unwrap_or(false);
+        self.drop_caches_command = self.drop_caches_command.take().or(workload.drop_caches_command);
+        self.cold_warm |= workload.cold_warm.unwrap_or(false);
+        self.shared_keys = self.shared_keys.or(workload.shared_keys);
+        self.scrub = self.scrub.or(config_ratio("workload.scrub", workload.scrub)?);
+        self.verify_at_end |= workload.verify_at_end.unwrap_or(false);
+        self.cross_read |= workload.cross_read.unwrap_or(false);
+        self.space_interval = self.space_interval.or(workload.space_interval_ms.map(Duration::from_millis));
+        self.health_interval = self.health_interval.or(workload.health_interval_ms.map(Duration::from_millis));
+        self.size_sweep = self.size_sweep.take().or(workload.size_sweep);
+        self.concurrency_sweep = self.concurrency_sweep.or(load.concurrency_sweep);
+
+        self.qps_steps = self.qps_steps.take().or(load.qps);
+        self.step_duration = self.step_duration.or(load.duration_secs.map(Duration::from_secs));
+        self.warm_up = self.warm_up.or(load.warm_up_secs.map(Duration::from_secs));
+        self.timeout = self.timeout.or(load.timeout_ms.map(Duration::from_millis));
+        self.retries = self.retries.or(load.retries);
+        self.pool_size = self.pool_size.or(load.pool_size);
+        self.namespaces = self.namespaces.or(load.namespaces);
+        self.priority_ratio = self.priority_ratio.or(config_ratio("load.priority_ratio", load.priority_ratio)?);
+        if self.tenants.is_empty() {
+            for tenant in load.tenants {
+                self.tenants.push(parse_tenant(&tenant)?);
+            }
+        }
+        if self.hooks.is_empty() {
+            for hook in load.hooks {
+                self.hooks.push(parse_hook(&hook)?);
+            }
+        }
+
+        self.worker_threads = self.worker_threads.or(runtime.worker_threads);
+        self.max_blocking_threads = self.max_blocking_threads.or(runtime.max_blocking_threads);
+        self.cpus = self.cpus.take().or(runtime.cpus);
+
+        self.max_error_rate = self.max_error_rate.or(config_ratio("slo.max_error_rate", slo.max_error_rate)?);
+        self.slo_p99 = self.slo_p99.or(slo.p99_ms.map(Duration::from_millis));
+        self.stall_factor = self.stall_factor.or(slo.stall_factor);
+        self.stall_secs = self.stall_secs.or(slo.stall_secs);
+        for objective in &slo.objectives {
+            self.objectives.push(objective.parse().map_err(|err| format!("slo.objectives: {}", err))?);
+        }
+        self.regression = self.regression.or(slo.regression_percent);
+
+        self.results_dir = self.results_dir.take().or(output.results_dir);
+        self.label = self.label.take().or(output.label);
+        self.summary = self.summary.take().or(output.summary);
+        self.checkpoint = self.checkpoint.take().or(output.checkpoint);
+        self.request_log = self.request_log.take().or(output.request_log);
+        self.parquet_dir = self.parquet_dir.take().or(output.parquet_dir);
+        self.images_dir = self.images_dir.take().or(output.images_dir);
+        self.html_charts |= output.html_charts.unwrap_or(false);
+        self.profile |= output.profile.unwrap_or(false);
+        self.history_db = self.history_db.take().or(output.history_db);
+        if self.webhooks.is_empty() {
+            self.webhooks = output.webhooks;
+        }
+        self.webhook_only_violations |= output.webhook_only_violations.unwrap_or(false);
+        self.live = self.live.or(output.live);
+        self.prometheus = self.prometheus.or(output.prometheus);
+        self.grafana_dashboard = self.grafana_dashboard.take().or(output.grafana_dashboard);
+        Ok(())
+    }
+
+    /// The directory the run directories go under.
+    pub fn results_dir(&self) -> PathBuf {
+        self.results_dir.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_RESULTS_DIR))
+    }
+
+    /// Where to write the summary, if anywhere.
+    pub fn summary_path(&self) -> Option<PathBuf> {
+        match &self.summary {
+            Some(path) => Some(path.clone()),
+            None if self.ci => Some(PathBuf::from("iotest-summary.json")),
+            None => None,
+        }
+    }
+
+    /// The workers of every step of the concurrency sweep: the powers of two
+    /// under the most workers, then the most.
+    pub fn concurrency_steps(&self) -> Vec<usize> {
+        let Some(max) = self.concurrency_sweep else {
+            return vec![];
+        };
+        let mut steps: Vec<usize> = (0..usize::BITS).map(|shift| 1 << shift).take_while(|&workers| workers < max).collect();
+        steps.push(max.max(1));
+        steps
+    }
+
+    /// The retry policy, if any retry option is given.
+    pub fn retry(&self) -> Option<RetryPolicy> {
+        if self.retries.is_none() && self.retry_backoff.is_none() && self.retry_max_backoff.is_none()
+            && self.retry_jitter.is_none() && self.retry_on.is_none() {
+            return None;
+        }
+        let default = RetryPolicy::default();
+        Some(RetryPolicy {
+            attempts: self.retries.map_or(default.attempts, |retries| retries + 1),
+            backoff: self.retry_backoff.unwrap_or(default.backoff),
+            max_backoff: self.retry_max_backoff.unwrap_or(default.max_backoff),
+            jitter: self.retry_jitter.unwrap_or(default.jitter),
+            retry_on: self.retry_on.clone().unwrap_or(default.retry_on),
+        })
+    }
+
+    /// The objectives every step must keep: the ones of `--slo`, and the p99
+    /// latency of `--slo-p99-ms` as one.
+    pub fn objectives(&self) -> Vec<Objective> {
+        let mut objectives = self.objectives.clone();
+        objectives.extend(self.slo_p99.map(Objective::p99_within));
+        objectives
+    }
+
+    /// The faults to inject into the backend, if any fault option is given.
+    pub fn fault(&self) -> Option<FaultConfig> {
+        if self.fault_latency.is_none() && self.fault_jitter.is_none()
+            && self.fault_error_rate.is_none() && self.fault_partial_write_rate.is_none() {
+            return None;
+        }
+        Some(FaultConfig {
+            latency: self.fault_latency.unwrap_or_default(),
+            jitter: self.fault_jitter.unwrap_or_default(),
+            error_rate: self.fault_error_rate.unwrap_or_default(),
+            partial_write_rate: self.fault_partial_write_rate.unwrap_or_default(),
+        })
+    }
+
+    /// The network to simulate to the backend, if any network option is
+    /// given.
+    pub fn network(&self) -> Option<NetworkConfig> {
+        if self.net_rtt.is_none() && self.net_jitter.is_none()
+            && self.net_bandwidth.is_none() && self.net_loss_rate.is_none() {
+            return None;
+        }
+        Some(NetworkConfig {
+            rtt: self.net_rtt.unwrap_or_default(),
+            jitter: self.net_jitter.unwrap_or_default(),
+            bandwidth: self.net_bandwidth.map(|bandwidth| bandwidth as u64),
+            loss_rate: self.net_loss_rate.unwrap_or_default(),
+        })
+    }
+}
+
+/// Target is a backend to test and how, one of a run.
+#[derive(Clone)]
+pub struct Target {
+    pub backend: String,
+    /// The name of the target among the others, e.g. `localfs-fsync`.
+    pub label: String,
+    pub settings: Settings,
+    /// Run with cold caches, or warm ones, in a pair of `--cold-warm`.
+    pub cold: Option<bool>,
+}
+
+/// ReportArgs is the arguments of `iotest report`.
+#[derive(Args)]
+pub struct ReportArgs {
+    /// The summary written by `run --summary`.
+    pub summary: PathBuf,
+}
+
+/// CompareArgs is the arguments of `iotest compare`.
+#[derive(Args)]
+pub struct CompareArgs {
+    /// The summary to compare against.
+    pub base: PathBuf,
+
+    /// The summary to compare.
+    pub new: PathBuf,
+}
+
+/// HistoryArgs is the arguments of `iotest history`.
+#[derive(Args)]
+pub struct HistoryArgs {
+    /// The results database.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_HISTORY_DB)]
+    pub db: PathBuf,
+
+    /// Only show runs against the backend.
+    #[arg(long, value_name = "NAME")]
+    pub backend: Option<String>,
+
+    /// Only show runs started since the date (e.g. 2024-07-01).
+    #[arg(long, value_name = "DATE")]
+    pub since: Option<String>,
+
+    /// Only show runs started before the date.
+    #[arg(long, value_name = "DATE")]
+    pub until: Option<String>,
+
+    /// Only show runs whose option KEY is VALUE.
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_pair)]
+    pub config: Vec<(String, String)>,
+
+    /// The QPS step to show. Defaults to the highest one of every run.
+    #[arg(long)]
+    pub qps: Option<u64>,
+
+    /// Plot the trend of p99 latencies to the path.
+    #[arg(long, value_name = "PATH")]
+    pub plot: Option<PathBuf>,
+}
+
+/// ProbeArgs is the arguments of `iotest probe`.
+#[derive(Args)]
+pub struct ProbeArgs {
+    /// The backend to probe [default: localfs].
+    #[arg(long, value_parser = PossibleValuesParser::new(backends::builtin().names()))]
+    pub backend: Option<String>,
+
+    /// A setting of the backend, like `run --backend-opt`.
+    #[arg(long = "backend-opt", value_name = "KEY=VALUE", value_parser = parse_pair)]
+    pub backend_opts: Vec<(String, String)>,
+
+    /// The largest value to probe.
+    #[arg(long, value_name = "BYTES", default_value_t = 256 * 1024 * 1024, value_parser = parse_len)]
+    pub max_value_len: usize,
+
+    /// The longest key to probe.
+    #[arg(long, value_name = "BYTES", default_value_t = 64 * 1024, value_parser = parse_len)]
+    pub max_key_len: usize,
+
+    /// The most keys under a prefix to probe.
+    #[arg(long, value_name = "N", default_value_t = 100_000)]
+    pub max_keys: usize,
+
+    /// Listing keys slower than MS milliseconds counts as degraded.
+    #[arg(long = "list-slo-ms", value_name = "MS", default_value = "1000", value_parser = parse_millis)]
+    pub list_slo: Duration,
+}
+
+impl ProbeArgs {
+    /// The settings to create the backend client from.
+    pub fn settings(&self) -> Settings {
+        self.backend_opts.iter().cloned().collect()
+    }
+}
+
+/// CrashArgs is the arguments of `iotest crash-test`.
+#[derive(Args)]
+pub struct CrashArgs {
+    /// Where to write.
+    #[arg(long, value_name = "DIR", default_value_os_t = default_crash_dir())]
+    pub dir: PathBuf,
+
+    /// The length of every file.
+    #[arg(long, value_name = "BYTES", default_value_t = 64 * 1024, value_parser = parse_len)]
+    pub payload_len: usize,
+
+    /// How long the writer runs before it is killed.
+    #[arg(long = "kill-after-ms", value_name = "MS", default_value = "5000", value_parser = parse_millis)]
+    pub kill_after: Duration,
+
+    /// Drop the page cache after the crash (root required).
+    #[arg(long)]
+    pub drop_caches: bool,
+
+    /// A command run by the shell after the crash, e.g. to switch a dm-flakey device
+    /// to drop writes and back.
+    #[arg(long, value_name = "COMMAND")]
+    pub crash_command: Option<String>,
+}
+
+/// CleanArgs is the arguments of `iotest clean`.
+#[derive(Args)]
+pub struct CleanArgs {
+    /// Where the runs left their data.
+    #[arg(long, value_name = "DIR", default_value_os_t = env::temp_dir())]
+    pub dir: PathBuf,
+
+    /// Only print what would be removed.
+    #[arg(long)]
+    pub dry_run: bool,
+}
+
+/// ServeArgs is the arguments of `iotest serve`.
+#[derive(Args)]
+pub struct ServeArgs {
+    /// The address to listen on.
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+
+    /// The results of every run go into a directory of its ID under this
+    /// one.
+    #[arg(long, value_name = "DIR", default_value = DEFAULT_RESULTS_DIR)]
+    pub results_dir: PathBuf,
+
+    /// The results database the web UI browses.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_HISTORY_DB)]
+    pub db: PathBuf,
+}
+
+/// KvServerArgs is the arguments of `iotest kv-server`.
+#[derive(Args)]
+pub struct KvServerArgs {
+    /// The address to listen on.
+    #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:7878")]
+    pub listen: SocketAddr,
+
+    /// Serve over TLS, presenting the PEM certificate chain with its key.
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    pub tls_cert: Option<PathBuf>,
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_key: Option<PathBuf>,
+
+    /// Require the clients to present a certificate signed by the CAs of the
+    /// PEM bundle.
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    pub tls_ca: Option<PathBuf>,
+}
+
+/// DaemonArgs is the arguments of `iotest daemon`.
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// When to run, as a cron expression of minute, hour, day of month,
+    /// month and day of week, e.g. `0 3 * * *` every night at 3 (UTC).
+    #[arg(long, value_name = "CRON")]
+    pub schedule: String,
+
+    /// The TOML file of the test to run, like `run --config`.
+    #[arg(long, value_name = "PATH")]
+    pub config: PathBuf,
+
+    /// The results database every run is appended to.
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_HISTORY_DB)]
+    pub db: PathBuf,
+
+    /// Alert when a p99 latency is so many percent above its baseline.
+    #[arg(long, value_name = "PERCENT", default_value_t = 20.0)]
+    pub regression: f64,
+
+    /// The baseline is the mean p99 latency of so many runs before, of the
+    /// same backend, QPS and metric.
+    #[arg(long, value_name = "N", default_value_t = crate::history::DEFAULT_BASELINE_RUNS)]
+    pub baseline_runs: u32,
+
+    /// Alert by running the command by the shell, with the alert in the
+    /// `IOTEST_ALERT` environment variable. Alerts are always printed.
+    #[arg(long, value_name = "COMMAND")]
+    pub alert_command: Option<String>,
+}
+
+/// CrashWriterArgs is the arguments of `iotest crash-writer`.
+#[derive(Args)]
+pub struct CrashWriterArgs {
+    #[arg(long)]
+    pub dir: PathBuf,
+
+    #[arg(long, default_value_t = 64 * 1024, value_parser = parse_len)]
+    pub payload_len: usize,
+}
+
+fn default_crash_dir() -> PathBuf {
+    env::temp_dir().join(format!("iotest_crash_{}", std::process::id()))
+}
+
+fn parse_millis(value: &str) -> Result<Duration, String> {
+    value.parse().map(Duration::from_millis).map_err(|err| format!("{}", err))
+}
+
+fn parse_secs(value: &str) -> Result<Duration, String> {
+    value.parse().map(Duration::from_secs).map_err(|err| format!("{}", err))
+}
+
+fn parse_ratio(value: &str) -> Result<f64, String> {
+    check_ratio(value.parse().map_err(|err| format!("{}", err))?)
+}
+
+fn check_ratio(ratio: f64) -> Result<f64, String> {
+    if !(0.0..=1.0).contains(&ratio) {
+        return Err(format!("{} is not between 0 and 1", ratio));
+    }
+    Ok(ratio)
+}
+
+/// Check the ratio of the config file, named by its section and key.
+fn config_ratio(name: &str, ratio: Option<f64>) -> Result<Option<f64>, String> {
+    ratio.map(check_ratio).transpose().map_err(|err| format!("{}: {}", name, err))
+}
+
+/// Parse a length in bytes, or in K, M or G with the suffix.
+fn parse_len(value: &str) -> Result<usize, String> {
+    let (number, unit) = match value.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        Some((idx, _)) => value.split_at(idx),
+        None => (value, ""),
+    };
+    let unit: usize = match unit {
+        "" => 1,
+        "K" | "k" => 1 << 10,
+        "M" | "m" => 1 << 20,
+        "G" | "g" => 1 << 30,
+        _ => return Err(format!("unknown unit: {}", unit)),
+    };
+    let number: usize = number.parse().map_err(|err| format!("{}", err))?;
+    number.checked_mul(unit).ok_or_else(|| format!("too long: {}", value))
+}
+
+fn parse_hook(value: &str) -> Result<Hook, String> {
+    let (at, command) = value.split_once(':').ok_or("expect SECS:COMMAND")?;
+    Ok(Hook {
+        at: parse_secs(at)?,
+        command: command.to_string(),
+    })
+}
+
+fn parse_tenant(value: &str) -> Result<Tenant, String> {
+    let (name, settings) = value.split_once(':').unwrap_or((value, ""));
+    if name.is_empty() {
+        return Err("expect NAME[:qps=N,len=BYTES,reads=N]".to_string());
+    }
+    let mut tenant = Tenant::new(name);
+    for setting in settings.split(',').filter(|setting| !setting.is_empty()) {
+        let (key, value) = setting.split_once('=').ok_or("expect KEY=VALUE")?;
+        match key {
+            "qps" => tenant.qps = Some(value.parse().map_err(|err| format!("{}", err))?),
+            "len" => tenant.payload_len = Some(parse_len(value)?),
+            "reads" => tenant.reads = value.parse().map_err(|err| format!("{}", err))?,
+            _ => return Err(format!("unknown tenant setting: {}", key)),
+        }
+    }
+    Ok(tenant)
+}
+
+fn parse_pair(value: &str) -> Result<(String, String), String> {
+    let (key, value) = value.split_once('=').ok_or("expect KEY=VALUE")?;
+    Ok((key.to_string(), value.to_string()))
+}
+
+fn parse_error_class(value: &str) -> Result<ErrorClass, String> {
+    ErrorClass::ALL.into_iter()
+        .find(|class| class.name() == value)
+        .ok_or_else(|| format!("unknown error class: {}", value))
+}
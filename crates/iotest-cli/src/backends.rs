@@ -0,0 +1,119 @@
+#[cfg(feature = "localfs")]
+use iotest_backends_localfs::{FanOut, LocalFsBlockClient, LocalFsClient, Pattern};
+use iotest_core::Registry;
+#[cfg(any(feature = "localfs", feature = "plugin", feature = "kv"))]
+use iotest_core::Error;
+#[cfg(feature = "localfs")]
+use iotest_core::{Result, Settings, TestClient};
+#[cfg(feature = "plugin")]
+use iotest_core::{Credentials, ProxyConfig};
+
+/// A registry with the backends iotest is built with, by feature.
+pub fn builtin() -> Registry {
+    #[allow(unused_mut)]
+    let mut registry = Registry::new();
+    #[cfg(feature = "localfs")]
+    registry.register("localfs", |settings| {
+        if let Some(pattern) = settings.get("pattern") {
+            return localfs_block(settings, pattern);
+        }
+        let mut client = match settings.get("prefix") {
+            Some(prefix) => LocalFsClient::with_prefix(prefix),
+            None => LocalFsClient::new(),
+        };
+        if let Some(sync) = settings.get("sync") {
+            client = client.with_sync_mode(sync.parse().map_err(Error::new)?);
+        }
+        if settings.get("fallocate").map_or(false, |fallocate| fallocate == "true") {
+            client = client.with_fallocate(true);
+        }
+        if let Some(count) = number(settings, "preallocate")? {
+            // The values are a bit longer than the payload with the headers.
+            let len = LocalFsBlockClient::io_size_for(number(settings, "payload_len")?.unwrap_or(0) as usize);
+            client = client.with_preallocate(count, len);
+        }
+        if let Some(width) = number(settings, "fan_out")? {
+            let levels = number(settings, "fan_out_levels")?.unwrap_or(2);
+            client = client.with_fan_out(FanOut::new(levels as u32, width as u32));
+        }
+        if let Some(max_open) = number(settings, "keep_open")? {
+            client = client.with_keep_open(max_open as usize);
+        }
+        Ok(Box::new(client))
+    });
+    #[cfg(feature = "plugin")]
+    registry.register("plugin", |settings| {
+        let command = settings.get("command")
+            .ok_or_else(|| Error::new("plugin: no command given"))?;
+        // Settings of `env:NAME` are environment variables of the plugin,
+        // and so are the credentials, as `IOTEST_<NAME>`.
+        let mut env: Vec<_> = settings.iter()
+            .filter_map(|(key, value)| key.strip_prefix("env:").map(|name| (name.to_string(), value.clone())))
+            .collect();
+        let credentials = Credentials::from_settings(settings)?;
+        for name in settings.get("credentials").iter().flat_map(|names| names.split(',')) {
+            let Some((secret, source)) = credentials.get(name)? else {
+                return Err(Error::new(format!("plugin: no credential {}: set IOTEST_{} or add it to the credentials file", name, name.to_uppercase())));
+            };
+            println!("CREDENTIAL: {} from {}", name, source.name());
+            env.push((format!("IOTEST_{}", name.to_uppercase()), secret.expose().to_string()));
+        }
+        // The proxy of the settings; one of the environment is inherited.
+        let proxy = ProxyConfig::from_settings(settings)?;
+        if let Some(proxy) = proxy.as_ref().filter(|proxy| !proxy.from_env) {
+            env.extend(proxy.env().into_iter().map(|(name, value)| (name.to_string(), value)));
+        }
+        let env: Vec<_> = env.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        let mut client = iotest_backends_plugin::PluginClient::spawn_with_env(command, &env)?;
+        if let Some(proxy) = proxy {
+            println!("  PROXY:         {}", proxy.describe());
+            client = client.with_detail("proxy", proxy.describe());
+        }
+        Ok(Box::new(client))
+    });
+    #[cfg(feature = "kv")]
+    registry.register("kv", |settings| {
+        let mut client = match settings.get("addr") {
+            Some(addr) => iotest_backends_kv::KvClient::new(addr),
+            None => iotest_backends_kv::KvClient::in_process()?,
+        };
+        match settings.get("reuse").map(String::as_str) {
+            None | Some("true") => (),
+            Some("false") => client = client.with_reuse(false),
+            Some(other) => return Err(Error::new(format!("kv reuse: not a bool: {}", other))),
+        }
+        if let Some(tls) = iotest_core::TlsConfig::from_settings(settings)? {
+            client = client.with_tls(tls)?;
+        }
+        Ok(Box::new(client))
+    });
+    registry
+}
+
+/// A localfs client writing at offsets of one file, by the pattern.
+#[cfg(feature = "localfs")]
+fn localfs_block(settings: &Settings, pattern: &str) -> Result<Box<dyn TestClient>> {
+    let prefix = settings.get("prefix").cloned().unwrap_or_else(iotest_backends_localfs::default_prefix);
+    let io_size = match number(settings, "io_size")? {
+        Some(io_size) => io_size,
+        None => LocalFsBlockClient::io_size_for(number(settings, "payload_len")?.unwrap_or(0) as usize),
+    };
+    let file_size = number(settings, "file_size")?.unwrap_or(1 << 30);
+    let mut client = LocalFsBlockClient::new(&prefix, file_size, io_size)
+        .with_pattern(pattern.parse::<Pattern>().map_err(Error::new)?);
+    if let Some(sync) = settings.get("sync") {
+        client = client.with_sync_mode(sync.parse().map_err(Error::new)?);
+    }
+    if let Some(queue_depth) = number(settings, "queue_depth")? {
+        client = client.with_queue_depth(queue_depth as usize);
+    }
+    Ok(Box::new(client))
+}
+
+/// The numeric setting of localfs, if given.
+#[cfg(feature = "localfs")]
+fn number(settings: &Settings, name: &str) -> Result<Option<u64>> {
+    settings.get(name)
+        .map(|value| value.parse().map_err(|err| Error::new(format!("localfs {}: {}", name, err))))
+        .transpose()
+}
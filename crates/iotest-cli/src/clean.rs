@@ -0,0 +1,68 @@
+use std::{fs, path::Path, process::{Command, Stdio}};
+
+use iotest_core::{Error, Result};
+
+use crate::args::CleanArgs;
+
+/// Remove the directories runs left in the directory, whose process is gone:
+/// `iotest_<PID>` of localfs and `iotest_crash_<PID>` of crash tests.
+pub fn clean(args: &CleanArgs) -> Result<()> {
+    println!("CLEAN:");
+    println!("  DIR:           {}", args.dir.display());
+    let entries = fs::read_dir(&args.dir)
+        .map_err(|err| Error::from_io_error(&format!("list {}", args.dir.display()), err))?;
+    let mut removed = 0;
+    for entry in entries {
+        let entry = entry.map_err(|err| Error::from_io_error(&format!("list {}", args.dir.display()), err))?;
+        let path = entry.path();
+        let Some(pid) = entry.file_name().to_str().and_then(leftover_pid) else {
+            continue;
+        };
+        if !path.is_dir() || is_running(pid) {
+            continue;
+        }
+        if args.dry_run {
+            println!("  WOULD REMOVE:  {}", path.display());
+        } else {
+            fs::remove_dir_all(&path)
+                .map_err(|err| Error::from_io_error(&format!("remove {}", path.display()), err))?;
+            println!("  REMOVED:       {}", path.display());
+        }
+        removed += 1;
+    }
+    println!("  TOTAL:         {}", removed);
+    Ok(())
+}
+
+/// The pid of the run which left the directory of the name, if it is one.
+fn leftover_pid(name: &str) -> Option<u32> {
+    let pid = name.strip_prefix("iotest_crash_").or_else(|| name.strip_prefix("iotest_"))?;
+    pid.parse().ok()
+}
+
+/// Whether the process is running: by /proc where there is one, `kill -0`
+/// on other unixes and `tasklist` on Windows. If none of them can tell, the
+/// process is taken to be running, and nothing is removed.
+fn is_running(pid: u32) -> bool {
+    let proc = Path::new("/proc");
+    if proc.is_dir() {
+        return proc.join(pid.to_string()).exists();
+    }
+    if cfg!(windows) {
+        Command::new("tasklist")
+            .args(["/NH", "/FI", &format!("PID eq {}", pid)])
+            .stderr(Stdio::null())
+            .output()
+            .map_or(true, |output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+    } else {
+        // Fails with EPERM for the processes of other users, which still run.
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .map_or(true, |output| {
+                output.status.success() || String::from_utf8_lossy(&output.stderr).contains("ermitted")
+            })
+    }
+}
@@ -0,0 +1,209 @@
+use std::{collections::BTreeMap, fs, net::SocketAddr, path::{Path, PathBuf}};
+
+use serde::Deserialize;
+
+use iotest_core::{Error, Result};
+
+/// ConfigFile is a versionable benchmark configuration, e.g.:
+///
+/// ```toml
+/// [backend]
+/// name = "localfs"
+/// prefix = "/mnt/nvme/iotest/"
+///
+/// [backend.settings]
+/// direct_io = "true"
+///
+/// [workload]
+/// payload_len = 1048576
+/// check_size = true
+///
+/// [load]
+/// qps = [10, 100, 1000]
+/// duration_secs = 60
+/// timeout_ms = 5000
+///
+/// [runtime]
+/// worker_threads = 2
+/// cpus = [0, 1]
+///
+/// [slo]
+/// p99_ms = 50
+/// objectives = ["write.p99 < 20ms", "error_rate < 0.1%"]
+///
+/// [output]
+/// summary = "iotest-summary.json"
+/// ```
+///
+/// Every option is optional, and flags override it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub backend: BackendSection,
+    pub workload: WorkloadSection,
+    pub load: LoadSection,
+    pub runtime: RuntimeSection,
+    pub slo: SloSection,
+    pub output: OutputSection,
+}
+
+/// BackendSection is which backend to test and how to connect to it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BackendSection {
+    pub name: Option<String>,
+    /// The directory localfs writes to.
+    #[serde(alias = "dir")]
+    pub prefix: Option<String>,
+    /// The sync modes of localfs, like `--localfs-sync`.
+    pub sync: Option<Vec<String>>,
+    /// Allocate files before writing them, and create so many up front, like
+    /// `--localfs-fallocate` and `--localfs-preallocate`.
+    pub fallocate: Option<bool>,
+    pub preallocate: Option<u64>,
+    /// Spread files over directories so wide and deep, like
+    /// `--localfs-fan-out` and `--localfs-fan-out-levels`.
+    pub fan_out: Option<u32>,
+    pub fan_out_levels: Option<u32>,
+    /// Keep so many files open, like `--localfs-keep-open`.
+    pub keep_open: Option<usize>,
+    /// Write at sequential or random offsets of one file, like
+    /// `--localfs-pattern`, of the size in bytes, by IOs of the size.
+    pub pattern: Option<String>,
+    pub file_size: Option<usize>,
+    pub io_size: Option<usize>,
+    pub queue_depth: Option<usize>,
+    /// The command plugin runs.
+    pub command: Option<String>,
+    /// The address of the kv server, like `--kv-addr`.
+    pub addr: Option<String>,
+    /// The endpoints to compare, by name, like `--endpoint`.
+    pub endpoints: BTreeMap<String, String>,
+    /// Connect over TLS, like `--tls` and the other `--tls-*` flags.
+    pub tls: Option<bool>,
+    pub tls_ca: Option<PathBuf>,
+    pub tls_cert: Option<PathBuf>,
+    pub tls_key: Option<PathBuf>,
+    pub tls_insecure: Option<bool>,
+    pub tls_server_name: Option<String>,
+    /// The credentials to resolve and where from, like `--credential` and
+    /// the other `--credentials-*` flags.
+    pub credentials: Option<Vec<String>>,
+    pub credentials_file: Option<PathBuf>,
+    pub credentials_profile: Option<String>,
+    pub credentials_imds: Option<bool>,
+    pub credentials_prompt: Option<bool>,
+    /// The proxy to go through, like `--proxy` and `--no-proxy`.
+    pub proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    /// More settings of the backend, e.g. endpoints or credentials, like
+    /// `--backend-opt`.
+    pub settings: BTreeMap<String, String>,
+}
+
+/// WorkloadSection is what every request does.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkloadSection {
+    pub payload_len: Option<usize>,
+    /// Draw value lengths up to this, like `--payload-len-max`.
+    pub payload_len_max: Option<usize>,
+    pub seed: Option<u64>,
+    pub verify: Option<bool>,
+    pub check_size: Option<bool>,
+    pub cold_reads: Option<bool>,
+    pub drop_caches: Option<bool>,
+    pub drop_caches_command: Option<String>,
+    /// Run every backend cold then warm, like `--cold-warm`.
+    pub cold_warm: Option<bool>,
+    pub xattr_len: Option<usize>,
+    pub hole_len: Option<usize>,
+    pub copy: Option<bool>,
+    pub versioned: Option<bool>,
+    pub connection_phases: Option<bool>,
+    pub shared_keys: Option<usize>,
+    pub scrub: Option<f64>,
+    pub verify_at_end: Option<bool>,
+    pub cross_read: Option<bool>,
+    /// Sample the space used every so often, like `--space-interval`.
+    pub space_interval_ms: Option<u64>,
+    /// Check the health every so often, like `--health-interval`.
+    pub health_interval_ms: Option<u64>,
+    /// The payload lengths to sweep, in bytes, like `--size-sweep`.
+    pub size_sweep: Option<Vec<usize>>,
+}
+
+/// LoadSection is how requests are issued.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoadSection {
+    pub qps: Option<Vec<u64>>,
+    /// The most closed-loop workers to sweep, like `--concurrency-sweep`.
+    pub concurrency_sweep: Option<usize>,
+    pub duration_secs: Option<u64>,
+    pub warm_up_secs: Option<u64>,
+    pub timeout_ms: Option<u64>,
+    pub retries: Option<u32>,
+    pub pool_size: Option<usize>,
+    pub namespaces: Option<usize>,
+    /// `NAME[:qps=N,len=BYTES,reads=N]`, like `--tenant`.
+    pub tenants: Vec<String>,
+    /// The ratio of high priority requests, like `--priority-ratio`.
+    pub priority_ratio: Option<f64>,
+    /// `SECS:COMMAND`, like `--hook`.
+    pub hooks: Vec<String>,
+}
+
+/// RuntimeSection is the threads requests are issued from.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeSection {
+    pub worker_threads: Option<usize>,
+    pub max_blocking_threads: Option<usize>,
+    pub cpus: Option<Vec<usize>>,
+}
+
+/// SloSection is what the verdict is judged by.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SloSection {
+    pub max_error_rate: Option<f64>,
+    pub p99_ms: Option<u64>,
+    /// Report stalls, like `--stall-factor` and `--stall-secs`.
+    pub stall_factor: Option<f64>,
+    pub stall_secs: Option<u64>,
+    /// The objectives every step must keep, like `--slo`.
+    pub objectives: Vec<String>,
+    pub regression_percent: Option<f64>,
+}
+
+/// OutputSection is where the results go.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputSection {
+    pub results_dir: Option<PathBuf>,
+    pub label: Option<String>,
+    pub summary: Option<PathBuf>,
+    pub checkpoint: Option<PathBuf>,
+    pub request_log: Option<PathBuf>,
+    pub parquet_dir: Option<PathBuf>,
+    pub images_dir: Option<PathBuf>,
+    pub html_charts: Option<bool>,
+    /// Write a CPU flamegraph per step, like `--profile`.
+    pub profile: Option<bool>,
+    pub history_db: Option<PathBuf>,
+    pub webhooks: Vec<String>,
+    pub webhook_only_violations: Option<bool>,
+    pub live: Option<SocketAddr>,
+    pub prometheus: Option<SocketAddr>,
+    pub grafana_dashboard: Option<PathBuf>,
+}
+
+impl ConfigFile {
+    pub fn read(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| Error::from_io_error(&format!("read {}", path.display()), err))?;
+        toml::from_str(&text)
+            .map_err(|err| Error::new(format!("parse {}: {}", path.display(), err)))
+    }
+}
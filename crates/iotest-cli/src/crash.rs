@@ -0,0 +1,141 @@
+use std::{fs::{self, File}, io::{self, Write}, path::Path, process::Stdio};
+
+use tokio::{io::{AsyncBufReadExt, BufReader}, process::Command, time::{sleep, Instant}};
+
+use iotest_core::{self as tester, drop_page_cache, Error, Payload, Result};
+
+use crate::args::{CrashArgs, CrashWriterArgs};
+
+/// CrashReport is what a crash-consistency test results in.
+pub struct CrashReport {
+    pub acknowledged: u64,
+    pub survived: u64,
+    pub lost: u64,
+    pub torn: u64,
+}
+
+impl CrashReport {
+    pub fn is_consistent(&self) -> bool {
+        self.lost == 0 && self.torn == 0
+    }
+}
+
+/// Run a crash-consistency test: a child process keeps writing durable files
+/// and acknowledging them, then it is killed (and the caches are dropped, or
+/// the crash command is run, if asked), and every acknowledged file must have
+/// survived intact.
+pub async fn test(args: &CrashArgs) -> Result<CrashReport> {
+    fs::create_dir_all(&args.dir)
+        .map_err(|err| Error::from_io_error(&format!("mkdir {}", args.dir.display()), err))?;
+    println!("CRASH TEST:");
+    println!("  DIR:           {}", args.dir.display());
+    println!("  KILL AFTER:    {:?}", args.kill_after);
+
+    // Start the writer.
+    let exe = std::env::current_exe()
+        .map_err(|err| Error::from_io_error("get current exe", err))?;
+    let mut child = Command::new(exe)
+        .arg("crash-writer")
+        .arg("--dir").arg(&args.dir)
+        .arg("--payload-len").arg(args.payload_len.to_string())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| Error::from_io_error("spawn crash writer", err))?;
+    let stdout = child.stdout.take().unwrap();
+    let mut lines = BufReader::new(stdout).lines();
+
+    // Collect the acknowledgements until it is time to crash.
+    let mut acked: Vec<(u64, String)> = vec![];
+    let deadline = Instant::now() + args.kill_after;
+    loop {
+        tokio::select! {
+            line = lines.next_line() => match line {
+                Ok(Some(line)) => acked.extend(parse_ack(&line)),
+                _ => return Err(Error::new("crash writer exited early")),
+            },
+            _ = sleep(deadline.saturating_duration_since(Instant::now())) => break,
+        }
+    }
+
+    // Crash.
+    child.start_kill().map_err(|err| Error::from_io_error("kill crash writer", err))?;
+    // The acknowledgements already written to the pipe count too.
+    while let Ok(Some(line)) = lines.next_line().await {
+        acked.extend(parse_ack(&line));
+    }
+    let _ = child.wait().await;
+    println!("  KILLED:        after {} acknowledged writes", acked.len());
+    if args.drop_caches {
+        drop_page_cache(None).await?;
+        println!("  DROPPED CACHES");
+    }
+    if let Some(command) = &args.crash_command {
+        let status = tester::shell(command).status().await
+            .map_err(|err| Error::from_io_error(&format!("run {}", command), err))?;
+        println!("  CRASH COMMAND: {} ({})", command, status);
+    }
+
+    // Verify.
+    let mut report = CrashReport {
+        acknowledged: acked.len() as u64,
+        survived: 0,
+        lost: 0,
+        torn: 0,
+    };
+    for (seq, key) in &acked {
+        match fs::read_to_string(key) {
+            Ok(value) => match tester::verify_payload(&value, key, *seq) {
+                Ok(()) => report.survived += 1,
+                Err(err) => {
+                    report.torn += 1;
+                    println!("    TORN {}: {}", key, err.msg);
+                }
+            },
+            Err(err) => {
+                report.lost += 1;
+                println!("    LOST {}: {}", key, err);
+            }
+        }
+    }
+    println!("  ACKNOWLEDGED:  {}", report.acknowledged);
+    println!("  SURVIVED:      {}", report.survived);
+    println!("  LOST:          {}", report.lost);
+    println!("  TORN:          {}", report.torn);
+    Ok(report)
+}
+
+fn parse_ack(line: &str) -> Option<(u64, String)> {
+    let (seq, key) = line.strip_prefix("ACK ")?.split_once(' ')?;
+    Some((seq.parse().ok()?, key.to_string()))
+}
+
+/// Keep writing durable files, acknowledging each on stdout once it is
+/// fsynced, until killed.
+pub fn write(args: &CrashWriterArgs) -> Result<()> {
+    let payload = Payload::new(args.payload_len);
+    let dir = File::open(&args.dir)
+        .map_err(|err| Error::from_io_error(&format!("open {}", args.dir.display()), err))?;
+    let mut stdout = io::stdout().lock();
+    for seq in 0.. {
+        let path = args.dir.join(seq.to_string());
+        let key = path.to_string_lossy().into_owned();
+        write_durable(&path, &payload.value(&key, seq))?;
+        // Make the new entry in the directory durable too.
+        dir.sync_all()
+            .map_err(|err| Error::from_io_error(&format!("fsync {}", args.dir.display()), err))?;
+        writeln!(stdout, "ACK {} {}", seq, key)
+            .and_then(|_| stdout.flush())
+            .map_err(|err| Error::from_io_error("acknowledge", err))?;
+    }
+    Ok(())
+}
+
+fn write_durable(path: &Path, value: &str) -> Result<()> {
+    let mut file = File::create(path)
+        .map_err(|err| Error::from_io_error(&format!("create {}", path.display()), err))?;
+    file.write_all(value.as_bytes())
+        .map_err(|err| Error::from_io_error(&format!("write {}", path.display()), err))?;
+    file.sync_all()
+        .map_err(|err| Error::from_io_error(&format!("fsync {}", path.display()), err))
+}
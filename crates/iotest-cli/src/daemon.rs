@@ -0,0 +1,88 @@
+use std::{env, process::Stdio, str::FromStr};
+
+use chrono::Utc;
+use cron::Schedule;
+use tokio::{process::Command, signal, time::sleep};
+
+use iotest_core::{shell, Error, Result};
+
+use crate::{args::DaemonArgs, history::History};
+
+/// Run the test of the config on the schedule until interrupted, each time in
+/// a child process appending to the results database, then alert on the
+/// failed runs and the regressions.
+pub async fn run(args: &DaemonArgs) -> Result<()> {
+    let schedule = parse_schedule(&args.schedule)?;
+    let exe = env::current_exe()
+        .map_err(|err| Error::from_io_error("get current exe", err))?;
+    println!("DAEMON:");
+    println!("  SCHEDULE:      {}", args.schedule);
+    println!("  CONFIG:        {}", args.config.display());
+    println!("  HISTORY:       {}", args.db.display());
+    loop {
+        let next = schedule.upcoming(Utc).next()
+            .ok_or_else(|| Error::new(format!("schedule {}: no time to run next", args.schedule)))?;
+        println!("NEXT RUN:        {}", next);
+        let wait = (next - Utc::now()).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = sleep(wait) => {}
+            _ = signal::ctrl_c() => {
+                println!("STOPPED");
+                return Ok(());
+            }
+        }
+
+        let last_id = History::open(&args.db)?.last_id()?;
+        println!("RUN:             {}", Utc::now());
+        let status = Command::new(&exe)
+            .arg("run")
+            .arg("--config").arg(&args.config)
+            .arg("--ci")
+            .arg("--history-db").arg(&args.db)
+            .stdin(Stdio::null())
+            .status()
+            .await
+            .map_err(|err| Error::from_io_error("spawn run", err))?;
+        println!("  EXIT:          {}", status);
+        if !status.success() {
+            alert(args, &format!("iotest run of {} failed: {}", args.config.display(), status)).await;
+        }
+
+        let history = History::open(&args.db)?;
+        for id in history.ids_after(last_id)? {
+            let regressions = history.regressions(id, args.baseline_runs, args.regression)?;
+            if regressions.is_empty() {
+                continue;
+            }
+            let mut text = format!("iotest run {} regressed:", id);
+            for regression in &regressions {
+                text += &format!("\n  {}", regression);
+            }
+            alert(args, &text).await;
+        }
+    }
+}
+
+/// Parse a cron expression of 5 fields, or of 6 or 7 with the seconds (and
+/// the year).
+fn parse_schedule(expr: &str) -> Result<Schedule> {
+    let expr = match expr.split_whitespace().count() {
+        5 => format!("0 {}", expr),
+        _ => expr.to_string(),
+    };
+    Schedule::from_str(&expr)
+        .map_err(|err| Error::new(format!("schedule {}: {}", expr, err)))
+}
+
+/// Print the alert, and run the alert command with it if any.
+async fn alert(args: &DaemonArgs, text: &str) {
+    println!("ALERT: {}", text);
+    let Some(command) = &args.alert_command else {
+        return;
+    };
+    match shell(command).env("IOTEST_ALERT", text).status().await {
+        Ok(status) if status.success() => (),
+        Ok(status) => eprintln!("  ALERT COMMAND: {}", status),
+        Err(err) => eprintln!("  ALERT COMMAND: {}", err),
+    }
+}
@@ -0,0 +1,365 @@
+use std::{fmt, path::Path};
+
+use plotters::{backend::BitMapBackend, chart::{ChartBuilder, SeriesLabelPosition}, drawing::IntoDrawingArea, element::PathElement, series::LineSeries, style::{Color, BLACK, BLUE, GREEN, RED, WHITE}};
+use rusqlite::{params, params_from_iter, types::Value, Connection, OptionalExtension};
+use serde::Serialize;
+
+use iotest_core::{Error, Result, Summary};
+
+use crate::args::HistoryArgs;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    started_at TEXT NOT NULL,
+    finished_at TEXT NOT NULL,
+    backend TEXT NOT NULL,
+    hostname TEXT NOT NULL,
+    iotest_commit TEXT NOT NULL,
+    verdict TEXT NOT NULL,
+    config TEXT NOT NULL,
+    summary TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS steps (
+    run_id INTEGER NOT NULL REFERENCES runs(id),
+    qps INTEGER NOT NULL,
+    op TEXT NOT NULL,
+    count INTEGER NOT NULL,
+    errors INTEGER NOT NULL,
+    error_rate REAL NOT NULL,
+    p50_us INTEGER NOT NULL,
+    p99_us INTEGER NOT NULL,
+    achieved_qps REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS steps_run_id ON steps(run_id);
+";
+
+/// History is a SQLite database every run's summary is appended to, so the
+/// performance of a storage can be tracked across months.
+pub struct History {
+    conn: Connection,
+}
+
+/// HistoryRun is a run in the history, with the results of one of its steps.
+pub struct HistoryRun {
+    pub id: i64,
+    pub started_at: String,
+    pub backend: String,
+    pub verdict: String,
+    pub qps: i64,
+    /// (op, p50_us, p99_us, error_rate)
+    pub ops: Vec<(String, i64, i64, f64)>,
+}
+
+/// HistoryEntry is a run in the history, without its steps.
+#[derive(Serialize)]
+pub struct HistoryEntry {
+    pub id: i64,
+    pub started_at: String,
+    pub backend: String,
+    pub hostname: String,
+    pub verdict: String,
+}
+
+/// How many runs before the baseline of a regression is the mean of by
+/// default.
+pub const DEFAULT_BASELINE_RUNS: u32 = 5;
+
+/// Regression is a p99 latency of a run above the baseline of the runs
+/// before.
+#[derive(Serialize)]
+pub struct Regression {
+    pub backend: String,
+    pub qps: i64,
+    pub op: String,
+    pub p99_us: i64,
+    pub baseline_us: f64,
+}
+
+impl fmt::Display for Regression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} QPS {} {} p99 {}µs, baseline {:.0}µs (+{:.1}%)",
+            self.backend, self.qps, self.op, self.p99_us, self.baseline_us,
+            (self.p99_us as f64 / self.baseline_us - 1.0) * 100.0)
+    }
+}
+
+impl History {
+    pub fn open(path: &Path) -> Result<Self> {
+        let conn = Connection::open(path)
+            .map_err(|err| sql_error(&format!("open {}", path.display()), err))?;
+        conn.execute_batch(SCHEMA)
+            .map_err(|err| sql_error("create tables", err))?;
+        Ok(Self { conn })
+    }
+
+    /// Append the summary of a run, returning the ID of the run.
+    pub fn append(&mut self, summary: &Summary) -> Result<i64> {
+        let metadata = &summary.metadata;
+        let config = serde_json::to_string(&metadata.options)
+            .map_err(|err| Error::new(format!("serialize options: {}", err)))?;
+        let json = serde_json::to_string(summary)
+            .map_err(|err| Error::new(format!("serialize summary: {}", err)))?;
+        let verdict = serde_json::to_value(summary.verdict)
+            .map_err(|err| Error::new(format!("serialize verdict: {}", err)))?;
+
+        let tx = self.conn.transaction()
+            .map_err(|err| sql_error("begin", err))?;
+        tx.execute(
+            "INSERT INTO runs (started_at, finished_at, backend, hostname, iotest_commit, verdict, config, summary)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                metadata.started_at.to_rfc3339(),
+                metadata.finished_at.to_rfc3339(),
+                metadata.backend,
+                metadata.hostname,
+                metadata.iotest_commit,
+                verdict.as_str().unwrap_or_default(),
+                config,
+                json,
+            ],
+        ).map_err(|err| sql_error("insert run", err))?;
+        let run_id = tx.last_insert_rowid();
+        for step in &summary.steps {
+            for metric in &step.metrics {
+                tx.execute(
+                    "INSERT INTO steps (run_id, qps, op, count, errors, error_rate, p50_us, p99_us, achieved_qps)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                    params![
+                        run_id,
+                        step.qps as i64,
+                        metric.metric.name(),
+                        metric.count as i64,
+                        metric.errors as i64,
+                        metric.error_rate,
+                        metric.p50_us as i64,
+                        metric.p99_us as i64,
+                        metric.achieved_qps,
+                    ],
+                ).map_err(|err| sql_error("insert step", err))?;
+            }
+        }
+        tx.commit().map_err(|err| sql_error("commit", err))?;
+        Ok(run_id)
+    }
+
+    /// Query the runs matching the arguments, oldest first.
+    pub fn query(&self, args: &HistoryArgs) -> Result<Vec<HistoryRun>> {
+        let mut sql = String::from(
+            "SELECT r.id, r.started_at, r.backend, r.verdict, s.qps, s.op, s.p50_us, s.p99_us, s.error_rate
+             FROM runs r JOIN steps s ON s.run_id = r.id
+             WHERE s.qps = COALESCE(?1, (SELECT MAX(qps) FROM steps WHERE run_id = r.id))",
+        );
+        let mut values = vec![args.qps.map_or(Value::Null, |qps| Value::Integer(qps as i64))];
+        if let Some(backend) = &args.backend {
+            values.push(Value::Text(backend.clone()));
+            sql += &format!(" AND r.backend = ?{}", values.len());
+        }
+        if let Some(since) = &args.since {
+            values.push(Value::Text(since.clone()));
+            sql += &format!(" AND r.started_at >= ?{}", values.len());
+        }
+        if let Some(until) = &args.until {
+            values.push(Value::Text(until.clone()));
+            sql += &format!(" AND r.started_at < ?{}", values.len());
+        }
+        for (key, value) in &args.config {
+            values.push(Value::Text(format!("$.{}", key)));
+            values.push(Value::Text(value.clone()));
+            sql += &format!(" AND CAST(json_extract(r.config, ?{}) AS TEXT) = ?{}", values.len() - 1, values.len());
+        }
+        sql += " ORDER BY r.started_at, r.id, s.rowid";
+
+        let mut stmt = self.conn.prepare(&sql)
+            .map_err(|err| sql_error("prepare query", err))?;
+        let rows = stmt.query_map(params_from_iter(values), |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, i64>(4)?,
+                (row.get::<_, String>(5)?, row.get::<_, i64>(6)?, row.get::<_, i64>(7)?, row.get::<_, f64>(8)?),
+            ))
+        }).map_err(|err| sql_error("query", err))?;
+
+        let mut runs: Vec<HistoryRun> = vec![];
+        for row in rows {
+            let (id, started_at, backend, verdict, qps, op) = row
+                .map_err(|err| sql_error("read row", err))?;
+            match runs.last_mut() {
+                Some(run) if run.id == id => run.ops.push(op),
+                _ => runs.push(HistoryRun { id, started_at, backend, verdict, qps, ops: vec![op] }),
+            }
+        }
+        Ok(runs)
+    }
+
+    /// Every run, oldest first.
+    pub fn entries(&self) -> Result<Vec<HistoryEntry>> {
+        let mut stmt = self.conn.prepare("SELECT id, started_at, backend, hostname, verdict FROM runs ORDER BY started_at, id")
+            .map_err(|err| sql_error("prepare query", err))?;
+        let entries = stmt.query_map([], |row| {
+            Ok(HistoryEntry {
+                id: row.get(0)?,
+                started_at: row.get(1)?,
+                backend: row.get(2)?,
+                hostname: row.get(3)?,
+                verdict: row.get(4)?,
+            })
+        }).map_err(|err| sql_error("query runs", err))?;
+        entries.collect::<rusqlite::Result<_>>()
+            .map_err(|err| sql_error("read row", err))
+    }
+
+    /// The summary of the run as JSON, if there is such a run.
+    pub fn summary_json(&self, id: i64) -> Result<Option<String>> {
+        self.conn.query_row("SELECT summary FROM runs WHERE id = ?1", [id], |row| row.get(0))
+            .optional()
+            .map_err(|err| sql_error("query summary", err))
+    }
+
+    /// The ID of the last run appended, 0 if none.
+    pub fn last_id(&self) -> Result<i64> {
+        self.conn.query_row("SELECT COALESCE(MAX(id), 0) FROM runs", [], |row| row.get(0))
+            .map_err(|err| sql_error("query last run", err))
+    }
+
+    /// The IDs of the runs appended after the one, in order.
+    pub fn ids_after(&self, id: i64) -> Result<Vec<i64>> {
+        let mut stmt = self.conn.prepare("SELECT id FROM runs WHERE id > ?1 ORDER BY id")
+            .map_err(|err| sql_error("prepare query", err))?;
+        let ids = stmt.query_map([id], |row| row.get(0))
+            .map_err(|err| sql_error("query runs", err))?;
+        ids.collect::<rusqlite::Result<_>>()
+            .map_err(|err| sql_error("read row", err))
+    }
+
+    /// The p99 latencies of the run more than `threshold` percent above the
+    /// mean of the same backend, QPS and metric in the `baseline_runs` runs
+    /// before.
+    pub fn regressions(&self, id: i64, baseline_runs: u32, threshold: f64) -> Result<Vec<Regression>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT r.backend, s.qps, s.op, s.p99_us,
+                (SELECT AVG(p99_us) FROM (
+                    SELECT b.p99_us FROM steps b JOIN runs br ON br.id = b.run_id
+                    WHERE br.backend = r.backend AND br.id < r.id AND b.qps = s.qps AND b.op = s.op
+                    ORDER BY br.id DESC LIMIT ?2))
+             FROM steps s JOIN runs r ON r.id = s.run_id
+             WHERE s.run_id = ?1
+             ORDER BY s.rowid",
+        ).map_err(|err| sql_error("prepare query", err))?;
+        let rows = stmt.query_map(params![id, baseline_runs], |row| {
+            Ok((
+                Regression {
+                    backend: row.get(0)?,
+                    qps: row.get(1)?,
+                    op: row.get(2)?,
+                    p99_us: row.get(3)?,
+                    baseline_us: 0.0,
+                },
+                row.get::<_, Option<f64>>(4)?,
+            ))
+        }).map_err(|err| sql_error("query", err))?;
+
+        let mut regressions = vec![];
+        for row in rows {
+            let (regression, baseline) = row.map_err(|err| sql_error("read row", err))?;
+            // Without runs before, there is nothing to regress from.
+            let Some(baseline_us) = baseline else {
+                continue;
+            };
+            if regression.p99_us as f64 > baseline_us * (1.0 + threshold / 100.0) {
+                regressions.push(Regression { baseline_us, ..regression });
+            }
+        }
+        Ok(regressions)
+    }
+}
+
+/// Show the runs in the history, and plot their trend if asked.
+pub fn show(args: &HistoryArgs) -> Result<()> {
+    let history = History::open(&args.db)?;
+    let runs = history.query(args)?;
+
+    println!("HISTORY:");
+    let mut line = format!("  {:>6}  {:25}  {:10}  {:16}  {:>6}", "ID", "STARTED AT", "BACKEND", "VERDICT", "QPS");
+    if let Some(run) = runs.first() {
+        for op in &run.ops {
+            line += &format!("  {:>12}", format!("{} P99", op.0.to_uppercase()));
+        }
+    }
+    println!("{}", line);
+    for run in &runs {
+        let mut line = format!("  {:>6}  {:25}  {:10}  {:16}  {:>6}",
+            run.id, run.started_at.get(..25).unwrap_or(&run.started_at), run.backend, run.verdict, run.qps);
+        for op in &run.ops {
+            line += &format!("  {:>10}µs", op.2);
+        }
+        println!("{}", line);
+    }
+
+    if let Some(path) = &args.plot {
+        plot_trend(path, &runs)?;
+        println!("  See also: {}", path.display());
+    }
+    Ok(())
+}
+
+fn plot_trend(path: &Path, runs: &[HistoryRun]) -> Result<()> {
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", path.display(), err));
+    let max_p99 = runs.iter()
+        .flat_map(|run| run.ops.iter().map(|op| op.2))
+        .max()
+        .unwrap_or(0);
+    let area = BitMapBackend::new(path, (1280, 720)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(32)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption("p99 latency trend", ("sans-serif", 40))
+        .build_cartesian_2d(0..(runs.len().max(1) as i32), 0.0..(max_p99 as f64 / 1000.0 * 1.1 + 1.0))
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("run")
+        .y_desc("p99 (ms)")
+        .x_labels(runs.len().min(20))
+        .x_label_formatter(&|idx: &i32| {
+            runs.get(*idx as usize).map(|run| run.id.to_string()).unwrap_or_default()
+        })
+        .axis_desc_style(("sans-serif", 24))
+        .draw()
+        .map_err(plot_error)?;
+
+    let colors = [RED, BLUE, GREEN];
+    let ops: Vec<String> = runs.first()
+        .map(|run| run.ops.iter().map(|op| op.0.clone()).collect())
+        .unwrap_or_default();
+    for (i, op) in ops.iter().enumerate() {
+        let color = colors[i % colors.len()];
+        let points = runs.iter().enumerate().filter_map(|(idx, run)| {
+            run.ops.iter()
+                .find(|run_op| &run_op.0 == op)
+                .map(|run_op| (idx as i32, run_op.2 as f64 / 1000.0))
+        });
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(op.as_str())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(())
+}
+
+fn sql_error(prefix: &str, err: rusqlite::Error) -> Error {
+    Error::new(format!("{}: {}", prefix, err)).with_source(err)
+}
@@ -0,0 +1,452 @@
+use std::{path::{Path, PathBuf}, process};
+
+use chrono::Utc;
+use clap::Parser;
+use tokio::signal;
+use tokio_util::sync::CancellationToken;
+
+use args::{Cli, Command, ProbeArgs, RunArgs, Target};
+use config::ConfigFile;
+use history::{History, DEFAULT_BASELINE_RUNS};
+use provision::Container;
+use results::{Artifacts, Manifest};
+use runtime::RuntimeOptions;
+use iotest_core::{fault::FaultClient, network::NetworkClient, redact, Error, Options, ProxyConfig, Result, Summary, TestClient, Tester, Verdict, EXIT_HARNESS_FAILURE};
+#[cfg(feature = "compression")]
+use iotest_core::compression::{Codec, CompressionClient, CompressionConfig};
+use iotest_report::{ChartReporter, ConsoleReporter, HtmlReporter, JsonReporter, RequestLogReporter};
+
+mod args;
+mod backends;
+mod clean;
+mod config;
+mod crash;
+mod daemon;
+mod history;
+mod notify;
+mod probe;
+mod provision;
+mod report;
+mod results;
+mod runtime;
+mod serve;
+
+/// The conventional exit code of a process killed by SIGINT.
+const EXIT_INTERRUPTED: i32 = 130;
+
+fn main() {
+    let mut command = match Cli::try_parse() {
+        Ok(cli) => cli.command(),
+        // Help and version.
+        Err(err) if !err.use_stderr() => err.exit(),
+        Err(err) => {
+            let _ = err.print();
+            process::exit(EXIT_HARNESS_FAILURE);
+        }
+    };
+
+    // A run can tune its runtime, by flags or by the config file.
+    let runtime_options = match &mut command {
+        Command::Run(args) => {
+            read_config(args);
+            RuntimeOptions::of(args)
+        }
+        _ => RuntimeOptions::default(),
+    };
+    let runtime = match runtime::build(&runtime_options) {
+        Ok(runtime) => runtime,
+        Err(err) => {
+            eprintln!("HARNESS FAILURE: {}", err);
+            process::exit(EXIT_HARNESS_FAILURE);
+        }
+    };
+    runtime.block_on(execute(command));
+}
+
+async fn execute(command: Command) {
+    match command {
+        Command::Run(args) => run(args).await,
+        Command::ListBackends => {
+            for backend in backends::builtin().names() {
+                println!("{}", backend);
+            }
+        }
+        Command::Report(args) => {
+            if let Err(err) = report::show(&args) {
+                eprintln!("ERROR: {}", err);
+                process::exit(1);
+            }
+        }
+        Command::Compare(args) => {
+            if let Err(err) = report::compare(&args) {
+                eprintln!("ERROR: {}", err);
+                process::exit(1);
+            }
+        }
+        Command::History(args) => {
+            if let Err(err) = history::show(&args) {
+                eprintln!("ERROR: {}", err);
+                process::exit(1);
+            }
+        }
+        Command::CrashTest(args) => match crash::test(&args).await {
+            Ok(report) if report.is_consistent() => (),
+            Ok(_) => process::exit(Verdict::ExcessiveErrors.exit_code()),
+            Err(err) => {
+                eprintln!("HARNESS FAILURE: {}", err);
+                process::exit(EXIT_HARNESS_FAILURE);
+            }
+        },
+        Command::Probe(args) => match probe(&args).await {
+            Ok(report) => report.print(),
+            Err(err) => {
+                eprintln!("HARNESS FAILURE: {}", err);
+                process::exit(EXIT_HARNESS_FAILURE);
+            }
+        },
+        Command::Clean(args) => {
+            if let Err(err) = clean::clean(&args) {
+                eprintln!("ERROR: {}", err);
+                process::exit(1);
+            }
+        }
+        Command::Serve(args) => {
+            if let Err(err) = serve::serve(&args).await {
+                eprintln!("HARNESS FAILURE: {}", err);
+                process::exit(EXIT_HARNESS_FAILURE);
+            }
+        }
+        Command::Daemon(args) => {
+            if let Err(err) = daemon::run(&args).await {
+                eprintln!("HARNESS FAILURE: {}", err);
+                process::exit(EXIT_HARNESS_FAILURE);
+            }
+        }
+        #[cfg(feature = "kv")]
+        Command::KvServer(args) => {
+            let served = iotest_backends_kv::KvServer::bind(args.listen)
+                .and_then(|mut server| {
+                    println!("KV SERVER:");
+                    println!("  ADDR:          {}", server.local_addr()?);
+                    if let (Some(cert), Some(key)) = (&args.tls_cert, &args.tls_key) {
+                        server = server.with_tls(iotest_core::tls_acceptor(cert, key, args.tls_ca.as_deref())?);
+                        println!("  TLS:           {}", cert.display());
+                    }
+                    Ok(server)
+                });
+            let served = match served {
+                Ok(server) => server.serve().await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = served {
+                eprintln!("ERROR: {}", err);
+                process::exit(1);
+            }
+        }
+        Command::CrashWriter(args) => {
+            if let Err(err) = crash::write(&args) {
+                eprintln!("ERROR: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+/// Fill the arguments from the config file, if any.
+fn read_config(args: &mut RunArgs) {
+    if let Some(path) = args.config.clone() {
+        let merged = ConfigFile::read(&path)
+            .and_then(|file| args.merge(file).map_err(Error::new));
+        if let Err(err) = merged {
+            eprintln!("HARNESS FAILURE: {}", err);
+            process::exit(EXIT_HARNESS_FAILURE);
+        }
+    }
+}
+
+async fn run(args: RunArgs) {
+    let defaults = Options::default();
+    let mut options = Options {
+        payload_len: args.payload_len.unwrap_or(defaults.payload_len),
+        payload_len_max: args.payload_len_max,
+        // Pick the seed here, so all backends get the same operations.
+        seed: Some(args.seed.unwrap_or_else(rand::random)),
+        qps_steps: args.qps_steps.clone().unwrap_or(defaults.qps_steps.clone()),
+        size_sweep: args.size_sweep.clone().unwrap_or_default(),
+        concurrency_sweep: args.concurrency_steps(),
+        step_duration: args.step_duration.unwrap_or(defaults.step_duration),
+        warm_up: args.warm_up,
+        timeout: args.timeout,
+        retry: args.retry(),
+        pool_size: args.pool_size.unwrap_or(defaults.pool_size),
+        verify: !args.no_verify,
+        check_size: args.check_size,
+        cold_reads: args.cold_reads,
+        drop_caches: args.drop_caches || args.drop_caches_command.is_some(),
+        drop_caches_command: args.drop_caches_command.clone(),
+        xattr_len: args.xattr_len,
+        hole_len: args.hole_len,
+        copy: args.copy,
+        versioned: args.versioned,
+        connection_phases: args.connection_phases,
+        probe_visibility: args.probe_visibility,
+        cross_read: args.cross_read,
+        probe_delete_visibility: args.probe_delete_visibility,
+        progress: !args.ci,
+        shared_keys: args.shared_keys,
+        namespaces: args.namespaces.unwrap_or(defaults.namespaces),
+        scrub_ratio: args.scrub,
+        verify_at_end: args.verify_at_end,
+        max_error_rate: args.max_error_rate.unwrap_or(defaults.max_error_rate),
+        stall_factor: args.stall_factor,
+        stall_secs: args.stall_secs.unwrap_or(defaults.stall_secs),
+        objectives: args.objectives(),
+        tenants: args.tenants.clone(),
+        priority_ratio: args.priority_ratio,
+        hooks: args.hooks.clone(),
+        live: args.live,
+        prometheus: args.prometheus,
+        calibrate: args.calibrate,
+        subtract_overhead: args.subtract_overhead,
+        space_interval: args.space_interval,
+        health_interval: args.health_interval,
+        keep_data: args.keep_data,
+        checkpoint: args.checkpoint.clone(),
+        ..defaults
+    };
+    if let Some(path) = &args.grafana_dashboard {
+        options.grafana_dashboard = path.clone();
+    }
+
+    // The first interrupt aborts the run cleanly, the second one exits.
+    let cancel = CancellationToken::new();
+    let interrupted = cancel.clone();
+    tokio::spawn(async move {
+        if signal::ctrl_c().await.is_ok() {
+            eprintln!("INTERRUPTED: draining requests, interrupt again to exit");
+            interrupted.cancel();
+        }
+        if signal::ctrl_c().await.is_ok() {
+            process::exit(EXIT_INTERRUPTED);
+        }
+    });
+
+    // Run the same workload against every backend in turn.
+    let mut targets = args.targets();
+    if targets.is_empty() {
+        eprintln!("HARNESS FAILURE: no backend is built in");
+        process::exit(EXIT_HARNESS_FAILURE);
+    }
+    // Start the storage the backends talk to, if asked, and tell them where.
+    let container = match &args.provision_service {
+        Some(service) if args.provision.is_some() => match Container::start(service, args.provision_image.as_deref()).await {
+            Ok(container) => Some(container),
+            Err(err) => {
+                eprintln!("HARNESS FAILURE: {}", err);
+                process::exit(EXIT_HARNESS_FAILURE);
+            }
+        },
+        _ => None,
+    };
+    if let Some(container) = &container {
+        for target in &mut targets {
+            target.settings.extend(container.settings());
+        }
+    }
+    // Every backend's results go into a directory of its own, named by when
+    // the run started.
+    let started_at = Utc::now();
+    let results_dir = args.results_dir();
+    let label = args.label.as_deref();
+    let mut summaries = vec![];
+    for (i, target) in targets.iter().enumerate() {
+        let mut options = options.clone();
+        if let Some(cold) = target.cold {
+            options.cold_reads = cold;
+            options.drop_caches &= cold;
+        }
+        let run_dir = match results::create_run_dir(&results_dir, &results::run_name(&target.label, &started_at, label)) {
+            Ok(dir) => dir,
+            Err(err) => {
+                eprintln!("HARNESS FAILURE: {}", err);
+                Container::exit(container.as_ref(), EXIT_HARNESS_FAILURE);
+            }
+        };
+        let mut images_dir = args.images_dir.clone();
+        let mut summary_path = args.summary_path();
+        let mut request_log = args.request_log.clone();
+        let mut parquet_dir = args.parquet_dir.clone();
+        if targets.len() > 1 {
+            println!("BACKEND:         {}", target.label);
+            options.label = Some(target.label.clone());
+            images_dir = images_dir.map(|dir| dir.join(&target.label));
+            summary_path = summary_path.map(|path| per_backend(&path, &target.label));
+            request_log = request_log.map(|path| per_backend(&path, &target.label));
+            parquet_dir = parquet_dir.map(|dir| dir.join(&target.label));
+            options.checkpoint = options.checkpoint.map(|path| per_backend(&path, &target.label));
+        }
+        let artifacts = Artifacts {
+            images_dir: images_dir.unwrap_or_else(|| run_dir.join("images")),
+            html_charts: args.html_charts,
+            summary: Some(summary_path.unwrap_or_else(|| run_dir.join("summary.json"))),
+            request_log,
+            parquet_dir,
+        };
+        options.images_dir = artifacts.images_dir.clone();
+        options.profile_dir = args.profile.then(|| run_dir.join("profiles"));
+        if i > 0 {
+            // The exporters keep their addresses until the process exits, so
+            // only the first backend is exported.
+            options.live = None;
+            options.prometheus = None;
+        }
+        let summary = match test(target, &args, options, &artifacts, cancel.clone()).await {
+            Ok(summary) => summary,
+            Err(err) => {
+                eprintln!("HARNESS FAILURE: {}", redact(&err.to_string()));
+                Container::exit(container.as_ref(), EXIT_HARNESS_FAILURE);
+            }
+        };
+
+        println!("VERDICT: {:?}", summary.verdict);
+        let mut regressions = vec![];
+        if let Some(history_db) = &args.history_db {
+            let appended = History::open(history_db).and_then(|mut history| {
+                let id = history.append(&summary)?;
+                if let Some(threshold) = args.regression {
+                    regressions = history.regressions(id, DEFAULT_BASELINE_RUNS, threshold)?;
+                }
+                Ok(id)
+            });
+            match appended {
+                Ok(id) => println!("  HISTORY:       run {} in {}", id, history_db.display()),
+                Err(err) => eprintln!("  HISTORY:       {}", err),
+            }
+        }
+        for regression in &regressions {
+            println!("  REGRESSION:    {}", regression);
+        }
+        let notification = notify::Notification::new(&target.label, &summary, &regressions);
+        if !args.webhooks.is_empty() && (notification.violated || !args.webhook_only_violations) {
+            match ProxyConfig::from_settings(&target.settings) {
+                Ok(proxy) => notify::send(&args.webhooks, &notification, proxy.as_ref()).await,
+                Err(err) => eprintln!("  WEBHOOK:       {}", err),
+            }
+        }
+        match Manifest::new(&target.label, label, &summary, &artifacts).write_to(&run_dir) {
+            Ok(path) => println!("  MANIFEST:      {}", path.display()),
+            Err(err) => eprintln!("  MANIFEST:      {}", err),
+        }
+        summaries.push(summary);
+    }
+    if summaries.len() > 1 {
+        let dir = match &args.images_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => results::create_run_dir(&results_dir, &results::run_name("compare", &started_at, label)),
+        };
+        if let Err(err) = dir.and_then(|dir| iotest_report::compare_backends(&summaries, &dir)) {
+            eprintln!("  COMPARE:       {}", err);
+        }
+    }
+    if args.cold_warm {
+        for pair in summaries.chunks(2) {
+            if let [cold, warm] = pair {
+                iotest_report::compare_cold_warm(cold, warm);
+            }
+        }
+    }
+    if args.ci || !args.objectives.is_empty() {
+        // Exit by the first backend which didn't succeed.
+        let verdict = summaries.iter()
+            .map(|summary| summary.verdict)
+            .find(|verdict| *verdict != Verdict::Success)
+            .unwrap_or(Verdict::Success);
+        Container::exit(container.as_ref(), verdict.exit_code());
+    }
+    if let Some(container) = &container {
+        container.remove();
+    }
+}
+
+/// Test the backend with the options, printing the results and writing them
+/// as the artifacts.
+async fn test(target: &Target, args: &RunArgs, options: Options, artifacts: &Artifacts, cancel: CancellationToken) -> Result<Summary> {
+    let client = backends::builtin().create(&target.backend, &target.settings)?;
+    let client: Box<dyn TestClient> = match args.fault() {
+        Some(fault) => Box::new(FaultClient::new(client, fault, options.seed)),
+        None => client,
+    };
+    let client: Box<dyn TestClient> = match args.network() {
+        Some(network) => Box::new(NetworkClient::new(client, network, options.seed)),
+        None => client,
+    };
+    let client = with_compression(client, args)?;
+    let mut tester = Tester::with_options(client, options)
+        .with_cancellation(cancel)
+        .with_reporter(ConsoleReporter)
+        .with_reporter(ChartReporter { dir: artifacts.images_dir.clone() });
+    if artifacts.html_charts {
+        tester = tester.with_reporter(HtmlReporter { dir: artifacts.images_dir.clone() });
+    }
+    if let Some(path) = &artifacts.summary {
+        tester = tester.with_reporter(JsonReporter { path: path.clone() });
+    }
+    if let Some(path) = &artifacts.request_log {
+        tester = tester.with_reporter(RequestLogReporter::create(path.clone())?);
+    }
+    if let Some(dir) = &artifacts.parquet_dir {
+        tester = with_parquet(tester, dir.clone())?;
+    }
+    let report = tester.test().await?;
+    Ok(report.summary)
+}
+
+/// Compress the values of the client, if `--compression`.
+#[cfg(feature = "compression")]
+fn with_compression(client: Box<dyn TestClient>, args: &RunArgs) -> Result<Box<dyn TestClient>> {
+    let Some(name) = &args.compression else {
+        return Ok(client);
+    };
+    let codec = Codec::ALL.into_iter()
+        .find(|codec| codec.name() == name)
+        .ok_or_else(|| Error::new(format!("--compression: unknown codec: {}", name)))?;
+    Ok(Box::new(CompressionClient::new(client, CompressionConfig { codec, level: args.compression_level })))
+}
+
+#[cfg(not(feature = "compression"))]
+fn with_compression(client: Box<dyn TestClient>, args: &RunArgs) -> Result<Box<dyn TestClient>> {
+    match args.compression {
+        Some(_) => Err(Error::new("--compression: iotest is built without the compression feature")),
+        None => Ok(client),
+    }
+}
+
+/// Export the results of the tester as Parquet into the directory.
+#[cfg(feature = "parquet")]
+fn with_parquet<C: TestClient>(tester: Tester<C>, dir: PathBuf) -> Result<Tester<C>> {
+    Ok(tester.with_reporter(iotest_report::ParquetReporter::new(dir)))
+}
+
+#[cfg(not(feature = "parquet"))]
+fn with_parquet<C: TestClient>(_tester: Tester<C>, _dir: PathBuf) -> Result<Tester<C>> {
+    Err(Error::new("--parquet-dir: iotest is built without the parquet feature"))
+}
+
+/// The path with the backend name added to the file name, e.g.
+/// `iotest-summary-localfs.json`.
+fn per_backend(path: &Path, backend: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(ext) => format!("{}-{}.{}", stem, backend, ext.to_string_lossy()),
+        None => format!("{}-{}", stem, backend),
+    };
+    path.with_file_name(name)
+}
+
+async fn probe(args: &ProbeArgs) -> Result<probe::ProbeReport> {
+    let registry = backends::builtin();
+    let backend = args.backend.as_deref().or(registry.default_name())
+        .ok_or_else(|| Error::new("no backend is built in"))?;
+    let client = registry.create(backend, &args.settings())?;
+    probe::probe(client, args).await
+}
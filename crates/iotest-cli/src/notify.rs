@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use iotest_core::{ProxyConfig, Summary, Verdict};
+
+use crate::history::Regression;
+
+/// How long a webhook may take to answer.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Notification is what the webhooks are POSTed when the test of a backend
+/// finishes. `text` is what chat webhooks, e.g. Slack's, show.
+#[derive(Serialize)]
+pub struct Notification<'a> {
+    pub text: String,
+    pub backend: &'a str,
+    pub verdict: Verdict,
+    pub violated: bool,
+    pub regressions: &'a [Regression],
+    pub summary: &'a Summary,
+}
+
+impl<'a> Notification<'a> {
+    pub fn new(backend: &'a str, summary: &'a Summary, regressions: &'a [Regression]) -> Self {
+        let violated = summary.verdict != Verdict::Success || !regressions.is_empty();
+        let mut text = format!("iotest run of {} on {} finished: {:?}", backend, summary.metadata.hostname, summary.verdict);
+        for regression in regressions {
+            text += &format!("\nregressed: {}", regression);
+        }
+        Self { text, backend, verdict: summary.verdict, violated, regressions, summary }
+    }
+}
+
+/// POST the notification to every webhook, by the proxy if any, telling which
+/// failed.
+pub async fn send(webhooks: &[String], notification: &Notification<'_>, proxy: Option<&ProxyConfig>) {
+    let mut builder = reqwest::Client::builder().timeout(WEBHOOK_TIMEOUT);
+    if let Some(proxy) = proxy {
+        match reqwest::Proxy::all(&proxy.url) {
+            Ok(all) => builder = builder.proxy(all.no_proxy(proxy.no_proxy.as_deref().and_then(reqwest::NoProxy::from_string))),
+            Err(err) => {
+                eprintln!("  WEBHOOK:       {}", err);
+                return;
+            }
+        }
+        println!("  PROXY:         {}", proxy.describe());
+    }
+    let client = match builder.build() {
+        Ok(client) => client,
+        Err(err) => {
+            eprintln!("  WEBHOOK:       {}", err);
+            return;
+        }
+    };
+    for url in webhooks {
+        let sent = client.post(url)
+            .json(notification)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match sent {
+            Ok(response) => println!("  WEBHOOK:       {} {}", url, response.status()),
+            Err(err) => eprintln!("  WEBHOOK:       {}: {}", url, err),
+        }
+    }
+}
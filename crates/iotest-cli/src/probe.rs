@@ -0,0 +1,187 @@
+use std::{fmt, time::Duration};
+
+use tokio::time::Instant;
+
+use iotest_core::{Capabilities, Capability, Error, HandlerContext, Result, TestClient, TestClientHandler};
+
+use crate::args::ProbeArgs;
+
+/// Limit is a practical limit of a backend found by probing.
+pub enum Limit {
+    /// The largest working size.
+    Found(usize),
+    /// Everything worked up to the max probed.
+    AtLeast(usize),
+    /// The backend can't tell, e.g. it can't list keys.
+    Unknown(String),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Found(n) => write!(f, "{}", n),
+            Limit::AtLeast(n) => write!(f, ">= {} (max probed)", n),
+            Limit::Unknown(reason) => write!(f, "unknown ({})", reason),
+        }
+    }
+}
+
+/// ProbeReport is what probing a backend results in.
+pub struct ProbeReport {
+    pub backend: &'static str,
+    /// The operations supported besides writing and reading.
+    pub capabilities: Capabilities,
+    pub max_value_len: Limit,
+    pub max_key_len: Limit,
+    /// The keys under a prefix until listing them takes longer than the SLO.
+    pub max_keys_per_prefix: Limit,
+}
+
+impl ProbeReport {
+    pub fn print(&self) {
+        println!("CAPABILITIES:");
+        println!("  BACKEND:       {}", self.backend);
+        let mut names = vec!["write", "read"];
+        names.extend(self.capabilities.iter().map(|capability| capability.name()));
+        println!("  SUPPORTS:      {}", names.join(", "));
+        println!("  MAX VALUE LEN: {}", self.max_value_len);
+        println!("  MAX KEY LEN:   {}", self.max_key_len);
+        println!("  MAX KEYS:      {}", self.max_keys_per_prefix);
+    }
+}
+
+/// Probe the practical limits of the backend by binary searches, using the
+/// operations of the tester.
+pub async fn probe<C>(client: C, args: &ProbeArgs) -> Result<ProbeReport> where C: TestClient {
+    client.init();
+    let hdlr = client.handler(&HandlerContext::default());
+    hdlr.setup().await?;
+    let probed = probe_limits(&client, &*hdlr, args).await;
+    hdlr.teardown().await?;
+    probed
+}
+
+async fn probe_limits<C>(client: &C, hdlr: &dyn TestClientHandler, args: &ProbeArgs) -> Result<ProbeReport> where C: TestClient {
+    println!("PROBE VALUE LEN:");
+    let key = &client.gen_unique_key();
+    let max_value_len = search(1, args.max_value_len, move |len| {
+        async move {
+            let works = round_trip(hdlr, key, &"x".repeat(len)).await;
+            println!("  {:>12} bytes: {}", len, if works { "ok" } else { "failed" });
+            works
+        }
+    }).await?;
+
+    println!("PROBE KEY LEN:");
+    let base = client.gen_unique_key();
+    let max_key_len = search(base.len(), args.max_key_len, move |len| {
+        let key = format!("{}{}", base, "k".repeat(len - base.len()));
+        async move {
+            let works = round_trip(hdlr, &key, "x").await;
+            println!("  {:>12} bytes: {}", len, if works { "ok" } else { "failed" });
+            works
+        }
+    }).await?;
+
+    println!("PROBE KEYS PER PREFIX:");
+    let prefix = format!("{}-", client.gen_unique_key());
+    let max_keys_per_prefix = probe_keys(hdlr, &prefix, args.max_keys, args.list_slo).await;
+
+    Ok(ProbeReport {
+        backend: client.name(),
+        capabilities: hdlr.capabilities(),
+        max_value_len,
+        max_key_len,
+        max_keys_per_prefix,
+    })
+}
+
+/// Binary search the largest working size in `min..=max`, given that larger
+/// sizes only fail more.
+async fn search<F, Fut>(min: usize, max: usize, mut works: F) -> Result<Limit>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: std::future::Future<Output = bool>,
+{
+    if !works(min).await {
+        return Err(Error::new(format!("probe: even {} fails", min)));
+    }
+    if max <= min || works(max).await {
+        return Ok(Limit::AtLeast(max.max(min)));
+    }
+    let (mut good, mut bad) = (min, max);
+    while bad - good > 1 {
+        let mid = good + (bad - good) / 2;
+        if works(mid).await {
+            good = mid;
+        } else {
+            bad = mid;
+        }
+    }
+    Ok(Limit::Found(good))
+}
+
+/// Write the value, read it back and delete it. Return whether all of them
+/// worked.
+async fn round_trip(hdlr: &dyn TestClientHandler, key: &str, value: &str) -> bool {
+    if hdlr.write(key, value).await.is_err() {
+        return false;
+    }
+    let read = hdlr.read(key).await;
+    let _ = hdlr.delete(key).await;
+    read.map_or(false, |read| read == value)
+}
+
+/// Write keys under the prefix, doubling their count, until listing them
+/// takes longer than the SLO or returns a wrong count. The keys are deleted
+/// at the end.
+async fn probe_keys(hdlr: &dyn TestClientHandler, prefix: &str, max: usize, slo: Duration) -> Limit {
+    if !hdlr.capabilities().contains(Capability::List) {
+        return Limit::Unknown("list not supported".to_string());
+    }
+    if let Err(err) = hdlr.list(prefix).await {
+        return Limit::Unknown(err.to_string());
+    }
+    let mut written = 0;
+    let mut good = 0;
+    let mut limit = None;
+    let mut target = 1;
+    while limit.is_none() {
+        while written < target {
+            if let Err(err) = hdlr.write(&format!("{}{}", prefix, written), "x").await {
+                println!("  {:>12} keys: write failed: {}", written, err);
+                limit = Some(Limit::Found(good));
+                break;
+            }
+            written += 1;
+        }
+        if limit.is_some() {
+            break;
+        }
+        let list_start = Instant::now();
+        let listed = hdlr.list(prefix).await;
+        let elapsed = list_start.elapsed();
+        match listed {
+            Ok(keys) if keys.len() == written && elapsed <= slo => {
+                println!("  {:>12} keys: listed in {:?}", written, elapsed);
+                good = written;
+            }
+            Ok(keys) => {
+                println!("  {:>12} keys: listed {} in {:?}", written, keys.len(), elapsed);
+                limit = Some(Limit::Found(good));
+            }
+            Err(err) => {
+                println!("  {:>12} keys: list failed: {}", written, err);
+                limit = Some(Limit::Found(good));
+            }
+        }
+        if written >= max {
+            limit.get_or_insert(Limit::AtLeast(written));
+        }
+        target = (target * 2).min(max);
+    }
+    for idx in 0..written {
+        let _ = hdlr.delete(&format!("{}{}", prefix, idx)).await;
+    }
+    limit.unwrap()
+}
@@ -0,0 +1,132 @@
+use std::{process::{self, Command as StdCommand}, time::Duration};
+
+use tokio::{net::TcpStream, process::Command, time::{sleep, Instant}};
+
+use iotest_core::{Error, Result, Settings};
+
+/// The services a container can be provisioned of.
+pub const SERVICES: [&str; 3] = ["redis", "minio", "postgres"];
+
+/// How long a provisioned service may take to listen.
+const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Service is how to run a storage in a container.
+struct Service {
+    image: &'static str,
+    port: u16,
+    env: &'static [(&'static str, &'static str)],
+    args: &'static [&'static str],
+}
+
+fn service(name: &str) -> Option<Service> {
+    match name {
+        "redis" => Some(Service { image: "redis:7", port: 6379, env: &[], args: &[] }),
+        "minio" => Some(Service {
+            image: "minio/minio",
+            port: 9000,
+            env: &[("MINIO_ROOT_USER", "iotest"), ("MINIO_ROOT_PASSWORD", "iotest-secret")],
+            args: &["server", "/data"],
+        }),
+        "postgres" => Some(Service {
+            image: "postgres:16",
+            port: 5432,
+            env: &[("POSTGRES_USER", "iotest"), ("POSTGRES_PASSWORD", "iotest"), ("POSTGRES_DB", "iotest")],
+            args: &[],
+        }),
+        _ => None,
+    }
+}
+
+/// Container is a storage provisioned for the test, removed by `remove`.
+pub struct Container {
+    id: String,
+    addr: String,
+    env: &'static [(&'static str, &'static str)],
+}
+
+impl Container {
+    /// Run the service in a container on a local port, and wait until it
+    /// listens.
+    pub async fn start(name: &str, image: Option<&str>) -> Result<Self> {
+        let service = service(name).ok_or_else(|| Error::new(format!("provision: unknown service: {}", name)))?;
+        let image = image.unwrap_or(service.image);
+        println!("PROVISION:");
+        println!("  IMAGE:         {}", image);
+        let mut command = Command::new("docker");
+        command.args(["run", "--detach", "--rm", "--label", "iotest=provisioned"])
+            .arg("--publish").arg(format!("127.0.0.1::{}", service.port));
+        for (name, value) in service.env {
+            command.arg("--env").arg(format!("{}={}", name, value));
+        }
+        let id = docker(command.arg(image).args(service.args)).await?;
+        let mut container = Self { id, addr: String::new(), env: service.env };
+        let addr = docker(Command::new("docker").arg("port").arg(&container.id).arg(format!("{}/tcp", service.port))).await;
+        // The first line is of IPv4, if it also listens on IPv6.
+        container.addr = match addr {
+            Ok(addr) => addr.lines().next().unwrap_or_default().to_string(),
+            Err(err) => {
+                container.remove();
+                return Err(err);
+            }
+        };
+        println!("  CONTAINER:     {}", container.id.get(..12).unwrap_or(&container.id));
+        println!("  ADDR:          {}", container.addr);
+        if let Err(err) = container.wait_ready().await {
+            container.remove();
+            return Err(err);
+        }
+        Ok(container)
+    }
+
+    async fn wait_ready(&self) -> Result<()> {
+        let deadline = Instant::now() + READY_TIMEOUT;
+        loop {
+            match TcpStream::connect(&self.addr).await {
+                Ok(_) => return Ok(()),
+                Err(err) if Instant::now() >= deadline => {
+                    return Err(Error::from_io_error(&format!("provision: {} not ready in {:?}", self.addr, READY_TIMEOUT), err));
+                }
+                Err(_) => sleep(Duration::from_millis(500)).await,
+            }
+        }
+    }
+
+    /// The settings telling a plugin where the storage is, as environment
+    /// variables.
+    pub fn settings(&self) -> Settings {
+        let mut settings = Settings::new();
+        settings.insert("env:IOTEST_BACKEND_ADDR".to_string(), self.addr.clone());
+        for (name, value) in self.env {
+            settings.insert(format!("env:{}", name), value.to_string());
+        }
+        settings
+    }
+
+    /// Remove the container, blocking, so it is done even on the way out of
+    /// the process.
+    pub fn remove(&self) {
+        match StdCommand::new("docker").args(["rm", "--force", &self.id]).output() {
+            Ok(output) if output.status.success() => println!("  PROVISIONED:   removed"),
+            Ok(output) => eprintln!("  PROVISIONED:   {}", String::from_utf8_lossy(&output.stderr).trim()),
+            Err(err) => eprintln!("  PROVISIONED:   {}", err),
+        }
+    }
+
+    /// Remove the container, and exit with the code.
+    pub fn exit(container: Option<&Self>, code: i32) -> ! {
+        if let Some(container) = container {
+            container.remove();
+        }
+        process::exit(code)
+    }
+}
+
+/// Run the docker command, returning what it printed.
+async fn docker(command: &mut Command) -> Result<String> {
+    let output = command.output().await
+        .map_err(|err| Error::from_io_error("run docker", err))?;
+    if !output.status.success() {
+        return Err(Error::new(format!("docker: {}", String::from_utf8_lossy(&output.stderr).trim())));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
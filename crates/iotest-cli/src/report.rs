@@ -0,0 +1,55 @@
+use iotest_core::{Result, Summary};
+
+use crate::args::{CompareArgs, ReportArgs};
+
+/// Print a summary written by `run --summary`.
+pub fn show(args: &ReportArgs) -> Result<()> {
+    let summary = Summary::read_from(&args.summary)?;
+    let metadata = &summary.metadata;
+    println!("REPORT:");
+    println!("  BACKEND:       {}", metadata.backend);
+    for (key, value) in &metadata.backend_details {
+        println!("    {:<13}{}", format!("{}:", key.replace('_', " ").to_uppercase()), value);
+    }
+    println!("  HOSTNAME:      {}", metadata.hostname);
+    println!("  STARTED AT:    {}", metadata.started_at);
+    println!("  COMMIT:        {}", metadata.iotest_commit);
+    println!("  VERDICT:       {:?}", summary.verdict);
+    summary.print_table();
+    Ok(())
+}
+
+/// Compare the p50 and p99 latencies of every step and metric in both
+/// summaries.
+pub fn compare(args: &CompareArgs) -> Result<()> {
+    let base = Summary::read_from(&args.base)?;
+    let new = Summary::read_from(&args.new)?;
+    println!("COMPARE:");
+    println!("  BASE:          {} ({}, {:?})", args.base.display(), base.metadata.started_at, base.verdict);
+    println!("  NEW:           {} ({}, {:?})", args.new.display(), new.metadata.started_at, new.verdict);
+    println!("  {:>6} {:<20} {:>21} {:>21} {:>8}", "QPS", "METRIC", "P50 (BASE -> NEW)", "P99 (BASE -> NEW)", "P99 +/-");
+    for new_step in &new.steps {
+        let Some(base_step) = base.steps.iter().find(|step| step.qps == new_step.qps) else {
+            continue;
+        };
+        for new_metric in &new_step.metrics {
+            let Some(base_metric) = base_step.metrics.iter().find(|metric| metric.metric == new_metric.metric) else {
+                continue;
+            };
+            let change = if base_metric.p99_us == 0 {
+                0.0
+            } else {
+                (new_metric.p99_us as f64 / base_metric.p99_us as f64 - 1.0) * 100.0
+            };
+            println!("  {:>6} {:<20} {:>21} {:>21} {:>+7.1}%",
+                new_step.qps,
+                new_metric.metric.name(),
+                format!("{} -> {}", base_metric.p50_us, new_metric.p50_us),
+                format!("{} -> {}", base_metric.p99_us, new_metric.p99_us),
+                change,
+            );
+        }
+    }
+    println!("  (latencies in µs)");
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use std::{fs, io::ErrorKind, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use iotest_core::{redact_args, Error, Result, Summary, Verdict};
+
+/// Artifacts are where the results of testing a backend go.
+#[derive(Debug, Clone, Serialize)]
+pub struct Artifacts {
+    pub images_dir: PathBuf,
+    pub html_charts: bool,
+    pub summary: Option<PathBuf>,
+    pub request_log: Option<PathBuf>,
+    pub parquet_dir: Option<PathBuf>,
+}
+
+/// Manifest tells what a run directory holds and how it came to be, written
+/// as `manifest.json` into it.
+#[derive(Debug, Serialize)]
+pub struct Manifest<'a> {
+    pub backend: &'a str,
+    pub label: Option<&'a str>,
+    pub command: Vec<String>,
+    pub seed: Option<u64>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub verdict: Verdict,
+    pub artifacts: &'a Artifacts,
+}
+
+impl<'a> Manifest<'a> {
+    pub fn new(backend: &'a str, label: Option<&'a str>, summary: &Summary, artifacts: &'a Artifacts) -> Self {
+        Self {
+            backend,
+            label,
+            command: redact_args(&std::env::args().collect::<Vec<_>>()),
+            seed: summary.metadata.options.seed,
+            started_at: summary.metadata.started_at,
+            finished_at: summary.metadata.finished_at,
+            verdict: summary.verdict,
+            artifacts,
+        }
+    }
+
+    pub fn write_to(&self, dir: &Path) -> Result<PathBuf> {
+        let path = dir.join("manifest.json");
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| Error::new(format!("encode the manifest: {}", err)))?;
+        fs::write(&path, json)
+            .map_err(|err| Error::from_io_error(&format!("write {}", path.display()), err))?;
+        Ok(path)
+    }
+}
+
+/// The name of the directory of a run of the backend started at the time,
+/// e.g. `localfs-20240718T093000Z-nvme`.
+pub fn run_name(backend: &str, started_at: &DateTime<Utc>, label: Option<&str>) -> String {
+    let mut name = format!("{}-{}", backend, started_at.format("%Y%m%dT%H%M%SZ"));
+    if let Some(label) = label {
+        name = format!("{}-{}", name, label);
+    }
+    name
+}
+
+/// Create the directory of the name under the results directory, a new one
+/// with a number appended if it already exists, so runs never clobber each
+/// other.
+pub fn create_run_dir(results_dir: &Path, name: &str) -> Result<PathBuf> {
+    fs::create_dir_all(results_dir)
+        .map_err(|err| Error::from_io_error(&format!("mkdir {}", results_dir.display()), err))?;
+    let mut path = results_dir.join(name);
+    for n in 2.. {
+        match fs::create_dir(&path) {
+            Ok(()) => break,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                path = results_dir.join(format!("{}-{}", name, n));
+            }
+            Err(err) => return Err(Error::from_io_error(&format!("mkdir {}", path.display()), err)),
+        }
+    }
+    Ok(path)
+}
@@ -0,0 +1,88 @@
+use std::sync::{atomic::{AtomicUsize, Ordering}, Arc};
+
+use core_affinity::CoreId;
+use tokio::runtime::{Builder, Runtime};
+
+use iotest_core::{Error, Result};
+
+use crate::args::RunArgs;
+
+/// RuntimeOptions is how the runtime issuing requests is tuned, since at high
+/// QPS the generator competes with the backend and the page cache for CPUs.
+#[derive(Debug, Default, Clone)]
+pub struct RuntimeOptions {
+    pub worker_threads: Option<usize>,
+    pub max_blocking_threads: Option<usize>,
+    pub cpus: Option<Vec<usize>>,
+}
+
+impl RuntimeOptions {
+    pub fn of(args: &RunArgs) -> Self {
+        Self {
+            worker_threads: args.worker_threads,
+            max_blocking_threads: args.max_blocking_threads,
+            cpus: args.cpus.clone(),
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        self.worker_threads.is_none() && self.max_blocking_threads.is_none() && self.cpus.is_none()
+    }
+}
+
+/// Build the multi-threaded runtime with the options. Pinned, the threads
+/// take the CPUs one each in turn, and there is a worker per CPU by default.
+pub fn build(options: &RuntimeOptions) -> Result<Runtime> {
+    let mut builder = Builder::new_multi_thread();
+    builder.enable_all();
+    if !options.is_default() {
+        println!("RUNTIME:");
+    }
+    let cpus = match &options.cpus {
+        Some(cpus) => Some(core_ids(cpus)?),
+        None => None,
+    };
+    let worker_threads = options.worker_threads.or(cpus.as_ref().map(Vec::len));
+    if let Some(worker_threads) = worker_threads {
+        if worker_threads == 0 {
+            return Err(Error::new("worker threads must be at least 1"));
+        }
+        builder.worker_threads(worker_threads);
+        println!("  WORKERS:       {}", worker_threads);
+    }
+    if let Some(max_blocking_threads) = options.max_blocking_threads {
+        if max_blocking_threads == 0 {
+            return Err(Error::new("max blocking threads must be at least 1"));
+        }
+        builder.max_blocking_threads(max_blocking_threads);
+        println!("  BLOCKING:      {}", max_blocking_threads);
+    }
+    if let Some(cpus) = cpus {
+        let names: Vec<_> = cpus.iter().map(|cpu| cpu.id.to_string()).collect();
+        println!("  CPUS:          {}", names.join(","));
+        let next = Arc::new(AtomicUsize::new(0));
+        builder.on_thread_start(move || {
+            let cpu = cpus[next.fetch_add(1, Ordering::Relaxed) % cpus.len()];
+            if !core_affinity::set_for_current(cpu) {
+                eprintln!("  PIN FAILED:    cpu {}", cpu.id);
+            }
+        });
+    }
+    builder.build().map_err(|err| Error::from_io_error("build runtime", err))
+}
+
+/// The cores of the CPUs, failing on the ones this machine doesn't have.
+fn core_ids(cpus: &[usize]) -> Result<Vec<CoreId>> {
+    if cpus.is_empty() {
+        return Err(Error::new("no cpus to pin to"));
+    }
+    let available = core_affinity::get_core_ids()
+        .ok_or_else(|| Error::new("pinning threads to cpus is not supported here"))?;
+    cpus.iter()
+        .map(|&cpu| {
+            available.iter().copied()
+                .find(|core| core.id == cpu)
+                .ok_or_else(|| Error::new(format!("no cpu {}", cpu)))
+        })
+        .collect()
+}
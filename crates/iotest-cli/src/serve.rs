@@ -0,0 +1,367 @@
+use std::{fs, io, path::PathBuf, process::Stdio, sync::{Arc, Mutex, PoisonError}};
+
+use serde::{Deserialize, Serialize};
+use tokio::{io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader}, net::{TcpListener, TcpStream}, process::Command, signal, sync::broadcast};
+
+use iotest_core::{redact_args, Error, Result};
+
+use crate::{args::ServeArgs, history::History};
+
+/// The page browsing the results database, drawing the runs picked.
+const UI: &str = include_str!("ui.html");
+
+/// The longest request head read, so a client can't make the server buffer
+/// without end.
+const MAX_HEAD_LEN: usize = 64 * 1024;
+
+/// Server runs tests for HTTP clients, each as an `iotest run` child process
+/// with the results in a directory of its own.
+struct Server {
+    exe: PathBuf,
+    results_dir: PathBuf,
+    history_db: PathBuf,
+    runs: Mutex<Vec<Arc<Run>>>,
+}
+
+/// Run is a test started by a client.
+struct Run {
+    id: usize,
+    args: Vec<String>,
+    dir: PathBuf,
+    pid: Option<u32>,
+    state: Mutex<RunState>,
+    /// Every line the run printed, sent to the event streams as it goes.
+    output: Mutex<Vec<String>>,
+    events: broadcast::Sender<Event>,
+}
+
+#[derive(Clone, Copy, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum RunState {
+    Running,
+    Stopping,
+    Exited { exit_code: Option<i32> },
+}
+
+#[derive(Clone)]
+enum Event {
+    Line(String),
+    Exit(Option<i32>),
+}
+
+/// RunStatus is what the API tells of a run.
+#[derive(Serialize)]
+struct RunStatus<'a> {
+    id: usize,
+    args: &'a [String],
+    #[serde(flatten)]
+    state: RunState,
+}
+
+/// StartRequest is the body of `POST /runs`: the flags of `iotest run`.
+#[derive(Deserialize)]
+struct StartRequest {
+    #[serde(default)]
+    args: Vec<String>,
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Serve the API on the address until interrupted:
+///
+/// - `POST /runs` starts a run with `{"args": [...]}`, the flags of `iotest run`
+/// - `GET /runs` lists the runs, `GET /runs/<ID>` tells one
+/// - `POST /runs/<ID>/stop` interrupts a run, which drains and cleans up
+/// - `GET /runs/<ID>/events` streams what the run prints as server-sent events
+/// - `GET /runs/<ID>/summary` fetches the summaries of a finished run
+/// - `GET /history` lists the runs in the results database, and
+///   `GET /history/<ID>` fetches the summary of one
+/// - `GET /` is a page browsing the results database, overlaying runs
+pub async fn serve(args: &ServeArgs) -> Result<()> {
+    let exe = std::env::current_exe()
+        .map_err(|err| Error::from_io_error("get current exe", err))?;
+    let listener = TcpListener::bind(args.listen).await
+        .map_err(|err| Error::from_io_error(&format!("bind {}", args.listen), err))?;
+    println!("SERVE:");
+    println!("  URL:           http://{}/", args.listen);
+    println!("  RESULTS:       {}", args.results_dir.display());
+    let server = Arc::new(Server {
+        exe,
+        results_dir: args.results_dir.clone(),
+        history_db: args.db.clone(),
+        runs: Mutex::new(vec![]),
+    });
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted
+                    .map_err(|err| Error::from_io_error(&format!("accept on {}", args.listen), err))?;
+                tokio::spawn(handle(stream, server.clone()));
+            }
+            _ = signal::ctrl_c() => {
+                println!("STOPPED");
+                return Ok(());
+            }
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, server: Arc<Server>) {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err(err) => {
+            let _ = respond(&mut stream, "400 Bad Request", "text/plain", err.to_string().as_bytes()).await;
+            return;
+        }
+    };
+    let path = request.path.split('?').next().unwrap_or_default();
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    let run = segments.get(1).and_then(|id| id.parse().ok()).and_then(|id| server.run(id));
+    let _ = match (request.method.as_str(), segments.as_slice(), run) {
+        ("GET", [""], _) => respond(&mut stream, "200 OK", "text/html; charset=utf-8", UI.as_bytes()).await,
+        ("GET", ["history"], _) => match History::open(&server.history_db).and_then(|history| history.entries()) {
+            Ok(entries) => respond_json(&mut stream, "200 OK", &entries).await,
+            Err(err) => respond(&mut stream, "500 Internal Server Error", "text/plain", err.to_string().as_bytes()).await,
+        },
+        ("GET", ["history", id], _) => {
+            let summary = id.parse()
+                .map_err(|_| Error::new(format!("bad run ID: {}", id)))
+                .and_then(|id| History::open(&server.history_db)?.summary_json(id));
+            match summary {
+                Ok(Some(json)) => respond(&mut stream, "200 OK", "application/json", json.as_bytes()).await,
+                Ok(None) => respond(&mut stream, "404 Not Found", "text/plain", b"no such run").await,
+                Err(err) => respond(&mut stream, "500 Internal Server Error", "text/plain", err.to_string().as_bytes()).await,
+            }
+        }
+        ("GET", ["runs"], _) => {
+            let runs = server.runs.lock().unwrap_or_else(PoisonError::into_inner).clone();
+            let statuses: Vec<_> = runs.iter().map(|run| run.status()).collect();
+            respond_json(&mut stream, "200 OK", &statuses).await
+        }
+        ("POST", ["runs"], _) => match serde_json::from_slice::<StartRequest>(&request.body) {
+            Ok(start) => match server.start(start.args) {
+                Ok(run) => respond_json(&mut stream, "201 Created", &run.status()).await,
+                Err(err) => respond(&mut stream, "500 Internal Server Error", "text/plain", err.to_string().as_bytes()).await,
+            },
+            Err(err) => respond(&mut stream, "400 Bad Request", "text/plain", err.to_string().as_bytes()).await,
+        },
+        ("GET", ["runs", _], Some(run)) => respond_json(&mut stream, "200 OK", &run.status()).await,
+        ("POST", ["runs", _, "stop"], Some(run)) => match run.stop() {
+            Ok(()) => respond_json(&mut stream, "202 Accepted", &run.status()).await,
+            Err(err) => respond(&mut stream, "409 Conflict", "text/plain", err.to_string().as_bytes()).await,
+        },
+        ("GET", ["runs", _, "events"], Some(run)) => stream_events(&mut stream, &run).await,
+        ("GET", ["runs", _, "summary"], Some(run)) => match run.summaries() {
+            Some(json) => respond(&mut stream, "200 OK", "application/json", json.as_bytes()).await,
+            None => respond(&mut stream, "404 Not Found", "text/plain", b"no summary yet").await,
+        },
+        _ => respond(&mut stream, "404 Not Found", "text/plain", b"not found").await,
+    };
+}
+
+impl Server {
+    fn run(&self, id: usize) -> Option<Arc<Run>> {
+        let runs = self.runs.lock().unwrap_or_else(PoisonError::into_inner);
+        runs.iter().find(|run| run.id == id).cloned()
+    }
+
+    /// Start a run with the flags, its results in `<RESULTS>/<ID>/`.
+    fn start(&self, args: Vec<String>) -> Result<Arc<Run>> {
+        let mut runs = self.runs.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = runs.len() + 1;
+        let dir = self.results_dir.join(id.to_string());
+        fs::create_dir_all(&dir)
+            .map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+        let mut child = Command::new(&self.exe)
+            .arg("run")
+            .args(&args)
+            .arg("--results-dir").arg(&dir)
+            .arg("--summary").arg(dir.join("summary.json"))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|err| Error::from_io_error("spawn run", err))?;
+        // The args may hold credentials, e.g. `--backend-opt password=...`.
+        let args = redact_args(&args);
+        println!("RUN {}:          {}", id, args.join(" "));
+        let (events, _) = broadcast::channel(1024);
+        let run = Arc::new(Run {
+            id,
+            args,
+            dir,
+            pid: child.id(),
+            state: Mutex::new(RunState::Running),
+            output: Mutex::new(vec![]),
+            events,
+        });
+        let stdout = tokio::spawn(run.clone().follow(child.stdout.take().unwrap()));
+        let stderr = tokio::spawn(run.clone().follow(child.stderr.take().unwrap()));
+        let exited = run.clone();
+        tokio::spawn(async move {
+            let _ = stdout.await;
+            let _ = stderr.await;
+            let exit_code = child.wait().await.ok().and_then(|status| status.code());
+            println!("RUN {}:          exited with {:?}", exited.id, exit_code);
+            // Under the lock of the output, so an event stream either sees
+            // the run exited or is sent the exit.
+            let _output = exited.output.lock().unwrap_or_else(PoisonError::into_inner);
+            *exited.state.lock().unwrap_or_else(PoisonError::into_inner) = RunState::Exited { exit_code };
+            let _ = exited.events.send(Event::Exit(exit_code));
+        });
+        runs.push(run.clone());
+        Ok(run)
+    }
+}
+
+impl Run {
+    fn status(&self) -> RunStatus {
+        RunStatus { id: self.id, args: &self.args, state: *self.state.lock().unwrap_or_else(PoisonError::into_inner) }
+    }
+
+    /// Keep the lines the run prints, and send them to the event streams.
+    async fn follow(self: Arc<Self>, out: impl AsyncRead + Unpin) {
+        let mut lines = BufReader::new(out).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            let mut output = self.output.lock().unwrap_or_else(PoisonError::into_inner);
+            output.push(line.clone());
+            let _ = self.events.send(Event::Line(line));
+        }
+    }
+
+    /// Interrupt the run, as by Ctrl-C, so it drains and cleans up.
+    fn stop(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let (RunState::Running, Some(pid)) = (*state, self.pid) else {
+            return Err(Error::new(format!("run {} is not running", self.id)));
+        };
+        interrupt(pid)?;
+        *state = RunState::Stopping;
+        Ok(())
+    }
+
+    /// The summaries the run wrote, one per backend, as a JSON array.
+    fn summaries(&self) -> Option<String> {
+        let mut paths: Vec<_> = fs::read_dir(&self.dir).ok()?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.file_name().map_or(false, |name| name.to_string_lossy().starts_with("summary")))
+            .collect();
+        if paths.is_empty() {
+            return None;
+        }
+        paths.sort();
+        let summaries: Vec<_> = paths.iter().filter_map(|path| fs::read_to_string(path).ok()).collect();
+        Some(format!("[{}]", summaries.join(",")))
+    }
+}
+
+#[cfg(unix)]
+fn interrupt(pid: u32) -> Result<()> {
+    // SAFETY: kill only sends a signal to the child.
+    if unsafe { libc::kill(pid as libc::pid_t, libc::SIGINT) } != 0 {
+        return Err(Error::from_io_error(&format!("interrupt {}", pid), io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn interrupt(pid: u32) -> Result<()> {
+    Err(Error::new(format!("interrupt {}: not supported on this platform", pid)))
+}
+
+/// Stream what the run printed so far and prints from now on, until it
+/// exits, as server-sent events.
+async fn stream_events(stream: &mut TcpStream, run: &Run) -> io::Result<()> {
+    let (printed, mut events, state) = {
+        let output = run.output.lock().unwrap_or_else(PoisonError::into_inner);
+        let state = *run.state.lock().unwrap_or_else(PoisonError::into_inner);
+        (output.clone(), run.events.subscribe(), state)
+    };
+    let header = "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: keep-alive\r\n\
+        Access-Control-Allow-Origin: *\r\n\r\n";
+    stream.write_all(header.as_bytes()).await?;
+    for line in printed {
+        stream.write_all(format!("data: {}\n\n", line).as_bytes()).await?;
+    }
+    if let RunState::Exited { exit_code } = state {
+        return stream.write_all(exit_event(exit_code).as_bytes()).await;
+    }
+    loop {
+        match events.recv().await {
+            Ok(Event::Line(line)) => stream.write_all(format!("data: {}\n\n", line).as_bytes()).await?,
+            Ok(Event::Exit(exit_code)) => return stream.write_all(exit_event(exit_code).as_bytes()).await,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+fn exit_event(exit_code: Option<i32>) -> String {
+    format!("event: exit\ndata: {}\n\n", exit_code.map_or("null".to_string(), |code| code.to_string()))
+}
+
+async fn read_request(stream: &mut TcpStream) -> io::Result<Request> {
+    let mut buf = vec![];
+    let mut chunk = [0; 4096];
+    let head_len = loop {
+        if let Some(pos) = buf.windows(4).position(|window| window == b"\r\n\r\n") {
+            break pos;
+        }
+        if buf.len() > MAX_HEAD_LEN {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "request head too long"));
+        }
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed in the request head"));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    };
+    let head = String::from_utf8_lossy(&buf[..head_len]).into_owned();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let content_len = lines
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+        .unwrap_or(0);
+    let mut body = buf.split_off(head_len + 4);
+    while body.len() < content_len {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed in the request body"));
+        }
+        body.extend_from_slice(&chunk[..n]);
+    }
+    Ok(Request { method, path, body })
+}
+
+async fn respond_json(stream: &mut TcpStream, status: &str, value: &impl Serialize) -> io::Result<()> {
+    let body = serde_json::to_vec(value)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    respond(stream, status, "application/json", &body).await
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &[u8]) -> io::Result<()> {
+    let head = format!(
+        "HTTP/1.1 {}\r\n\
+        Content-Type: {}\r\n\
+        Content-Length: {}\r\n\
+        Access-Control-Allow-Origin: *\r\n\
+        Connection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len(),
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await
+}
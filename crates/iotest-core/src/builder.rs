@@ -0,0 +1,329 @@
+use std::{net::SocketAddr, path::PathBuf, sync::Arc, time::Duration};
+
+use super::{client::TestClient, clock::Clock, hooks::Hook, options::Options, report::TestReport, reporter::Reporter, retry::RetryPolicy, slo::Objective, tenant::Tenant, tester::Tester};
+
+/// TesterBuilder builds a Tester option by option, starting from the default
+/// options.
+pub struct TesterBuilder<C> {
+    client: C,
+    options: Options,
+    reporters: Vec<Arc<dyn Reporter>>,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl<C> TesterBuilder<C> where C: TestClient {
+    pub fn new(client: C) -> Self {
+        Self { client, options: Options::default(), reporters: vec![], clock: None }
+    }
+
+    /// Replace all options.
+    pub fn options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The length of the value written to every key.
+    pub fn payload_len(mut self, len: usize) -> Self {
+        self.options.payload_len = len;
+        self
+    }
+
+    /// Draw the length of every value up to `max`, from the payload length.
+    pub fn payload_len_max(mut self, max: usize) -> Self {
+        self.options.payload_len_max = Some(max);
+        self
+    }
+
+    /// Seed the randomness, to issue the same operations as another run.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.options.seed = Some(seed);
+        self
+    }
+
+    /// The QPS of every step, in order.
+    pub fn qps_steps(mut self, steps: impl IntoIterator<Item = u64>) -> Self {
+        self.options.qps_steps = steps.into_iter().collect();
+        self
+    }
+
+    /// Sweep the payload lengths at the first QPS, a step per length.
+    pub fn size_sweep(mut self, lens: impl IntoIterator<Item = usize>) -> Self {
+        self.options.size_sweep = lens.into_iter().collect();
+        self
+    }
+
+    /// Sweep the closed-loop workers, a step per count, e.g. `[1, 2, 4, 8]`.
+    pub fn concurrency_sweep(mut self, workers: impl IntoIterator<Item = usize>) -> Self {
+        self.options.concurrency_sweep = workers.into_iter().collect();
+        self
+    }
+
+    pub fn step_duration(mut self, duration: Duration) -> Self {
+        self.options.step_duration = duration;
+        self
+    }
+
+    /// Warm up at the first QPS for so long before measuring.
+    pub fn warm_up(mut self, duration: Duration) -> Self {
+        self.options.warm_up = Some(duration);
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.options.retry = Some(policy);
+        self
+    }
+
+    /// How many handlers requests of a step share.
+    pub fn pool_size(mut self, size: usize) -> Self {
+        self.options.pool_size = size;
+        self
+    }
+
+    pub fn verify(mut self, verify: bool) -> Self {
+        self.options.verify = verify;
+        self
+    }
+
+    pub fn check_size(mut self, check_size: bool) -> Self {
+        self.options.check_size = check_size;
+        self
+    }
+
+    /// Evict every written key before reading it.
+    pub fn cold_reads(mut self, cold_reads: bool) -> Self {
+        self.options.cold_reads = cold_reads;
+        self
+    }
+
+    /// Drop the page cache before every step, by the command if any.
+    pub fn drop_caches(mut self, command: Option<String>) -> Self {
+        self.options.drop_caches = true;
+        self.options.drop_caches_command = command;
+        self
+    }
+
+    /// Set, get and remove an extended attribute of the length on every
+    /// written key.
+    pub fn xattr_len(mut self, xattr_len: usize) -> Self {
+        self.options.xattr_len = Some(xattr_len);
+        self
+    }
+
+    /// Punch a hole of up to the length in every read key, and read it back.
+    pub fn hole_len(mut self, hole_len: usize) -> Self {
+        self.options.hole_len = Some(hole_len);
+        self
+    }
+
+    /// Copy every read key to another one.
+    pub fn copy(mut self, copy: bool) -> Self {
+        self.options.copy = copy;
+        self
+    }
+
+    /// Write every key as a version, and read the version back after an
+    /// overwrite.
+    pub fn versioned(mut self, versioned: bool) -> Self {
+        self.options.versioned = versioned;
+        self
+    }
+
+    /// Measure the phases of connecting to a network backend.
+    pub fn connection_phases(mut self, connection_phases: bool) -> Self {
+        self.options.connection_phases = connection_phases;
+        self
+    }
+
+    pub fn verify_at_end(mut self, verify_at_end: bool) -> Self {
+        self.options.verify_at_end = verify_at_end;
+        self
+    }
+
+    pub fn scrub_ratio(mut self, ratio: f64) -> Self {
+        self.options.scrub_ratio = Some(ratio);
+        self
+    }
+
+    pub fn shared_keys(mut self, keys: usize) -> Self {
+        self.options.shared_keys = Some(keys);
+        self
+    }
+
+    /// Run so many key namespaces concurrently, each at the QPS of the step.
+    pub fn namespaces(mut self, namespaces: usize) -> Self {
+        self.options.namespaces = namespaces;
+        self
+    }
+
+    /// Run the tenant concurrently with the others added, in a namespace of
+    /// its own.
+    pub fn tenant(mut self, tenant: Tenant) -> Self {
+        self.options.tenants.push(tenant);
+        self
+    }
+
+    /// Tag the ratio of requests as high priority, on handlers of their own.
+    pub fn priority_ratio(mut self, ratio: f64) -> Self {
+        self.options.priority_ratio = Some(ratio);
+        self
+    }
+
+    pub fn probe_visibility(mut self, timeout: Duration) -> Self {
+        self.options.probe_visibility = Some(timeout);
+        self
+    }
+
+    /// Read keys by another handler than the one which wrote them.
+    pub fn cross_read(mut self, cross_read: bool) -> Self {
+        self.options.cross_read = cross_read;
+        self
+    }
+
+    pub fn probe_delete_visibility(mut self, window: Duration) -> Self {
+        self.options.probe_delete_visibility = Some(window);
+        self
+    }
+
+    pub fn progress(mut self, progress: bool) -> Self {
+        self.options.progress = progress;
+        self
+    }
+
+    pub fn max_error_rate(mut self, rate: f64) -> Self {
+        self.options.max_error_rate = rate;
+        self
+    }
+
+    /// Add an objective of the p99 latency of every operation.
+    pub fn slo_p99(mut self, slo: Duration) -> Self {
+        self.options.objectives.push(Objective::p99_within(slo));
+        self
+    }
+
+    /// Add an objective every step must keep, e.g. `write.p99 < 20ms`.
+    pub fn objective(mut self, objective: Objective) -> Self {
+        self.options.objectives.push(objective);
+        self
+    }
+
+    /// Add a hook, keeping the ones added before.
+    pub fn hook(mut self, hook: Hook) -> Self {
+        self.options.hooks.push(hook);
+        self
+    }
+
+    pub fn live(mut self, addr: SocketAddr) -> Self {
+        self.options.live = Some(addr);
+        self
+    }
+
+    pub fn prometheus(mut self, addr: SocketAddr) -> Self {
+        self.options.prometheus = Some(addr);
+        self
+    }
+
+    pub fn grafana_dashboard(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.grafana_dashboard = path.into();
+        self
+    }
+
+    pub fn images_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.images_dir = dir.into();
+        self
+    }
+
+    /// Measure the overhead of the tester for so long at every QPS first.
+    pub fn calibrate(mut self, duration: Duration) -> Self {
+        self.options.calibrate = Some(duration);
+        self
+    }
+
+    /// Report seconds whose p99 is over `factor` times the median, for at
+    /// least `secs` in a row, as stalls.
+    pub fn stalls(mut self, factor: f64, secs: u64) -> Self {
+        self.options.stall_factor = Some(factor);
+        self.options.stall_secs = secs;
+        self
+    }
+
+    /// Sample the space the data takes on the storage at the interval.
+    pub fn space_interval(mut self, every: Duration) -> Self {
+        self.options.space_interval = Some(every);
+        self
+    }
+
+    /// Check the health of the backend at the interval, apart from the load.
+    pub fn health_interval(mut self, every: Duration) -> Self {
+        self.options.health_interval = Some(every);
+        self
+    }
+
+    /// Write a CPU flamegraph of the process per step into the directory,
+    /// with the `profile` feature.
+    pub fn profile_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.options.profile_dir = Some(dir.into());
+        self
+    }
+
+    /// Subtract the calibrated overhead from the latencies.
+    pub fn subtract_overhead(mut self, subtract: bool) -> Self {
+        self.options.subtract_overhead = subtract;
+        self
+    }
+
+    /// Keep the data the test wrote instead of cleaning it up.
+    pub fn keep_data(mut self, keep_data: bool) -> Self {
+        self.options.keep_data = keep_data;
+        self
+    }
+
+    /// Checkpoint the run to the path after every step, resuming from it.
+    pub fn checkpoint(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.checkpoint = Some(path.into());
+        self
+    }
+
+    /// Name the test among the others of a run.
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.options.label = Some(label.into());
+        self
+    }
+
+    /// Report the results to the reporter, keeping the ones added before.
+    pub fn reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.reporters.push(Arc::new(reporter));
+        self
+    }
+
+    /// Take the time from the clock instead of tokio's.
+    pub fn clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub fn build(self) -> Tester<C> {
+        let tester = self.reporters.into_iter().fold(
+            Tester::with_options(self.client, self.options),
+            |tester, reporter| tester.with_reporter(reporter),
+        );
+        match self.clock {
+            Some(clock) => tester.with_clock(clock),
+            None => tester,
+        }
+    }
+
+    /// Build the tester and run the test, for an integration test: a harness
+    /// failure panics, and the report can be asserted on.
+    pub async fn run(self) -> TestReport {
+        match self.build().test().await {
+            Ok(report) => report,
+            Err(err) => panic!("iotest harness failure: {}", err),
+        }
+    }
+}
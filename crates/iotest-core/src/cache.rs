@@ -0,0 +1,26 @@
+use super::{client::{Error, Result}, shell::shell};
+
+/// Drop the page cache of the system, so the next reads come from the
+/// devices: run the command if any, e.g. a helper allowed by sudo, or else
+/// sync and write `/proc/sys/vm/drop_caches` (root required, so only Linux).
+pub async fn drop_page_cache(command: Option<&str>) -> Result<()> {
+    if let Some(command) = command {
+        let status = shell(command).status().await
+            .map_err(|err| Error::from_io_error(&format!("run {}", command), err))?;
+        if !status.success() {
+            return Err(Error::new(format!("drop caches: {} exited with {}", command, status)));
+        }
+        return Ok(());
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(Error::new("drop caches: only supported on Linux, give a command instead"));
+    }
+    // Dirty pages are not dropped, write them back first.
+    let status = shell("sync").status().await
+        .map_err(|err| Error::from_io_error("sync", err))?;
+    if !status.success() {
+        return Err(Error::new(format!("drop caches: sync exited with {}", status)));
+    }
+    tokio::fs::write("/proc/sys/vm/drop_caches", "3").await
+        .map_err(|err| Error::from_io_error("drop caches", err))
+}
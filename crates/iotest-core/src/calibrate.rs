@@ -0,0 +1,87 @@
+use std::{collections::HashSet, sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex, PoisonError}};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{client::{Capabilities, Capability, Error, HandlerContext, Result, TestClient, TestClientHandler}, stats::Metric, summary::MetricSummary};
+
+/// Overhead is the latency the tester itself adds to the operations of a
+/// metric at a QPS (spawning the request, making the value, timing it),
+/// measured against a backend doing nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Overhead {
+    pub metric: Metric,
+    pub p50_us: u64,
+    pub p99_us: u64,
+}
+
+impl Overhead {
+    /// The overhead of the operations of a calibration step.
+    pub fn of(metrics: &[MetricSummary]) -> Vec<Overhead> {
+        metrics.iter()
+            .filter(|summary| matches!(summary.metric, Metric::Op(_)) && summary.count > summary.errors + summary.timeouts)
+            .map(|summary| Overhead { metric: summary.metric, p50_us: summary.p50_us, p99_us: summary.p99_us })
+            .collect()
+    }
+}
+
+/// NoopClient is a backend doing nothing, to calibrate the tester against.
+/// Reads return an empty value, or not found after a delete.
+pub struct NoopClient {
+    auto_increment: AtomicU64,
+    deleted: Arc<Mutex<HashSet<String>>>,
+}
+
+impl NoopClient {
+    pub fn new() -> Self {
+        Self {
+            auto_increment: AtomicU64::new(0),
+            deleted: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+}
+
+impl TestClient for NoopClient {
+    fn name(&self) -> &'static str {
+        "noop"
+    }
+
+    fn gen_unique_key(&self) -> String {
+        format!("noop-{}", self.auto_increment.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn init(&self) {}
+
+    fn handler(&self, _ctx: &HandlerContext) -> Arc<dyn TestClientHandler> {
+        Arc::new(NoopClientHandler { deleted: self.deleted.clone() })
+    }
+}
+
+pub struct NoopClientHandler {
+    /// The keys deleted and not read since, so the read after a delete fails
+    /// as it should.
+    deleted: Arc<Mutex<HashSet<String>>>,
+}
+
+#[async_trait]
+impl TestClientHandler for NoopClientHandler {
+    fn capabilities(&self) -> Capabilities {
+        [Capability::Delete].into_iter().collect()
+    }
+
+    async fn write(&self, _key: &str, _value: &str) -> Result<()> {
+        Ok(())
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        if self.deleted.lock().unwrap_or_else(PoisonError::into_inner).remove(key) {
+            return Err(Error::not_found(format!("read {}: deleted", key)));
+        }
+        Ok(String::new())
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.deleted.lock().unwrap_or_else(PoisonError::into_inner).insert(key.to_string());
+        Ok(())
+    }
+}
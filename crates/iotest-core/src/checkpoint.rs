@@ -0,0 +1,56 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{client::{Error, Result}, summary::StepSummary};
+
+/// Checkpoint is the state of a run after its last finished step: the results
+/// of the steps and what is needed to go on with the data written, so an
+/// interrupted run can be resumed from there instead of starting over.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub seed: u64,
+    pub started_at: DateTime<Utc>,
+    /// The finished steps, in order.
+    pub steps: Vec<StepSummary>,
+    /// The sequence number of the next request.
+    pub seq: u64,
+    /// How many keys the client generated, so a resumed run doesn't generate
+    /// them again.
+    pub keys: u64,
+    /// The keys kept until the end to be scrubbed, with their sequence numbers.
+    pub retained: Vec<(String, u64)>,
+    /// The shared key pool, if requests target the same keys concurrently.
+    pub shared_keys: Option<Vec<String>>,
+}
+
+impl Checkpoint {
+    pub fn new(seed: u64, started_at: DateTime<Utc>) -> Self {
+        Self { seed, started_at, steps: vec![], seq: 0, keys: 0, retained: vec![], shared_keys: None }
+    }
+
+    /// Read the checkpoint at the path, if any.
+    pub fn read(path: &Path) -> Result<Option<Self>> {
+        let json = match fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::from_io_error(&format!("read {}", path.display()), err)),
+        };
+        serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|err| Error::new(format!("parse {}: {}", path.display(), err)))
+    }
+
+    /// Write the checkpoint to the path, replacing the one there at once, so
+    /// an interrupt never leaves half of it.
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string(self)
+            .map_err(|err| Error::new(format!("serialize checkpoint: {}", err)))?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, json)
+            .map_err(|err| Error::from_io_error(&format!("write {}", tmp.display()), err))?;
+        fs::rename(&tmp, path)
+            .map_err(|err| Error::from_io_error(&format!("rename {}", tmp.display()), err))
+    }
+}
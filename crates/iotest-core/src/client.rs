@@ -0,0 +1,523 @@
+use std::{collections::BTreeMap, error, io, result, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use super::stats::Metric;
+
+/// Error is why an operation (or the tester) failed, by kind, so failures
+/// can be classified, retried and reported by their class.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum Error {
+    /// The key doesn't exist.
+    #[error(transparent)]
+    NotFound(ErrorDetail),
+    /// The operation was cancelled by the tester since it took too long.
+    #[error(transparent)]
+    Timeout(ErrorDetail),
+    /// The storage rejected the operation for exceeding its rate limit.
+    #[error(transparent)]
+    Throttled(ErrorDetail),
+    /// The storage can't be reached, or the connection to it broke.
+    #[error(transparent)]
+    Connection(ErrorDetail),
+    /// A local I/O operation failed.
+    #[error(transparent)]
+    Io(ErrorDetail),
+    /// Anything else, e.g. an error reported by the storage.
+    #[error(transparent)]
+    Other(ErrorDetail),
+}
+
+/// ErrorDetail is what is known of an error whatever its kind: the
+/// underlying error if any, and the operation and key it failed on once
+/// known.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("{msg}")]
+pub struct ErrorDetail {
+    msg: String,
+    op: Option<Op>,
+    key: Option<String>,
+    #[source]
+    source: Option<Arc<dyn error::Error + Send + Sync>>,
+}
+
+impl Error {
+    /// An error of the class.
+    pub fn of(class: ErrorClass, msg: impl Into<String>) -> Self {
+        let detail = ErrorDetail {
+            msg: msg.into(),
+            op: None,
+            key: None,
+            source: None,
+        };
+        match class {
+            ErrorClass::NotFound => Self::NotFound(detail),
+            ErrorClass::Timeout => Self::Timeout(detail),
+            ErrorClass::Throttled => Self::Throttled(detail),
+            ErrorClass::Connection => Self::Connection(detail),
+            ErrorClass::Io => Self::Io(detail),
+            ErrorClass::Other => Self::Other(detail),
+        }
+    }
+
+    pub fn new(msg: impl Into<String>) -> Self {
+        Self::of(ErrorClass::Other, msg)
+    }
+
+    pub fn not_found(msg: impl Into<String>) -> Self {
+        Self::of(ErrorClass::NotFound, msg)
+    }
+
+    pub fn timeout(msg: impl Into<String>) -> Self {
+        Self::of(ErrorClass::Timeout, msg)
+    }
+
+    pub fn throttled(msg: impl Into<String>) -> Self {
+        Self::of(ErrorClass::Throttled, msg)
+    }
+
+    pub fn connection(msg: impl Into<String>) -> Self {
+        Self::of(ErrorClass::Connection, msg)
+    }
+
+    /// An error of the kind of the I/O error, e.g. NotFound for a missing
+    /// file or Connection for a broken pipe, caused by it.
+    pub fn from_io_error(prefix: &str, err: io::Error) -> Self {
+        let class = match err.kind() {
+            io::ErrorKind::NotFound => ErrorClass::NotFound,
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe => ErrorClass::Connection,
+            _ => ErrorClass::Io,
+        };
+        Self::of(class, format!("{}: {}", prefix, err)).with_source(err)
+    }
+
+    /// Set the error this one is caused by.
+    pub fn with_source(mut self, source: impl error::Error + Send + Sync + 'static) -> Self {
+        self.detail_mut().source = Some(Arc::new(source));
+        self
+    }
+
+    /// Set the operation and the key the error happened on, unless set.
+    pub fn with_context(mut self, op: Op, key: &str) -> Self {
+        let detail = self.detail_mut();
+        if detail.op.is_none() {
+            detail.op = Some(op);
+            detail.key = Some(key.to_string());
+        }
+        self
+    }
+
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            Self::NotFound(_) => ErrorClass::NotFound,
+            Self::Timeout(_) => ErrorClass::Timeout,
+            Self::Throttled(_) => ErrorClass::Throttled,
+            Self::Connection(_) => ErrorClass::Connection,
+            Self::Io(_) => ErrorClass::Io,
+            Self::Other(_) => ErrorClass::Other,
+        }
+    }
+
+    /// The operation the error happened on, if known.
+    pub fn op(&self) -> Option<Op> {
+        self.detail().op
+    }
+
+    /// The key the error happened on, if known.
+    pub fn key(&self) -> Option<&str> {
+        self.detail().key.as_deref()
+    }
+
+    /// The error is transient, so the same operation may succeed if retried.
+    pub fn is_retryable(&self) -> bool {
+        self.class().is_retryable()
+    }
+
+    /// The key doesn't exist. Only this means a missing key: a read which
+    /// failed otherwise, e.g. by a broken connection, tells nothing about it.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::NotFound(_))
+    }
+
+    fn detail(&self) -> &ErrorDetail {
+        match self {
+            Self::NotFound(detail)
+            | Self::Timeout(detail)
+            | Self::Throttled(detail)
+            | Self::Connection(detail)
+            | Self::Io(detail)
+            | Self::Other(detail) => detail,
+        }
+    }
+
+    fn detail_mut(&mut self) -> &mut ErrorDetail {
+        match self {
+            Self::NotFound(detail)
+            | Self::Timeout(detail)
+            | Self::Throttled(detail)
+            | Self::Connection(detail)
+            | Self::Io(detail)
+            | Self::Other(detail) => detail,
+        }
+    }
+}
+
+/// ErrorClass is a kind of error, to decide whether a retry is worth it and
+/// to count errors by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorClass {
+    /// The key doesn't exist.
+    NotFound,
+    /// The operation was cancelled by the tester.
+    Timeout,
+    /// The storage rejected the operation for exceeding its rate limit.
+    Throttled,
+    /// The storage can't be reached, or the connection to it broke.
+    Connection,
+    /// A local I/O operation failed.
+    Io,
+    /// Any other error, e.g. reported by the storage.
+    Other,
+}
+
+impl ErrorClass {
+    pub const ALL: [ErrorClass; 6] = [
+        ErrorClass::NotFound,
+        ErrorClass::Timeout,
+        ErrorClass::Throttled,
+        ErrorClass::Connection,
+        ErrorClass::Io,
+        ErrorClass::Other,
+    ];
+
+    /// Errors of the class are transient: timeouts, throttling and broken
+    /// connections.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorClass::Timeout | ErrorClass::Throttled | ErrorClass::Connection)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorClass::NotFound => "not_found",
+            ErrorClass::Timeout => "timeout",
+            ErrorClass::Throttled => "throttled",
+            ErrorClass::Connection => "connection",
+            ErrorClass::Io => "io",
+            ErrorClass::Other => "other",
+        }
+    }
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+/// Op is a kind of operation issued to a storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Op {
+    Write,
+    Read,
+    Delete,
+}
+
+impl Op {
+    pub const ALL: [Op; 3] = [Op::Write, Op::Read, Op::Delete];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Op::Write => "write",
+            Op::Read => "read",
+            Op::Delete => "delete",
+        }
+    }
+}
+
+/// Capability is an operation a handler may support, besides writing and
+/// reading which every handler does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Capability {
+    Delete,
+    /// Get the stored size of a object without reading it.
+    Stat,
+    /// List the keys with a prefix.
+    List,
+    /// Append to a object.
+    Append,
+    /// Drop a object from the caches, so the next read comes from the
+    /// device.
+    Evict,
+    /// Set, get and remove extended attributes of a object.
+    Xattr,
+    /// Punch a hole in a object, which then reads as zeros.
+    PunchHole,
+    /// Copy a object to another key, e.g. by a clone of the filesystem or a
+    /// copy on the server.
+    Copy,
+    /// Keep the versions of a object, each written one by an id to read it
+    /// back by, e.g. object versioning or the revisions of a store.
+    Versions,
+}
+
+impl Capability {
+    pub const ALL: [Capability; 9] = [Capability::Delete, Capability::Stat, Capability::List, Capability::Append, Capability::Evict, Capability::Xattr, Capability::PunchHole, Capability::Copy, Capability::Versions];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Capability::Delete => "delete",
+            Capability::Stat => "stat",
+            Capability::List => "list",
+            Capability::Append => "append",
+            Capability::Evict => "evict",
+            Capability::Xattr => "xattr",
+            Capability::PunchHole => "punch_hole",
+            Capability::Copy => "copy",
+            Capability::Versions => "versions",
+        }
+    }
+}
+
+/// Capabilities is a set of capabilities.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities(u16);
+
+impl Capabilities {
+    pub fn contains(&self, capability: Capability) -> bool {
+        self.0 & (1 << capability as u16) != 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Capability> + '_ {
+        Capability::ALL.into_iter().filter(|capability| self.contains(*capability))
+    }
+}
+
+impl FromIterator<Capability> for Capabilities {
+    fn from_iter<I: IntoIterator<Item = Capability>>(iter: I) -> Self {
+        Self(iter.into_iter().fold(0, |bits, capability| bits | 1 << capability as u16))
+    }
+}
+
+/// HandlerContext is what a handler is created for.
+#[derive(Debug, Clone)]
+pub struct HandlerContext {
+    /// The index of the handler in its pool.
+    pub worker: usize,
+    /// The number of handlers in the pool.
+    pub workers: usize,
+    /// The timeout of every operation, if any, which the handler may pass to
+    /// the storage.
+    pub timeout: Option<Duration>,
+    /// Cancelled when the run is aborted, so the handler can give up long
+    /// operations.
+    pub cancel: CancellationToken,
+    /// Measure the phases of connecting and of requests, for `take_phases`,
+    /// e.g. by a `PhaseTimer`.
+    pub connection_phases: bool,
+    /// The handler serves the high priority requests, which it may hint to
+    /// the storage, e.g. by the QoS class of its connection.
+    pub priority: bool,
+}
+
+impl Default for HandlerContext {
+    /// The context of a lone handler.
+    fn default() -> Self {
+        Self { worker: 0, workers: 1, timeout: None, cancel: CancellationToken::new(), connection_phases: false, priority: false }
+    }
+}
+
+/// TestClient is used to talk with a storage. It is object safe, so the
+/// backend can be chosen at runtime as a `Box<dyn TestClient>`.
+pub trait TestClient: Send + Sync {
+    /// The name of the storage backend, e.g. "localfs".
+    fn name(&self) -> &'static str;
+
+    /// Generate an unique key to write / read / delete object. It is called
+    /// for every request, so it should not lock.
+    fn gen_unique_key(&self) -> String;
+
+    /// Generate an unique key in the namespace, e.g. under a prefix or in a
+    /// bucket of its name. By default the namespace is a prefix of the key.
+    fn gen_namespaced_key(&self, namespace: &str) -> String {
+        format!("{}/{}", namespace, self.gen_unique_key())
+    }
+
+    /// Init the client.
+    fn init(&self);
+
+    /// Remove the data the test wrote, after it. Nothing by default.
+    fn cleanup(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Details of the storage to record in the report, e.g. the filesystem
+    /// a directory is on. Asked after the init.
+    fn describe(&self) -> BTreeMap<String, String> {
+        BTreeMap::new()
+    }
+
+    /// Create a handler for a worker. What to connect to (endpoints,
+    /// credentials, ...) belongs to the client, which got it from its
+    /// settings; the context is where the handler runs.
+    fn handler(&self, ctx: &HandlerContext) -> Arc<dyn TestClientHandler>;
+}
+
+impl TestClient for Box<dyn TestClient> {
+    fn name(&self) -> &'static str {
+        (**self).name()
+    }
+
+    fn gen_unique_key(&self) -> String {
+        (**self).gen_unique_key()
+    }
+
+    fn gen_namespaced_key(&self, namespace: &str) -> String {
+        (**self).gen_namespaced_key(namespace)
+    }
+
+    fn init(&self) {
+        (**self).init()
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        (**self).cleanup()
+    }
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        (**self).describe()
+    }
+
+    fn handler(&self, ctx: &HandlerContext) -> Arc<dyn TestClientHandler> {
+        (**self).handler(ctx)
+    }
+}
+
+#[async_trait]
+pub trait TestClientHandler: Send + Sync {
+    /// What the handler supports besides writing and reading. The tester skips
+    /// the operations a handler doesn't support, which report an error by
+    /// default. It is asked after the setup.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Set the handler up before its first request, e.g. connect and
+    /// authenticate. A handler serves many requests, so the setup is once per
+    /// handler rather than per operation.
+    async fn setup(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Release what the setup took, after the last request of the handler.
+    async fn teardown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// The phases measured since last asked, e.g. `Metric::Dns`, if
+    /// `HandlerContext::connection_phases`. The tester asks after every
+    /// request, and records them with its other metrics. None by default.
+    fn take_phases(&self) -> Vec<(Metric, Duration)> {
+        vec![]
+    }
+
+    /// The bytes the data of the test takes on the storage, e.g. the blocks
+    /// allocated to its files, sampled in the background if
+    /// `Options::space_interval`. None if the backend can't tell, by default.
+    async fn space_used(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Check the storage is up, cheaply, if `Options::health_interval`. By
+    /// default a read of a key never written, which must not be found.
+    async fn health(&self) -> Result<()> {
+        match self.read(HEALTH_KEY).await {
+            Err(err) if err.is_not_found() => Ok(()),
+            Err(err) => Err(err),
+            Ok(_) => Ok(()),
+        }
+    }
+
+    /// Write a object.
+    async fn write(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Read a object. A missing key must fail with `Error::not_found` and
+    /// nothing else, which is how the tester checks deletes.
+    async fn read(&self, key: &str) -> Result<String>;
+
+    /// Delete a object, with `Capability::Delete`.
+    async fn delete(&self, key: &str) -> Result<()> {
+        Err(unsupported(Capability::Delete, key))
+    }
+
+    /// Get the stored size of a object. Backends which can stat a object
+    /// should, with `Capability::Stat`, instead of reading it all.
+    async fn size(&self, key: &str) -> Result<u64> {
+        self.read(key).await.map(|value| value.len() as u64)
+    }
+
+    /// List the keys starting with the prefix, with `Capability::List`.
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Err(unsupported(Capability::List, prefix))
+    }
+
+    /// Append the value to a object, with `Capability::Append`.
+    async fn append(&self, key: &str, _value: &str) -> Result<()> {
+        Err(unsupported(Capability::Append, key))
+    }
+
+    /// Drop the object from the caches of the storage, e.g. the page cache,
+    /// with `Capability::Evict`.
+    async fn evict(&self, key: &str) -> Result<()> {
+        Err(unsupported(Capability::Evict, key))
+    }
+
+    /// Set the extended attribute of a object, with `Capability::Xattr`.
+    async fn set_xattr(&self, key: &str, _name: &str, _value: &str) -> Result<()> {
+        Err(unsupported(Capability::Xattr, key))
+    }
+
+    /// Get the extended attribute of a object, with `Capability::Xattr`.
+    async fn get_xattr(&self, key: &str, _name: &str) -> Result<String> {
+        Err(unsupported(Capability::Xattr, key))
+    }
+
+    /// Remove the extended attribute of a object, with `Capability::Xattr`.
+    async fn remove_xattr(&self, key: &str, _name: &str) -> Result<()> {
+        Err(unsupported(Capability::Xattr, key))
+    }
+
+    /// Deallocate `len` bytes of a object from the offset, keeping its size,
+    /// with `Capability::PunchHole`.
+    async fn punch_hole(&self, key: &str, _offset: u64, _len: u64) -> Result<()> {
+        Err(unsupported(Capability::PunchHole, key))
+    }
+
+    /// Copy a object to the other key, with `Capability::Copy`.
+    async fn copy(&self, key: &str, _to: &str) -> Result<()> {
+        Err(unsupported(Capability::Copy, key))
+    }
+
+    /// Write a object as a new version, returning the id of the version,
+    /// with `Capability::Versions`.
+    async fn write_versioned(&self, key: &str, _value: &str) -> Result<String> {
+        Err(unsupported(Capability::Versions, key))
+    }
+
+    /// Read the version of a object by its id, rather than the latest one,
+    /// with `Capability::Versions`.
+    async fn read_version(&self, key: &str, _version: &str) -> Result<String> {
+        Err(unsupported(Capability::Versions, key))
+    }
+}
+
+/// The key the default health check reads.
+pub const HEALTH_KEY: &str = "iotest-health";
+
+pub fn unsupported(capability: Capability, key: &str) -> Error {
+    Error::new(format!("{} {}: not supported", capability.name(), key))
+}
@@ -0,0 +1,62 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use tokio::time::{sleep_until, Instant};
+
+/// Clock is where the tester takes the time from: a monotonic instant to
+/// pace and time requests by, and the wall time only to timestamp them, so
+/// adjusting the system clock can't disturb the pacing.
+#[async_trait]
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Instant;
+
+    /// The time since the Unix epoch at the instant.
+    fn wall(&self, at: Instant) -> Duration;
+
+    /// Sleep until the instant.
+    async fn sleep_until(&self, deadline: Instant) {
+        sleep_until(deadline).await
+    }
+
+    /// The time since the instant.
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+
+    /// Sleep for the duration.
+    async fn sleep(&self, duration: Duration) {
+        self.sleep_until(self.now() + duration).await
+    }
+}
+
+/// TokioClock is the time of tokio, so a test can pause and advance it. The
+/// wall time is read once, then advanced by the monotonic time.
+pub struct TokioClock {
+    start: Instant,
+    start_wall: Duration,
+}
+
+impl TokioClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            start_wall: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default(),
+        }
+    }
+}
+
+impl Default for TokioClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall(&self, at: Instant) -> Duration {
+        self.start_wall + at.saturating_duration_since(self.start)
+    }
+}
@@ -0,0 +1,238 @@
+use std::{collections::BTreeMap, sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use tokio::{task, time::Instant};
+
+use super::{client::{Capabilities, Capability, Error, HandlerContext, Result, TestClient, TestClientHandler}, phases::PhaseTimer, stats::Metric};
+
+/// Codec is what a CompressionClient compresses values with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    Zstd,
+    Lz4,
+}
+
+impl Codec {
+    pub const ALL: [Codec; 2] = [Codec::Zstd, Codec::Lz4];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Codec::Zstd => "zstd",
+            Codec::Lz4 => "lz4",
+        }
+    }
+}
+
+/// CompressionConfig is how a CompressionClient compresses values.
+#[derive(Debug, Clone, Copy)]
+pub struct CompressionConfig {
+    pub codec: Codec,
+
+    /// The level of the codec: 1 to 22 for zstd, and 1 to 12 of the high
+    /// compression mode for lz4. The default of the codec if `None`.
+    pub level: Option<i32>,
+}
+
+impl CompressionConfig {
+    fn compress(&self, value: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self.codec {
+            Codec::Zstd => zstd::bulk::compress(value, self.level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL)),
+            Codec::Lz4 => {
+                let mode = self.level.map(lz4::block::CompressionMode::HIGHCOMPRESSION);
+                lz4::block::compress(value, mode, true)
+            }
+        }
+    }
+
+    fn decompress(&self, stored: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self.codec {
+            Codec::Zstd => zstd::stream::decode_all(stored),
+            Codec::Lz4 => lz4::block::decompress(stored, None),
+        }
+    }
+}
+
+/// CompressionClient wraps another client and compresses the values it
+/// writes and decompresses the values it reads, on the client, to weigh
+/// compressing before storing against storing as is. The writes and reads
+/// are measured end to end, with the compression, while the time spent
+/// compressing and decompressing is measured apart as `Metric::Compress`
+/// and `Metric::Decompress`.
+///
+/// The values of the backends are text, so the compressed bytes are stored
+/// as base64, a third more than the bytes themselves.
+pub struct CompressionClient<C> {
+    inner: C,
+    config: CompressionConfig,
+}
+
+impl<C> CompressionClient<C> where C: TestClient {
+    pub fn new(inner: C, config: CompressionConfig) -> Self {
+        println!("INIT COMPRESSION");
+        println!("  CODEC:         {}", config.codec.name());
+        match config.level {
+            Some(level) => println!("  LEVEL:         {}", level),
+            None => println!("  LEVEL:         default"),
+        }
+        Self { inner, config }
+    }
+}
+
+impl<C> TestClient for CompressionClient<C> where C: TestClient {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn init(&self) {
+        self.inner.init()
+    }
+
+    fn gen_unique_key(&self) -> String {
+        self.inner.gen_unique_key()
+    }
+
+    fn gen_namespaced_key(&self, namespace: &str) -> String {
+        self.inner.gen_namespaced_key(namespace)
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()
+    }
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        let mut details = self.inner.describe();
+        details.insert("compression".to_string(), self.config.codec.name().to_string());
+        if let Some(level) = self.config.level {
+            details.insert("compression_level".to_string(), level.to_string());
+        }
+        details
+    }
+
+    fn handler(&self, ctx: &HandlerContext) -> Arc<dyn TestClientHandler> {
+        Arc::new(CompressionClientHandler {
+            inner: self.inner.handler(ctx),
+            config: self.config,
+            timer: PhaseTimer::new(true),
+        })
+    }
+}
+
+pub struct CompressionClientHandler {
+    inner: Arc<dyn TestClientHandler>,
+    config: CompressionConfig,
+    timer: PhaseTimer,
+}
+
+impl CompressionClientHandler {
+    /// Compress the value off the runtime, recording the time it took.
+    async fn compress(&self, value: &str) -> Result<String> {
+        let (config, value) = (self.config, value.to_string());
+        let (compressed, elapsed) = task::spawn_blocking(move || {
+            let start = Instant::now();
+            let compressed = config.compress(value.as_bytes()).map(|compressed| STANDARD.encode(compressed));
+            (compressed, start.elapsed())
+        }).await.map_err(|err| Error::new(format!("compress: {}", err)))?;
+        let compressed = compressed.map_err(|err| Error::new(format!("compress: {}", err)))?;
+        self.timer.record(Metric::Compress, elapsed);
+        Ok(compressed)
+    }
+
+    /// Decompress the stored value off the runtime, recording the time it
+    /// took.
+    async fn decompress(&self, stored: String) -> Result<String> {
+        let config = self.config;
+        let (value, elapsed) = task::spawn_blocking(move || {
+            let start = Instant::now();
+            let value = STANDARD.decode(stored)
+                .map_err(|err| err.to_string())
+                .and_then(|compressed| config.decompress(&compressed).map_err(|err| err.to_string()))
+                .and_then(|value| String::from_utf8(value).map_err(|err| err.to_string()));
+            (value, start.elapsed())
+        }).await.map_err(|err| Error::new(format!("decompress: {}", err)))?;
+        let value = value.map_err(|err| Error::new(format!("decompress: {}", err)))?;
+        self.timer.record(Metric::Decompress, elapsed);
+        Ok(value)
+    }
+}
+
+#[async_trait]
+impl TestClientHandler for CompressionClientHandler {
+    /// The operations on the stored bytes are left out, as they are not the
+    /// bytes written: the stored size, appending and punching holes.
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities().iter()
+            .filter(|capability| !matches!(capability, Capability::Stat | Capability::Append | Capability::PunchHole))
+            .collect()
+    }
+
+    async fn setup(&self) -> Result<()> {
+        self.inner.setup().await
+    }
+
+    async fn teardown(&self) -> Result<()> {
+        self.inner.teardown().await
+    }
+
+    fn take_phases(&self) -> Vec<(Metric, Duration)> {
+        let mut phases = self.inner.take_phases();
+        phases.extend(self.timer.take());
+        phases
+    }
+
+    async fn space_used(&self) -> Result<Option<u64>> {
+        self.inner.space_used().await
+    }
+
+    async fn health(&self) -> Result<()> {
+        self.inner.health().await
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        let compressed = self.compress(value).await?;
+        self.inner.write(key, &compressed).await
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        let stored = self.inner.read(key).await?;
+        self.decompress(stored).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.inner.delete(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        self.inner.list(prefix).await
+    }
+
+    async fn evict(&self, key: &str) -> Result<()> {
+        self.inner.evict(key).await
+    }
+
+    async fn set_xattr(&self, key: &str, name: &str, value: &str) -> Result<()> {
+        self.inner.set_xattr(key, name, value).await
+    }
+
+    async fn get_xattr(&self, key: &str, name: &str) -> Result<String> {
+        self.inner.get_xattr(key, name).await
+    }
+
+    async fn remove_xattr(&self, key: &str, name: &str) -> Result<()> {
+        self.inner.remove_xattr(key, name).await
+    }
+
+    async fn copy(&self, key: &str, to: &str) -> Result<()> {
+        self.inner.copy(key, to).await
+    }
+
+    async fn write_versioned(&self, key: &str, value: &str) -> Result<String> {
+        let compressed = self.compress(value).await?;
+        self.inner.write_versioned(key, &compressed).await
+    }
+
+    async fn read_version(&self, key: &str, version: &str) -> Result<String> {
+        let stored = self.inner.read_version(key, version).await?;
+        self.decompress(stored).await
+    }
+}
@@ -0,0 +1,292 @@
+use std::{
+    collections::BTreeMap,
+    env, fmt, fs,
+    io::{self, BufRead, IsTerminal, Read, Write},
+    net::{SocketAddr, TcpStream},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use super::{client::{Error, Result}, registry::Settings};
+
+/// The secrets resolved so far, redacted wherever they would be printed.
+static SECRETS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// The instance metadata service of EC2 and its look-alikes.
+const INSTANCE_METADATA_ADDR: ([u8; 4], u16) = ([169, 254, 169, 254], 80);
+
+/// The credentials the instance metadata service hands out, by the names
+/// they are asked for.
+const INSTANCE_METADATA_NAMES: [(&str, &str); 3] = [
+    ("access_key_id", "AccessKeyId"),
+    ("secret_access_key", "SecretAccessKey"),
+    ("session_token", "Token"),
+];
+
+/// Secret is a credential, printed as `***` so it doesn't leak into logs.
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(***)")
+    }
+}
+
+impl fmt::Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("***")
+    }
+}
+
+/// CredentialSource is where a credential was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    Settings,
+    Env,
+    File,
+    InstanceMetadata,
+    Prompt,
+}
+
+impl CredentialSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CredentialSource::Settings => "settings",
+            CredentialSource::Env => "env",
+            CredentialSource::File => "file",
+            CredentialSource::InstanceMetadata => "instance metadata",
+            CredentialSource::Prompt => "prompt",
+        }
+    }
+}
+
+/// Credentials resolves the secrets a backend authenticates with by name,
+/// e.g. `password` or `secret_access_key`, the same way for every backend:
+/// from the settings, the environment (`IOTEST_<NAME>`, or the AWS variable
+/// of the name), a profile of the credentials file, the instance metadata
+/// (if `credentials_imds=true`), and at last a prompt (if
+/// `credentials_prompt=true` and on a terminal).
+///
+/// The credentials file is `~/.config/iotest/credentials` unless
+/// `credentials_file` is set, of profiles like
+///
+/// ```text
+/// [default]
+/// access_key_id = AKIA...
+/// secret_access_key = ...
+/// ```
+///
+/// read from the `credentials_profile` one, `IOTEST_PROFILE` or `default`.
+pub struct Credentials {
+    settings: Settings,
+    profile: BTreeMap<String, String>,
+    instance_metadata: bool,
+    prompt: bool,
+}
+
+impl Credentials {
+    pub fn from_settings(settings: &Settings) -> Result<Self> {
+        let profile_name = settings.get("credentials_profile").cloned()
+            .or_else(|| env::var("IOTEST_PROFILE").ok())
+            .unwrap_or_else(|| "default".to_string());
+        let profile = match settings.get("credentials_file") {
+            Some(path) => read_profile(Path::new(path), &profile_name)?
+                .ok_or_else(|| Error::new(format!("credentials {}: no profile {}", path, profile_name)))?,
+            None => match env::var_os("HOME") {
+                Some(home) => read_profile(&PathBuf::from(home).join(".config/iotest/credentials"), &profile_name)
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default(),
+                None => BTreeMap::new(),
+            },
+        };
+        Ok(Self {
+            settings: settings.clone(),
+            profile,
+            instance_metadata: settings.get("credentials_imds").map_or(false, |on| on == "true"),
+            prompt: settings.get("credentials_prompt").map_or(false, |on| on == "true"),
+        })
+    }
+
+    /// The credential of the name and where it was found, if anywhere.
+    pub fn get(&self, name: &str) -> Result<Option<(Secret, CredentialSource)>> {
+        let found = self.lookup(name)?;
+        if let Some((secret, _)) = &found {
+            register_secret(secret.expose());
+        }
+        Ok(found)
+    }
+
+    /// The credential of the name, failing if nowhere to be found.
+    pub fn require(&self, name: &str) -> Result<Secret> {
+        self.get(name)?
+            .map(|(secret, _)| secret)
+            .ok_or_else(|| Error::new(format!("no credential {}: set IOTEST_{} or add it to the credentials file", name, name.to_uppercase())))
+    }
+
+    fn lookup(&self, name: &str) -> Result<Option<(Secret, CredentialSource)>> {
+        if let Some(value) = self.settings.get(name) {
+            return Ok(Some((Secret(value.clone()), CredentialSource::Settings)));
+        }
+        let aws_name = INSTANCE_METADATA_NAMES.iter().any(|(known, _)| *known == name)
+            .then(|| format!("AWS_{}", name.to_uppercase()));
+        for var in [Some(format!("IOTEST_{}", name.to_uppercase())), aws_name].into_iter().flatten() {
+            if let Ok(value) = env::var(&var) {
+                return Ok(Some((Secret(value), CredentialSource::Env)));
+            }
+        }
+        if let Some(value) = self.profile.get(name) {
+            return Ok(Some((Secret(value.clone()), CredentialSource::File)));
+        }
+        if self.instance_metadata {
+            if let Some((_, field)) = INSTANCE_METADATA_NAMES.iter().find(|(known, _)| *known == name) {
+                let value = instance_metadata_credential(field)?;
+                return Ok(Some((Secret(value), CredentialSource::InstanceMetadata)));
+            }
+        }
+        if self.prompt && io::stdin().is_terminal() {
+            let value = prompt(name)?;
+            return Ok(Some((Secret(value), CredentialSource::Prompt)));
+        }
+        Ok(None)
+    }
+}
+
+/// Redact the secret wherever it is printed by `redact` from now on.
+pub fn register_secret(secret: &str) {
+    // Short ones would redact half of every line.
+    if secret.len() < 4 {
+        return;
+    }
+    let mut secrets = SECRETS.lock().unwrap_or_else(PoisonError::into_inner);
+    if !secrets.iter().any(|known| known == secret) {
+        secrets.push(secret.to_string());
+    }
+}
+
+/// Replace every secret resolved so far in the text with `***`.
+pub fn redact(text: &str) -> String {
+    let secrets = SECRETS.lock().unwrap_or_else(PoisonError::into_inner);
+    secrets.iter().fold(text.to_string(), |text, secret| text.replace(secret.as_str(), "***"))
+}
+
+/// The setting is a secret by its name, e.g. `password` or
+/// `env:AWS_SECRET_ACCESS_KEY`, rather than a path or an address.
+pub fn is_secret_setting(name: &str) -> bool {
+    let name = name.to_lowercase();
+    ["password", "passwd", "secret", "token", "credential", "access_key", "api_key"]
+        .iter()
+        .any(|word| name.contains(word))
+}
+
+/// Redact the settings that are secrets by their name, e.g. to record them.
+pub fn redact_settings(settings: &Settings) -> Settings {
+    settings.iter()
+        .map(|(name, value)| {
+            let value = if is_secret_setting(name) { "***".to_string() } else { redact(value) };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
+/// Redact a command line, e.g. to record it: the values of `name=value`
+/// arguments that are secrets by their name, and every secret resolved.
+pub fn redact_args(args: &[String]) -> Vec<String> {
+    args.iter()
+        .map(|arg| match arg.split_once('=') {
+            Some((name, _)) if is_secret_setting(name) => format!("{}=***", name),
+            _ => redact(arg),
+        })
+        .collect()
+}
+
+/// Read the profile of a credentials file, `None` if it has no such one.
+fn read_profile(path: &Path, profile: &str) -> Result<Option<BTreeMap<String, String>>> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| Error::from_io_error(&format!("read {}", path.display()), err))?;
+    let mut section = None;
+    let mut found = None;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            section = Some(name.trim().to_string());
+            if section.as_deref() == Some(profile) {
+                found.get_or_insert_with(BTreeMap::new);
+            }
+            continue;
+        }
+        let (name, value) = line.split_once('=')
+            .ok_or_else(|| Error::new(format!("{}:{}: not a `name = value`", path.display(), idx + 1)))?;
+        if section.as_deref() == Some(profile) {
+            let value = value.trim().trim_matches('"').to_string();
+            found.get_or_insert_with(BTreeMap::new).insert(name.trim().to_string(), value);
+        }
+    }
+    Ok(found)
+}
+
+/// Fetch a field of the credentials of the role of the instance, by IMDSv2.
+fn instance_metadata_credential(field: &str) -> Result<String> {
+    let token = instance_metadata_request("PUT", "/latest/api/token", &[("X-aws-ec2-metadata-token-ttl-seconds", "60")])?;
+    let auth = [("X-aws-ec2-metadata-token", token.trim())];
+    let role = instance_metadata_request("GET", "/latest/meta-data/iam/security-credentials/", &auth)?;
+    let role = role.lines().next()
+        .ok_or_else(|| Error::new("instance metadata: no role attached"))?;
+    let credentials = instance_metadata_request("GET", &format!("/latest/meta-data/iam/security-credentials/{}", role), &auth)?;
+    let credentials: serde_json::Value = serde_json::from_str(&credentials)
+        .map_err(|err| Error::new(format!("instance metadata: {}", err)))?;
+    let value = credentials.get(field).and_then(|value| value.as_str())
+        .ok_or_else(|| Error::new(format!("instance metadata: no {}", field)))?;
+    Ok(value.to_string())
+}
+
+fn instance_metadata_request(method: &str, path: &str, headers: &[(&str, &str)]) -> Result<String> {
+    let prefix = format!("instance metadata {} {}", method, path);
+    let timeout = Duration::from_secs(2);
+    let mut stream = TcpStream::connect_timeout(&SocketAddr::from(INSTANCE_METADATA_ADDR), timeout)
+        .map_err(|err| Error::from_io_error(&prefix, err))?;
+    let _ = stream.set_read_timeout(Some(timeout));
+    let mut request = format!("{} {} HTTP/1.1\r\nHost: 169.254.169.254\r\nConnection: close\r\nContent-Length: 0\r\n", method, path);
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes())
+        .map_err(|err| Error::from_io_error(&prefix, err))?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)
+        .map_err(|err| Error::from_io_error(&prefix, err))?;
+    let (head, body) = response.split_once("\r\n\r\n")
+        .ok_or_else(|| Error::new(format!("{}: malformed response", prefix)))?;
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(Error::new(format!("{}: status {}", prefix, status)));
+    }
+    Ok(body.to_string())
+}
+
+/// Ask for the credential on the terminal, without echoing it.
+fn prompt(name: &str) -> Result<String> {
+    eprint!("{}: ", name);
+    let _ = io::stderr().flush();
+    let stty = |arg: &str| Command::new("stty").arg(arg).stdin(Stdio::inherit()).status();
+    let _ = stty("-echo");
+    let mut line = String::new();
+    let read = io::stdin().lock().read_line(&mut line);
+    let _ = stty("echo");
+    eprintln!();
+    read.map_err(|err| Error::from_io_error(&format!("read {}", name), err))?;
+    Ok(line.trim_end_matches(['\r', '\n']).to_string())
+}
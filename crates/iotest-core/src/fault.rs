@@ -0,0 +1,207 @@
+use std::{collections::BTreeMap, sync::{Arc, Mutex, PoisonError}, time::Duration};
+
+use async_trait::async_trait;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::time::sleep;
+
+use super::{client::{Capabilities, Error, HandlerContext, Result, TestClient, TestClientHandler}, stats::Metric};
+
+/// FaultConfig is what a FaultClient injects into every operation.
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// Extra latency added to every operation.
+    pub latency: Duration,
+
+    /// Up to so much random latency added on top of `latency`.
+    pub jitter: Duration,
+
+    /// The probability of an operation failing.
+    pub error_rate: f64,
+
+    /// The probability of a write storing only a half of the value while
+    /// reporting success.
+    pub partial_write_rate: f64,
+}
+
+/// FaultClient wraps another client and injects faults into its operations,
+/// to see how the storage (and the tester) behaves under degraded conditions.
+pub struct FaultClient<C> {
+    inner: C,
+    config: FaultConfig,
+    /// Decides the faults of all handlers.
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl<C> FaultClient<C> where C: TestClient {
+    /// Create a client injecting faults decided by randomness seeded by the
+    /// seed, if any.
+    pub fn new(inner: C, config: FaultConfig, seed: Option<u64>) -> Self {
+        println!("INIT FAULT INJECTION");
+        println!("  LATENCY:       {:?}", config.latency);
+        println!("  JITTER:        {:?}", config.jitter);
+        println!("  ERROR RATE:    {}", config.error_rate);
+        println!("  PARTIAL WRITE: {}", config.partial_write_rate);
+        let rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        Self { inner, config, rng: Arc::new(Mutex::new(rng)) }
+    }
+}
+
+impl<C> TestClient for FaultClient<C> where C: TestClient {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn init(&self) {
+        self.inner.init()
+    }
+
+    fn gen_unique_key(&self) -> String {
+        self.inner.gen_unique_key()
+    }
+
+    fn gen_namespaced_key(&self, namespace: &str) -> String {
+        self.inner.gen_namespaced_key(namespace)
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()
+    }
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        self.inner.describe()
+    }
+
+    fn handler(&self, ctx: &HandlerContext) -> Arc<dyn TestClientHandler> {
+        Arc::new(FaultClientHandler {
+            inner: self.inner.handler(ctx),
+            config: self.config.clone(),
+            rng: self.rng.clone(),
+        })
+    }
+}
+
+pub struct FaultClientHandler {
+    inner: Arc<dyn TestClientHandler>,
+    config: FaultConfig,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl FaultClientHandler {
+    /// Draw a number in `[0, 1)` deciding a fault.
+    fn draw(&self) -> f64 {
+        self.rng.lock().unwrap_or_else(PoisonError::into_inner).gen()
+    }
+
+    /// Sleep for the injected latency, then fail if unlucky.
+    async fn inject(&self, op: &str, key: &str) -> Result<()> {
+        let jitter = self.config.jitter.mul_f64(self.draw());
+        let latency = self.config.latency + jitter;
+        if !latency.is_zero() {
+            sleep(latency).await;
+        }
+        if self.draw() < self.config.error_rate {
+            return Err(Error::new(format!("{} {}: injected fault", op, key)));
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TestClientHandler for FaultClientHandler {
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn setup(&self) -> Result<()> {
+        self.inner.setup().await
+    }
+
+    async fn teardown(&self) -> Result<()> {
+        self.inner.teardown().await
+    }
+
+    fn take_phases(&self) -> Vec<(Metric, Duration)> {
+        self.inner.take_phases()
+    }
+
+    async fn space_used(&self) -> Result<Option<u64>> {
+        self.inner.space_used().await
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        self.inject("write", key).await?;
+        if self.draw() < self.config.partial_write_rate {
+            let mut half = value.len() / 2;
+            while !value.is_char_boundary(half) {
+                half -= 1;
+            }
+            return self.inner.write(key, &value[..half]).await;
+        }
+        self.inner.write(key, value).await
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        self.inject("read", key).await?;
+        self.inner.read(key).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.inject("delete", key).await?;
+        self.inner.delete(key).await
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        self.inject("size", key).await?;
+        self.inner.size(key).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        self.inject("list", prefix).await?;
+        self.inner.list(prefix).await
+    }
+
+    async fn append(&self, key: &str, value: &str) -> Result<()> {
+        self.inject("append", key).await?;
+        self.inner.append(key, value).await
+    }
+
+    async fn evict(&self, key: &str) -> Result<()> {
+        self.inject("evict", key).await?;
+        self.inner.evict(key).await
+    }
+
+    async fn set_xattr(&self, key: &str, name: &str, value: &str) -> Result<()> {
+        self.inject("set_xattr", key).await?;
+        self.inner.set_xattr(key, name, value).await
+    }
+
+    async fn get_xattr(&self, key: &str, name: &str) -> Result<String> {
+        self.inject("get_xattr", key).await?;
+        self.inner.get_xattr(key, name).await
+    }
+
+    async fn remove_xattr(&self, key: &str, name: &str) -> Result<()> {
+        self.inject("remove_xattr", key).await?;
+        self.inner.remove_xattr(key, name).await
+    }
+
+    async fn punch_hole(&self, key: &str, offset: u64, len: u64) -> Result<()> {
+        self.inject("punch_hole", key).await?;
+        self.inner.punch_hole(key, offset, len).await
+    }
+
+    async fn copy(&self, key: &str, to: &str) -> Result<()> {
+        self.inject("copy", key).await?;
+        self.inner.copy(key, to).await
+    }
+
+    async fn write_versioned(&self, key: &str, value: &str) -> Result<String> {
+        self.inject("write", key).await?;
+        self.inner.write_versioned(key, value).await
+    }
+
+    async fn read_version(&self, key: &str, version: &str) -> Result<String> {
+        self.inject("read", key).await?;
+        self.inner.read_version(key, version).await
+    }
+}
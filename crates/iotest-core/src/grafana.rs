@@ -0,0 +1,69 @@
+use std::{fs, path::Path};
+
+use serde_json::{json, Value};
+
+use super::{client::{Error, Result}, prometheus::{METRIC_ERRORS, METRIC_LATENCY, METRIC_OPERATIONS, METRIC_TARGET_QPS}};
+
+/// Write a Grafana dashboard, ready to import, which shows the metrics
+/// exported by the `PrometheusExporter`.
+pub fn write_dashboard(path: &Path) -> Result<()> {
+    let panels = vec![
+        panel(1, "Target QPS", "reqps", 0, vec![
+            target(METRIC_TARGET_QPS, "target"),
+        ]),
+        panel(2, "Achieved QPS", "reqps", 12, vec![
+            target(&format!("sum by (op) (rate({}[1m]))", METRIC_OPERATIONS), "{{op}}"),
+        ]),
+        panel(3, "p50 latency", "s", 0, vec![
+            target(&format!("histogram_quantile(0.5, sum by (op, le) (rate({}_bucket[1m])))", METRIC_LATENCY), "{{op}}"),
+        ]),
+        panel(4, "p99 latency", "s", 12, vec![
+            target(&format!("histogram_quantile(0.99, sum by (op, le) (rate({}_bucket[1m])))", METRIC_LATENCY), "{{op}}"),
+        ]),
+        panel(5, "Error rate", "percentunit", 0, vec![
+            target(&format!("sum by (op) (rate({}[1m])) / sum by (op) (rate({}[1m]))", METRIC_ERRORS, METRIC_OPERATIONS), "{{op}}"),
+        ]),
+    ];
+    let dashboard = json!({
+        "__inputs": [{
+            "name": "DS_PROMETHEUS",
+            "label": "Prometheus",
+            "type": "datasource",
+            "pluginId": "prometheus",
+            "pluginName": "Prometheus",
+        }],
+        "title": "iotest",
+        "uid": "iotest",
+        "schemaVersion": 39,
+        "time": { "from": "now-1h", "to": "now" },
+        "refresh": "5s",
+        "panels": panels,
+    });
+    let json = serde_json::to_string_pretty(&dashboard)
+        .map_err(|err| Error::new(format!("serialize dashboard: {}", err)))?;
+    fs::write(path, json)
+        .map_err(|err| Error::from_io_error(&format!("write {}", path.display()), err))?;
+    println!("GRAFANA DASHBOARD");
+    println!("  PATH:          {}", path.display());
+    Ok(())
+}
+
+fn panel(id: u64, title: &str, unit: &str, x: u64, targets: Vec<Value>) -> Value {
+    json!({
+        "id": id,
+        "title": title,
+        "type": "timeseries",
+        "datasource": { "type": "prometheus", "uid": "${DS_PROMETHEUS}" },
+        "gridPos": { "x": x, "y": (id - 1) / 2 * 8, "w": 12, "h": 8 },
+        "fieldConfig": { "defaults": { "unit": unit }, "overrides": [] },
+        "targets": targets,
+    })
+}
+
+fn target(expr: &str, legend: &str) -> Value {
+    json!({
+        "datasource": { "type": "prometheus", "uid": "${DS_PROMETHEUS}" },
+        "expr": expr,
+        "legendFormat": legend,
+    })
+}
@@ -0,0 +1,136 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::{interval, timeout, Instant, MissedTickBehavior}};
+use tokio_util::sync::CancellationToken;
+
+use super::client::TestClientHandler;
+
+/// HealthCheck is a health check of the backend at a point of the test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub at_ms: u64,
+    pub latency_us: u64,
+    /// The error the check failed with, if it did.
+    pub error: Option<String>,
+}
+
+impl HealthCheck {
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Outage is a run of failed health checks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Outage {
+    /// When the first check failed.
+    pub from_ms: u64,
+    /// When the next check succeeded, or the last one failed.
+    pub to_ms: u64,
+    pub checks: u64,
+}
+
+/// HealthSummary is how available the backend was through the test, by its
+/// health checks.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthSummary {
+    /// The ratio of successful checks.
+    pub availability: f64,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub outages: Vec<Outage>,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthSummary {
+    /// Summarize the checks, in the order they were made. None without any.
+    pub fn new(checks: Vec<HealthCheck>) -> Option<Self> {
+        if checks.is_empty() {
+            return None;
+        }
+        let healthy = checks.iter().filter(|check| check.is_healthy()).count();
+        let mut latencies: Vec<u64> = checks.iter()
+            .filter(|check| check.is_healthy())
+            .map(|check| check.latency_us)
+            .collect();
+        latencies.sort_unstable();
+        let percentile = |p: f64| match latencies.len() {
+            0 => 0,
+            len => latencies[((len as f64 * p).ceil() as usize).clamp(1, len) - 1],
+        };
+        let mut outages: Vec<Outage> = vec![];
+        let mut down = false;
+        for check in &checks {
+            match (check.is_healthy(), down) {
+                (false, false) => outages.push(Outage { from_ms: check.at_ms, to_ms: check.at_ms, checks: 1 }),
+                (false, true) => {
+                    let outage = outages.last_mut().unwrap();
+                    outage.to_ms = check.at_ms;
+                    outage.checks += 1;
+                }
+                (true, true) => outages.last_mut().unwrap().to_ms = check.at_ms,
+                (true, false) => {}
+            }
+            down = !check.is_healthy();
+        }
+        Some(Self {
+            availability: healthy as f64 / checks.len() as f64,
+            p50_us: percentile(0.5),
+            p99_us: percentile(0.99),
+            outages,
+            checks,
+        })
+    }
+}
+
+/// HealthMonitor checks the health of the backend at a low rate in the
+/// background, apart from the load, so a brief outage in a long test shows
+/// as such instead of as some errors among many requests.
+pub struct HealthMonitor {
+    checks: Arc<Mutex<Vec<HealthCheck>>>,
+    stop: CancellationToken,
+    handle: JoinHandle<()>,
+}
+
+impl HealthMonitor {
+    /// Check every `every`, relative to `start`, until finished. A check not
+    /// done within the interval fails.
+    pub fn start(hdlr: Arc<dyn TestClientHandler>, every: Duration, start: Instant) -> Self {
+        let checks = Arc::new(Mutex::new(vec![]));
+        let stop = CancellationToken::new();
+        let handle = {
+            let (checks, stop) = (checks.clone(), stop.clone());
+            tokio::spawn(async move {
+                let mut ticks = interval(every);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    tokio::select! {
+                        _ = ticks.tick() => {}
+                        _ = stop.cancelled() => return,
+                    }
+                    let at = start.elapsed();
+                    let began = Instant::now();
+                    let error = match timeout(every, hdlr.health()).await {
+                        Ok(Ok(())) => None,
+                        Ok(Err(err)) => Some(err.to_string()),
+                        Err(_) => Some(format!("no answer in {:?}", every)),
+                    };
+                    checks.lock().unwrap().push(HealthCheck {
+                        at_ms: at.as_millis() as u64,
+                        latency_us: began.elapsed().as_micros() as u64,
+                        error,
+                    });
+                }
+            })
+        };
+        Self { checks, stop, handle }
+    }
+
+    /// Stop checking, and get all checks made.
+    pub async fn finish(self) -> Vec<HealthCheck> {
+        self.stop.cancel();
+        let _ = self.handle.await;
+        std::mem::take(&mut *self.checks.lock().unwrap())
+    }
+}
@@ -0,0 +1,69 @@
+use std::{sync::{Arc, Mutex}, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::Instant};
+
+use super::{clock::Clock, shell::shell};
+
+/// Hook is a command run at a point of a test, e.g. restarting the storage at
+/// t=60s to see how failover impacts the latency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Hook {
+    /// When to run the command, since the test started.
+    pub at: Duration,
+    pub command: String,
+}
+
+/// HookEvent is a hook which has been run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HookEvent {
+    pub at_ms: u64,
+    pub command: String,
+    /// The exit code of the command, or the error running it.
+    pub status: String,
+}
+
+/// Hooks runs the hooks of a test in the background.
+pub struct Hooks {
+    events: Arc<Mutex<Vec<HookEvent>>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl Hooks {
+    /// Schedule the hooks, relative to `start`, by the clock.
+    pub fn schedule(hooks: &[Hook], start: Instant, clock: Arc<dyn Clock>) -> Self {
+        let events = Arc::new(Mutex::new(vec![]));
+        let handles = hooks.iter().cloned().map(|hook| {
+            let events = events.clone();
+            let clock = clock.clone();
+            tokio::spawn(async move {
+                clock.sleep_until(start + hook.at).await;
+                let at = clock.elapsed(start);
+                println!("HOOK:");
+                println!("  AT:            {:?}", at);
+                println!("  COMMAND:       {}", hook.command);
+                let status = match shell(&hook.command).status().await {
+                    Ok(status) => status.code().map_or("killed".to_string(), |code| code.to_string()),
+                    Err(err) => err.to_string(),
+                };
+                println!("  STATUS:        {}", status);
+                events.lock().unwrap().push(HookEvent {
+                    at_ms: at.as_millis() as u64,
+                    command: hook.command,
+                    status,
+                });
+            })
+        }).collect();
+        Self { events, handles }
+    }
+
+    /// Stop the hooks not run yet, and get the events of the run ones.
+    pub fn finish(self) -> Vec<HookEvent> {
+        for handle in self.handles {
+            handle.abort();
+        }
+        let mut events = self.events.lock().unwrap().clone();
+        events.sort_by_key(|event| event.at_ms);
+        events
+    }
+}
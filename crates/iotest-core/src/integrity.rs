@@ -0,0 +1,48 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Integrity counts what requests found wrong with the stored data, apart from
+/// the errors reported by the storage.
+pub struct Integrity {
+    partial_writes: AtomicU64,
+    content_mismatches: AtomicU64,
+    torn_reads: AtomicU64,
+}
+
+/// IntegrityCounts is what Integrity counted in a step.
+pub struct IntegrityCounts {
+    /// Writes acknowledged while storing less (or more) than the value.
+    pub partial_writes: u64,
+    /// Reads returning a value which fails verification, except torn ones.
+    pub content_mismatches: u64,
+    /// Reads returning a value mixing blocks of different requests.
+    pub torn_reads: u64,
+}
+
+impl Integrity {
+    pub fn new() -> Self {
+        Self {
+            partial_writes: AtomicU64::new(0),
+            content_mismatches: AtomicU64::new(0),
+            torn_reads: AtomicU64::new(0),
+        }
+    }
+
+    pub fn partial_write(&self) {
+        self.partial_writes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a read value failing verification, torn or not.
+    pub fn mismatch(&self, torn: bool) {
+        let counter = if torn { &self.torn_reads } else { &self.content_mismatches };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Take the counts since the last take.
+    pub fn take(&self) -> IntegrityCounts {
+        IntegrityCounts {
+            partial_writes: self.partial_writes.swap(0, Ordering::Relaxed),
+            content_mismatches: self.content_mismatches.swap(0, Ordering::Relaxed),
+            torn_reads: self.torn_reads.swap(0, Ordering::Relaxed),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! iotest benchmarks and verifies a storage backend: it issues write / read /
+//! delete requests at stepped QPS, checks every value read back, and reports
+//! latencies, errors and integrity problems.
+//!
+//! Embed it by implementing `TestClient` for the storage (or using one of the
+//! `iotest-backends-*` crates) and running a `Tester`:
+//!
+//! ```no_run
+//! # async fn run<C: iotest_core::TestClient>(client: C) -> iotest_core::Result<()> {
+//! use iotest_core::{Options, Tester};
+//!
+//! let mut tester = Tester::with_options(client, Options::default());
+//! let report = tester.test().await?;
+//! assert_eq!(report.summary.verdict, iotest_core::Verdict::Success);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A blocking handler, e.g. on `std::fs` or a C library, implements
+//! `SyncClientHandler` instead and is wrapped in a `SyncClientAdapter`.
+//!
+//! In an integration test, `TesterBuilder::run` runs the test and the report
+//! asserts on latencies and errors, e.g. `assert_p99`, to fail the build on a
+//! regression.
+//!
+//! Network backends connect over TLS by a `TlsConfig` read from their
+//! settings, with the `tls` feature.
+//!
+//! Printing and drawing the report is left to `iotest-report`.
+
+mod tester;
+mod builder;
+mod cache;
+mod calibrate;
+mod checkpoint;
+mod client;
+mod clock;
+#[cfg(feature = "compression")]
+pub mod compression;
+mod credentials;
+pub mod fault;
+mod grafana;
+mod health;
+mod hooks;
+mod integrity;
+mod live;
+mod metadata;
+pub mod network;
+mod options;
+mod payload;
+mod phases;
+mod pool;
+#[cfg(feature = "profile")]
+mod profile;
+mod prometheus;
+mod proxy;
+mod registry;
+mod report;
+mod reporter;
+mod request;
+mod retry;
+mod scrub;
+mod shell;
+mod slo;
+mod space;
+mod stats;
+mod summary;
+mod sync;
+mod tenant;
+mod timeseries;
+#[cfg(feature = "tls")]
+mod tls;
+mod usage;
+mod versions;
+
+pub use builder::TesterBuilder;
+pub use cache::drop_page_cache;
+pub use calibrate::{NoopClient, Overhead};
+pub use checkpoint::Checkpoint;
+pub use client::{TestClient, TestClientHandler, HandlerContext, Capability, Capabilities, Result, Error, ErrorDetail, ErrorClass, Op};
+pub use credentials::{Credentials, CredentialSource, Secret, redact, redact_args, redact_settings, register_secret, is_secret_setting};
+pub use hooks::{Hook, HookEvent};
+pub use metadata::Metadata;
+pub use options::Options;
+pub use payload::{Payload, verify as verify_payload};
+pub use phases::PhaseTimer;
+pub use pool::PoolStats;
+pub use proxy::ProxyConfig;
+pub use clock::{Clock, TokioClock};
+pub use health::{HealthCheck, HealthSummary, Outage};
+pub use registry::{Registry, Settings, Constructor};
+pub use report::{TestReport, StepHistograms};
+pub use reporter::Reporter;
+pub use request::COPY_SUFFIX;
+pub use retry::RetryPolicy;
+pub use scrub::ScrubSummary;
+pub use shell::shell;
+pub use slo::{Objective, Stat, Violation};
+pub use space::SpaceSample;
+pub use stats::{Metric, RequestInfo, TestResult, BUCKETS, BUCKETS_LEN};
+pub use summary::{Summary, StepSummary, MetricSummary, NamespaceSummary, PrioritySummary, SizeSummary, Verdict, EXIT_HARNESS_FAILURE};
+pub use sync::{SyncClientHandler, SyncClientAdapter, SyncPool};
+pub use tenant::Tenant;
+pub use tester::Tester;
+pub use timeseries::{Interval, Stall, TimeSeries};
+pub use usage::TesterUsage;
+#[cfg(feature = "tls")]
+pub use tls::{TlsConfig, tls_acceptor};
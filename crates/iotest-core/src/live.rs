@@ -0,0 +1,116 @@
+use std::{net::SocketAddr, sync::{Arc, Mutex, PoisonError}, time::Duration};
+
+use serde::Serialize;
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}, sync::broadcast, task::JoinHandle, time};
+
+use super::{client::{Error, Result}, stats::{Metric, StepStats}, summary::MetricSummary};
+
+/// LiveStream streams per-second metrics as JSON server-sent events, so
+/// external dashboards can watch a run while it is going.
+pub struct LiveStream {
+    sender: broadcast::Sender<String>,
+}
+
+/// IntervalMetrics is what a step results in during one second.
+#[derive(Serialize)]
+struct IntervalMetrics {
+    qps: u64,
+    second: u64,
+    metrics: Vec<MetricSummary>,
+}
+
+impl LiveStream {
+    /// Listen on the address and serve every connection with the stream.
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr).await
+            .map_err(|err| Error::from_io_error(&format!("bind {}", addr), err))?;
+        println!("LIVE METRICS");
+        println!("  URL:           http://{}/", addr);
+        let (sender, _) = broadcast::channel(64);
+        let accept_sender = sender.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, accept_sender.subscribe()));
+            }
+        });
+        Ok(Self { sender })
+    }
+
+    /// Start publishing the metrics of a step every second.
+    pub fn start_step(&self, qps: u64) -> LiveStep {
+        let recorder = Arc::new(LiveRecorder {
+            stats: Mutex::new(StepStats::new()),
+        });
+        let sender = self.sender.clone();
+        let ticker_recorder = recorder.clone();
+        let ticker = tokio::spawn(async move {
+            let mut interval = time::interval(Duration::from_secs(1));
+            interval.tick().await;
+            for second in 1.. {
+                interval.tick().await;
+                let mut stats = std::mem::replace(&mut *ticker_recorder.stats.lock().unwrap_or_else(PoisonError::into_inner), StepStats::new());
+                let metrics = IntervalMetrics {
+                    qps,
+                    second,
+                    metrics: stats.summarize(Duration::from_secs(1)),
+                };
+                // Nobody listening is fine.
+                let _ = sender.send(serde_json::to_string(&metrics).unwrap());
+            }
+        });
+        LiveStep { recorder, ticker }
+    }
+}
+
+/// LiveStep is the live metrics of a running step.
+pub struct LiveStep {
+    recorder: Arc<LiveRecorder>,
+    ticker: JoinHandle<()>,
+}
+
+impl LiveStep {
+    pub fn recorder(&self) -> Arc<LiveRecorder> {
+        self.recorder.clone()
+    }
+
+    pub fn finish(self) {
+        self.ticker.abort();
+    }
+}
+
+/// LiveRecorder collects the results of the current second.
+pub struct LiveRecorder {
+    stats: Mutex<StepStats>,
+}
+
+impl LiveRecorder {
+    pub fn record(&self, result: &[(Metric, Result<Duration>)]) {
+        self.stats.lock().unwrap_or_else(PoisonError::into_inner).record(result);
+    }
+}
+
+async fn serve(mut stream: TcpStream, mut receiver: broadcast::Receiver<String>) {
+    // Whatever is requested, answer with the event stream.
+    let mut buf = [0; 1024];
+    if stream.read(&mut buf).await.is_err() {
+        return;
+    }
+    let header = "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/event-stream\r\n\
+        Cache-Control: no-cache\r\n\
+        Connection: keep-alive\r\n\
+        Access-Control-Allow-Origin: *\r\n\r\n";
+    if stream.write_all(header.as_bytes()).await.is_err() {
+        return;
+    }
+    loop {
+        let event = match receiver.recv().await {
+            Ok(event) => event,
+            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if stream.write_all(format!("data: {}\n\n", event).as_bytes()).await.is_err() {
+            return;
+        }
+    }
+}
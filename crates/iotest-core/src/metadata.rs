@@ -0,0 +1,79 @@
+use std::{collections::BTreeMap, env, fs};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::{credentials::redact_settings, options::Options};
+
+/// Metadata describes where, when and how a test ran, so the results are
+/// still interpretable long after.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Metadata {
+    pub backend: String,
+    /// What the backend told about the storage, e.g. its filesystem.
+    #[serde(default)]
+    pub backend_details: BTreeMap<String, String>,
+    pub hostname: String,
+    pub os: String,
+    pub kernel: String,
+    pub cpu_model: String,
+    pub iotest_version: String,
+    pub iotest_commit: String,
+    pub options: Options,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+}
+
+impl Metadata {
+    pub fn collect(backend: &str, backend_details: BTreeMap<String, String>, options: &Options, started_at: DateTime<Utc>) -> Self {
+        Self {
+            backend: backend.to_string(),
+            // The details may tell how the backend authenticates.
+            backend_details: redact_settings(&backend_details),
+            hostname: read_trimmed("/proc/sys/kernel/hostname")
+                .or_else(|| env::var("HOSTNAME").ok())
+                .or_else(|| env::var("COMPUTERNAME").ok())
+                .unwrap_or_else(unknown),
+            os: os_name(),
+            kernel: read_trimmed("/proc/sys/kernel/osrelease").unwrap_or_else(unknown),
+            cpu_model: cpu_model().unwrap_or_else(unknown),
+            iotest_version: env!("CARGO_PKG_VERSION").to_string(),
+            iotest_commit: env!("IOTEST_GIT_COMMIT").to_string(),
+            options: options.clone(),
+            started_at,
+            finished_at: Utc::now(),
+        }
+    }
+
+    /// The name of the test among the others of a run, the backend name by
+    /// default.
+    pub fn label(&self) -> &str {
+        self.options.label.as_deref().unwrap_or(&self.backend)
+    }
+}
+
+fn unknown() -> String {
+    "unknown".to_string()
+}
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+fn os_name() -> String {
+    // Prefer the pretty name of the distribution, e.g. "Ubuntu 24.04 LTS".
+    let pretty_name = fs::read_to_string("/etc/os-release").ok().and_then(|release| {
+        release.lines()
+            .find_map(|line| line.strip_prefix("PRETTY_NAME="))
+            .map(|name| name.trim_matches('"').to_string())
+    });
+    pretty_name.unwrap_or_else(|| env::consts::OS.to_string())
+}
+
+fn cpu_model() -> Option<String> {
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").ok()?;
+    cpuinfo.lines()
+        .find(|line| line.starts_with("model name"))
+        .and_then(|line| line.split_once(':'))
+        .map(|(_, model)| model.trim().to_string())
+}
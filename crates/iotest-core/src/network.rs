@@ -0,0 +1,284 @@
+use std::{collections::BTreeMap, sync::{Arc, Mutex, PoisonError}, time::Duration};
+
+use async_trait::async_trait;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::time::{sleep, sleep_until, Instant};
+
+use super::{client::{Capabilities, HandlerContext, Result, TestClient, TestClientHandler}, stats::Metric};
+
+/// The retransmission timeout of a lost packet, at least, as Linux's.
+const MIN_RTO: Duration = Duration::from_millis(200);
+
+/// The bytes of a request or a response besides its key and value.
+const HEADER_LEN: usize = 64;
+
+/// NetworkConfig is the network a NetworkClient simulates between iotest and
+/// the storage.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkConfig {
+    /// The round trip time, half of it on the way to the storage and half on
+    /// the way back.
+    pub rtt: Duration,
+
+    /// Up to so much random delay added on top of each way.
+    pub jitter: Duration,
+
+    /// The bytes per second the link carries each way, shared by all
+    /// handlers. Unlimited if `None`.
+    pub bandwidth: Option<u64>,
+
+    /// The probability of an operation losing a packet, which delays it by a
+    /// retransmission: twice the RTT, at least 200ms.
+    pub loss_rate: f64,
+}
+
+/// Link is one way of the simulated network, carrying a transfer at a time.
+#[derive(Default)]
+struct Link {
+    /// When the link is done with the transfers so far.
+    free_at: Mutex<Option<Instant>>,
+}
+
+impl Link {
+    /// Wait until `len` bytes went over the link, after the ones queued.
+    async fn transfer(&self, len: usize, bandwidth: u64) {
+        let took = Duration::from_secs_f64(len as f64 / bandwidth as f64);
+        let done = {
+            let mut free_at = self.free_at.lock().unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            let done = free_at.filter(|free_at| *free_at > now).unwrap_or(now) + took;
+            *free_at = Some(done);
+            done
+        };
+        sleep_until(done).await;
+    }
+}
+
+/// NetworkClient wraps another client and delays its operations as a
+/// network would: by the RTT and its jitter, by the bandwidth for the bytes
+/// sent and received, and by retransmissions of lost packets. It answers
+/// how a backend would feel from afar without touching tc or netem.
+pub struct NetworkClient<C> {
+    inner: C,
+    config: NetworkConfig,
+    /// The way to the storage and the way back.
+    links: Arc<(Link, Link)>,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl<C> NetworkClient<C> where C: TestClient {
+    /// Create a client delaying by randomness seeded by the seed, if any.
+    pub fn new(inner: C, config: NetworkConfig, seed: Option<u64>) -> Self {
+        println!("INIT NETWORK SIMULATION");
+        println!("  RTT:           {:?}", config.rtt);
+        println!("  JITTER:        {:?}", config.jitter);
+        match config.bandwidth {
+            Some(bandwidth) => println!("  BANDWIDTH:     {} bytes/s", bandwidth),
+            None => println!("  BANDWIDTH:     unlimited"),
+        }
+        println!("  LOSS RATE:     {}", config.loss_rate);
+        let rng = seed.map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+        Self { inner, config, links: Arc::default(), rng: Arc::new(Mutex::new(rng)) }
+    }
+}
+
+impl<C> TestClient for NetworkClient<C> where C: TestClient {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn init(&self) {
+        self.inner.init()
+    }
+
+    fn gen_unique_key(&self) -> String {
+        self.inner.gen_unique_key()
+    }
+
+    fn gen_namespaced_key(&self, namespace: &str) -> String {
+        self.inner.gen_namespaced_key(namespace)
+    }
+
+    fn cleanup(&self) -> Result<()> {
+        self.inner.cleanup()
+    }
+
+    fn describe(&self) -> BTreeMap<String, String> {
+        let mut details = self.inner.describe();
+        details.insert("simulated_rtt".to_string(), format!("{:?}", self.config.rtt));
+        details.insert("simulated_jitter".to_string(), format!("{:?}", self.config.jitter));
+        if let Some(bandwidth) = self.config.bandwidth {
+            details.insert("simulated_bandwidth".to_string(), bandwidth.to_string());
+        }
+        details.insert("simulated_loss_rate".to_string(), self.config.loss_rate.to_string());
+        details
+    }
+
+    fn handler(&self, ctx: &HandlerContext) -> Arc<dyn TestClientHandler> {
+        Arc::new(NetworkClientHandler {
+            inner: self.inner.handler(ctx),
+            config: self.config.clone(),
+            links: self.links.clone(),
+            rng: self.rng.clone(),
+        })
+    }
+}
+
+pub struct NetworkClientHandler {
+    inner: Arc<dyn TestClientHandler>,
+    config: NetworkConfig,
+    links: Arc<(Link, Link)>,
+    rng: Arc<Mutex<StdRng>>,
+}
+
+impl NetworkClientHandler {
+    /// Draw a number in `[0, 1)` deciding a delay.
+    fn draw(&self) -> f64 {
+        self.rng.lock().unwrap_or_else(PoisonError::into_inner).gen()
+    }
+
+    /// Carry so many bytes one way: a half of the RTT with its jitter, the
+    /// transfer, and a retransmission if unlucky.
+    async fn carry(&self, link: &Link, len: usize) {
+        let jitter = self.config.jitter.mul_f64(self.draw());
+        let mut delay = self.config.rtt / 2 + jitter;
+        if self.draw() < self.config.loss_rate / 2.0 {
+            delay += (self.config.rtt * 2).max(MIN_RTO);
+        }
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+        if let Some(bandwidth) = self.config.bandwidth.filter(|bandwidth| *bandwidth > 0) {
+            link.transfer(len + HEADER_LEN, bandwidth).await;
+        }
+    }
+
+    async fn send(&self, len: usize) {
+        self.carry(&self.links.0, len).await
+    }
+
+    async fn receive(&self, len: usize) {
+        self.carry(&self.links.1, len).await
+    }
+}
+
+#[async_trait]
+impl TestClientHandler for NetworkClientHandler {
+    fn capabilities(&self) -> Capabilities {
+        self.inner.capabilities()
+    }
+
+    async fn setup(&self) -> Result<()> {
+        self.inner.setup().await
+    }
+
+    async fn teardown(&self) -> Result<()> {
+        self.inner.teardown().await
+    }
+
+    fn take_phases(&self) -> Vec<(Metric, Duration)> {
+        self.inner.take_phases()
+    }
+
+    async fn space_used(&self) -> Result<Option<u64>> {
+        self.inner.space_used().await
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        self.send(key.len() + value.len()).await;
+        let written = self.inner.write(key, value).await;
+        self.receive(0).await;
+        written
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        self.send(key.len()).await;
+        let read = self.inner.read(key).await;
+        self.receive(read.as_ref().map_or(0, |value| value.len())).await;
+        read
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        self.send(key.len()).await;
+        let deleted = self.inner.delete(key).await;
+        self.receive(0).await;
+        deleted
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        self.send(key.len()).await;
+        let size = self.inner.size(key).await;
+        self.receive(0).await;
+        size
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        self.send(prefix.len()).await;
+        let keys = self.inner.list(prefix).await;
+        self.receive(keys.as_ref().map_or(0, |keys| keys.iter().map(String::len).sum())).await;
+        keys
+    }
+
+    async fn append(&self, key: &str, value: &str) -> Result<()> {
+        self.send(key.len() + value.len()).await;
+        let appended = self.inner.append(key, value).await;
+        self.receive(0).await;
+        appended
+    }
+
+    async fn evict(&self, key: &str) -> Result<()> {
+        self.send(key.len()).await;
+        let evicted = self.inner.evict(key).await;
+        self.receive(0).await;
+        evicted
+    }
+
+    async fn set_xattr(&self, key: &str, name: &str, value: &str) -> Result<()> {
+        self.send(key.len() + name.len() + value.len()).await;
+        let set = self.inner.set_xattr(key, name, value).await;
+        self.receive(0).await;
+        set
+    }
+
+    async fn get_xattr(&self, key: &str, name: &str) -> Result<String> {
+        self.send(key.len() + name.len()).await;
+        let got = self.inner.get_xattr(key, name).await;
+        self.receive(got.as_ref().map_or(0, |value| value.len())).await;
+        got
+    }
+
+    async fn remove_xattr(&self, key: &str, name: &str) -> Result<()> {
+        self.send(key.len() + name.len()).await;
+        let removed = self.inner.remove_xattr(key, name).await;
+        self.receive(0).await;
+        removed
+    }
+
+    async fn punch_hole(&self, key: &str, offset: u64, len: u64) -> Result<()> {
+        self.send(key.len()).await;
+        let punched = self.inner.punch_hole(key, offset, len).await;
+        self.receive(0).await;
+        punched
+    }
+
+    async fn copy(&self, key: &str, to: &str) -> Result<()> {
+        self.send(key.len() + to.len()).await;
+        let copied = self.inner.copy(key, to).await;
+        self.receive(0).await;
+        copied
+    }
+
+    async fn write_versioned(&self, key: &str, value: &str) -> Result<String> {
+        self.send(key.len() + value.len()).await;
+        let written = self.inner.write_versioned(key, value).await;
+        self.receive(written.as_ref().map_or(0, |version| version.len())).await;
+        written
+    }
+
+    async fn read_version(&self, key: &str, version: &str) -> Result<String> {
+        self.send(key.len() + version.len()).await;
+        let read = self.inner.read_version(key, version).await;
+        self.receive(read.as_ref().map_or(0, |value| value.len())).await;
+        read
+    }
+}
@@ -0,0 +1,265 @@
+use std::{env, net::SocketAddr, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use super::{hooks::Hook, retry::RetryPolicy, slo::Objective, tenant::Tenant};
+
+/// Options controls how the Tester runs a test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Options {
+    /// The length of the value written to every key.
+    pub payload_len: usize,
+
+    /// Draw the length of every value between the payload length and this,
+    /// log-uniformly, and summarize the latencies by size class.
+    pub payload_len_max: Option<usize>,
+
+    /// Seed the randomness of the test (the payload, the choices of keys and
+    /// the sampling of requests), so runs with the same seed issue the same
+    /// operations. The tester picks one if not given.
+    pub seed: Option<u64>,
+
+    /// The QPS of every step, in order.
+    pub qps_steps: Vec<u64>,
+
+    /// Sweep the payload lengths instead of the QPS: a step per length, in
+    /// order, all at the first QPS. No sweep if empty.
+    pub size_sweep: Vec<usize>,
+
+    /// Sweep the closed-loop concurrency instead of the QPS: a step per count
+    /// of workers, in order, every worker issuing its next request once its
+    /// last one finished. No sweep if empty.
+    pub concurrency_sweep: Vec<usize>,
+
+    /// How long every step lasts.
+    pub step_duration: Duration,
+
+    /// Warm up at the first QPS for so long before the steps, throwing the
+    /// results away.
+    pub warm_up: Option<Duration>,
+
+    /// Cancel an operation taking longer than this, counting it as a timeout.
+    pub timeout: Option<Duration>,
+
+    /// Retry failed operations under the policy, if any.
+    pub retry: Option<RetryPolicy>,
+
+    /// How many handlers requests of a step share, e.g. connections to the
+    /// storage.
+    pub pool_size: usize,
+
+    /// Verify the length and the checksum of every read value.
+    pub verify: bool,
+
+    /// Check the stored size of every written key against the value, to find
+    /// partial writes.
+    pub check_size: bool,
+
+    /// Evict every written key from the caches of the storage before reading
+    /// it, out of the measured time, so reads reflect the device rather than
+    /// RAM. Needs `Capability::Evict`.
+    pub cold_reads: bool,
+
+    /// Drop the page cache of the system before every step (root required).
+    pub drop_caches: bool,
+
+    /// Run this command to drop the page cache instead, e.g. a helper allowed
+    /// by sudo.
+    pub drop_caches_command: Option<String>,
+
+    /// Set an extended attribute of so many bytes on every written key, then
+    /// get and remove it, measuring each. Needs `Capability::Xattr`.
+    pub xattr_len: Option<usize>,
+
+    /// Punch a hole of up to so many bytes in the middle of every read key,
+    /// then read it back over the hole, measuring both, and checking the hole
+    /// reads as zeros. Needs `Capability::PunchHole`.
+    pub hole_len: Option<usize>,
+
+    /// Copy every read key to another one, measuring the copy, then read the
+    /// copy back to verify it and delete it. Needs `Capability::Copy`.
+    pub copy: bool,
+
+    /// Write every key as a version, overwrite it after the reads, then read
+    /// the version written back by its id, measuring the read against the
+    /// reads of the latest. Needs `Capability::Versions`.
+    pub versioned: bool,
+
+    /// Measure the phases of connecting, by the backends which can: the name
+    /// resolution, the TCP connect, the TLS handshake and the time to the
+    /// first byte of a response.
+    pub connection_phases: bool,
+
+    /// Probe how long a written key takes to be readable, giving up after the
+    /// timeout.
+    pub probe_visibility: Option<Duration>,
+
+    /// Read keys by another handler of the pool than the one which wrote
+    /// them, e.g. on another connection, so a write is checked visible beyond
+    /// its connection. The pool has 2 handlers at least.
+    pub cross_read: bool,
+
+    /// Probe how long a deleted key stays readable, for up to the window.
+    pub probe_delete_visibility: Option<Duration>,
+
+    /// Show a progress bar while issuing requests. Disable it when the output
+    /// is not a terminal (e.g. in CI).
+    pub progress: bool,
+
+    /// Let requests write and read a pool of so many shared keys instead of
+    /// unique ones, and check that reads never go back in versions.
+    pub shared_keys: Option<usize>,
+
+    /// Run so many independent key namespaces concurrently, each issuing the
+    /// QPS of the step, like applications sharing the storage. They are
+    /// summarized apart and together. The shared key pool, if any, is shared
+    /// by all of them.
+    pub namespaces: usize,
+
+    /// Run these tenants concurrently instead of the namespaces, each in a
+    /// namespace of its own with its own QPS, payload length and reads.
+    pub tenants: Vec<Tenant>,
+
+    /// Tag this ratio of requests as high priority, issued by handlers of
+    /// their own which may hint it to the storage, and summarize them apart
+    /// from the bulk.
+    pub priority_ratio: Option<f64>,
+
+    /// Keep this ratio of keys until the end of the test, then re-read and
+    /// validate all of them.
+    pub scrub_ratio: Option<f64>,
+
+    /// Only write keys while measuring, then read back and delete all keys
+    /// written in a step after it. Requests on shared keys are not affected.
+    pub verify_at_end: bool,
+
+    /// The maximum ratio of failed operations allowed in a step.
+    pub max_error_rate: f64,
+
+    /// Report the stalls of the operations, if set: seconds whose p99 is over
+    /// so many times their median, for at least `stall_secs` in a row.
+    pub stall_factor: Option<f64>,
+    pub stall_secs: u64,
+
+    /// The objectives every step must keep, e.g. `write.p99 < 20ms`.
+    pub objectives: Vec<Objective>,
+
+    /// Commands to run at points of the test.
+    pub hooks: Vec<Hook>,
+
+    /// Stream per-second metrics as server-sent events on the address.
+    pub live: Option<SocketAddr>,
+
+    /// Export Prometheus metrics on the address.
+    pub prometheus: Option<SocketAddr>,
+
+    /// Where to write the Grafana dashboard when Prometheus metrics are
+    /// exported.
+    pub grafana_dashboard: PathBuf,
+
+    /// Where to draw the charts, for the `ChartReporter` of the caller.
+    pub images_dir: PathBuf,
+
+    /// Measure the overhead of the tester at every QPS for so long before the
+    /// test, against a backend doing nothing, and report it with the steps.
+    pub calibrate: Option<Duration>,
+
+    /// Subtract the calibrated overhead from the p50 and p99 latencies.
+    pub subtract_overhead: bool,
+
+    /// Sample the space the data takes on the storage at the interval, next
+    /// to the bytes written.
+    pub space_interval: Option<Duration>,
+
+    /// Check the health of the backend at the interval in parallel with the
+    /// load, for its availability through the test.
+    pub health_interval: Option<Duration>,
+
+    /// Profile the CPU of the process through every step, writing a
+    /// flamegraph per step into the directory. Needs the `profile` feature.
+    pub profile_dir: Option<PathBuf>,
+
+    /// Keep the data the test wrote, instead of having the client clean it up
+    /// at the end.
+    pub keep_data: bool,
+
+    /// Checkpoint the run to the path after every finished step, and resume
+    /// it from there if the path exists. An interrupted run keeps its data
+    /// to be resumed.
+    pub checkpoint: Option<PathBuf>,
+
+    /// The name of the test among the others of a run, e.g. `localfs-fsync`
+    /// of localfs in a sync mode. The backend name if not given.
+    pub label: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            payload_len: 16 * 1024 * 1024, /* 16MiB */
+            payload_len_max: None,
+            seed: None,
+            qps_steps: vec![5, 10, 20, 50, 100, 200, 500, 1000],
+            step_duration: Duration::from_secs(30),
+            warm_up: None,
+            timeout: None,
+            retry: None,
+            pool_size: 1,
+            verify: true,
+            check_size: false,
+            cold_reads: false,
+            drop_caches: false,
+            drop_caches_command: None,
+            xattr_len: None,
+            hole_len: None,
+            copy: false,
+            versioned: false,
+            connection_phases: false,
+            probe_visibility: None,
+            cross_read: false,
+            probe_delete_visibility: None,
+            progress: true,
+            shared_keys: None,
+            namespaces: 1,
+            tenants: vec![],
+            priority_ratio: None,
+            scrub_ratio: None,
+            verify_at_end: false,
+            max_error_rate: 0.0,
+            stall_factor: None,
+            stall_secs: 3,
+            objectives: vec![],
+            hooks: vec![],
+            live: None,
+            prometheus: None,
+            grafana_dashboard: PathBuf::from("iotest-grafana-dashboard.json"),
+            images_dir: env::temp_dir().join("images"),
+            calibrate: None,
+            subtract_overhead: false,
+            space_interval: None,
+            health_interval: None,
+            profile_dir: None,
+            keep_data: false,
+            checkpoint: None,
+            label: None,
+            size_sweep: vec![],
+            concurrency_sweep: vec![],
+        }
+    }
+}
+
+impl Options {
+    /// The QPS (or the workers, in a concurrency sweep) and the payload
+    /// length of every step, in order.
+    pub fn steps(&self) -> Vec<(u64, usize)> {
+        if !self.concurrency_sweep.is_empty() {
+            return self.concurrency_sweep.iter().map(|&workers| (workers as u64, self.payload_len)).collect();
+        }
+        if self.size_sweep.is_empty() {
+            return self.qps_steps.iter().map(|&qps| (qps, self.payload_len)).collect();
+        }
+        let qps = self.qps_steps.first().copied().unwrap_or(1);
+        self.size_sweep.iter().map(|&len| (qps, len)).collect()
+    }
+}
@@ -0,0 +1,147 @@
+use rand::{distributions::Alphanumeric, Rng};
+
+/// The fixed part of the header: the length of the whole value, the length of
+/// the data in a block, the sequence number of the request and the length of
+/// the key, all in hex. The key itself follows.
+pub const HEADER_LEN: usize = 16 + 8 + 16 + 8;
+
+/// Every block starts with the sequence number of the request and the CRC32 of
+/// its data, in hex.
+pub const BLOCK_HEADER_LEN: usize = 16 + 8;
+
+/// The length of the data in a block (the last one may be shorter).
+pub const BLOCK_LEN: usize = 4096;
+
+/// Payload generates the values written to keys. Every value starts with a
+/// header naming the key and the request it is written by, followed by framed
+/// blocks each naming the request again, so a read value can be verified by
+/// itself: a truncated value, a corrupted block, another object's data, or a
+/// torn value mixing blocks of different requests are all detected. The data
+/// is shared by all values so its checksums are only computed once.
+pub struct Payload {
+    body: String,
+    checksums: Vec<u32>,
+}
+
+/// Mismatch is why a value is not the expected one.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub msg: String,
+    /// The value mixes blocks written by different requests, e.g. read while
+    /// being overwritten.
+    pub torn: bool,
+}
+
+impl Mismatch {
+    fn new(msg: impl Into<String>) -> Self {
+        Self { msg: msg.into(), torn: false }
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Mismatch>;
+
+impl Payload {
+    /// Create a payload whose values are about `len` bytes (the key and the
+    /// block headers are not counted).
+    pub fn new(len: usize) -> Self {
+        Self::with_rng(len, &mut rand::thread_rng())
+    }
+
+    /// Create a payload whose data is drawn from the generator, so a seeded one
+    /// always creates the same payload.
+    pub fn with_rng<R: Rng>(len: usize, rng: &mut R) -> Self {
+        let body: String = rng
+            .sample_iter(&Alphanumeric)
+            .take(len.saturating_sub(HEADER_LEN))
+            .map(char::from)
+            .collect();
+        let checksums = body.as_bytes().chunks(BLOCK_LEN).map(crc32fast::hash).collect();
+        Self { body, checksums }
+    }
+
+    /// Generate the value written to the key by the `seq`-th request.
+    pub fn value(&self, key: &str, seq: u64) -> String {
+        self.value_of_len(key, seq, HEADER_LEN + self.body.len())
+    }
+
+    /// Generate the value written to the key by the `seq`-th request, about
+    /// `len` bytes like `with_rng` but no longer than the payload's.
+    pub fn value_of_len(&self, key: &str, seq: u64, len: usize) -> String {
+        let body = &self.body.as_bytes()[..len.saturating_sub(HEADER_LEN).min(self.body.len())];
+        let blocks = (body.len() + BLOCK_LEN - 1) / BLOCK_LEN;
+        let len = HEADER_LEN + key.len() + blocks * BLOCK_HEADER_LEN + body.len();
+        let mut value = String::with_capacity(len);
+        value.push_str(&format!("{:016x}{:08x}{:016x}{:08x}", len, BLOCK_LEN, seq, key.len()));
+        value.push_str(key);
+        // The body is alphanumeric, so every chunk is valid UTF-8.
+        for (idx, chunk) in body.chunks(BLOCK_LEN).enumerate() {
+            // A block cut short has a checksum of its own.
+            let checksum = if chunk.len() == (self.body.len() - idx * BLOCK_LEN).min(BLOCK_LEN) {
+                self.checksums[idx]
+            } else {
+                crc32fast::hash(chunk)
+            };
+            value.push_str(&format!("{:016x}{:08x}", seq, checksum));
+            value.push_str(std::str::from_utf8(chunk).unwrap());
+        }
+        value
+    }
+}
+
+/// Verify that the value is the one the `seq`-th request wrote to the key.
+pub fn verify(value: &str, key: &str, seq: u64) -> Result<()> {
+    let value_seq = inspect(value, key)?;
+    if value_seq != seq {
+        return Err(Mismatch::new(format!("got the value of request {}, expect request {}", value_seq, seq)));
+    }
+    Ok(())
+}
+
+/// Verify that the value is an intact one written to the key, and get the
+/// sequence number of the request which wrote it.
+pub fn inspect(value: &str, key: &str) -> Result<u64> {
+    let header = value.as_bytes().get(..HEADER_LEN)
+        .ok_or_else(|| Mismatch::new(format!("short payload: {} bytes", value.len())))?;
+    let field = |range: std::ops::Range<usize>| std::str::from_utf8(&header[range]).ok()
+        .and_then(|field| u64::from_str_radix(field, 16).ok())
+        .ok_or_else(|| Mismatch::new("malformed payload header"));
+    let len = field(0..16)? as usize;
+    let block_len = field(16..24)? as usize;
+    let value_seq = field(24..40)?;
+    let key_len = field(40..48)? as usize;
+    if value.len() != len {
+        return Err(Mismatch::new(format!("length mismatch: expect {}, got {}", len, value.len())));
+    }
+    if block_len == 0 {
+        return Err(Mismatch::new("malformed payload header"));
+    }
+    let value_key = value.get(HEADER_LEN..HEADER_LEN + key_len)
+        .ok_or_else(|| Mismatch::new("malformed payload header"))?;
+    if value_key != key {
+        return Err(Mismatch::new(format!("got the value of {} (request {})", value_key, value_seq)));
+    }
+
+    let mut torn = None;
+    let blocks = value.as_bytes()[HEADER_LEN + key_len..].chunks(BLOCK_HEADER_LEN + block_len);
+    for (idx, block) in blocks.enumerate() {
+        let block_field = |range: std::ops::Range<usize>| block.get(range)
+            .and_then(|field| std::str::from_utf8(field).ok())
+            .and_then(|field| u64::from_str_radix(field, 16).ok())
+            .ok_or_else(|| Mismatch::new(format!("malformed header of block {}", idx)));
+        let block_seq = block_field(0..16)?;
+        let checksum = block_field(16..24)? as u32;
+        if crc32fast::hash(&block[BLOCK_HEADER_LEN..]) != checksum {
+            return Err(Mismatch::new(format!("checksum mismatch in block {}", idx)));
+        }
+        if block_seq != value_seq && torn.is_none() {
+            torn = Some((idx, block_seq));
+        }
+    }
+    if let Some((idx, block_seq)) = torn {
+        return Err(Mismatch {
+            msg: format!("torn read: block {} is of request {}, the header of request {}", idx, block_seq, value_seq),
+            torn: true,
+        });
+    }
+    Ok(value_seq)
+}
@@ -0,0 +1,33 @@
+use std::{sync::{Mutex, PoisonError}, time::Duration};
+
+use super::stats::Metric;
+
+/// PhaseTimer keeps the phases of connections and requests a handler
+/// measures, e.g. `Metric::Connect`, until the tester takes them by
+/// `TestClientHandler::take_phases`. Nothing is kept unless enabled by
+/// `HandlerContext::connection_phases`.
+#[derive(Debug, Default)]
+pub struct PhaseTimer {
+    enabled: bool,
+    phases: Mutex<Vec<(Metric, Duration)>>,
+}
+
+impl PhaseTimer {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, phases: Mutex::new(vec![]) }
+    }
+
+    pub fn record(&self, metric: Metric, elapsed: Duration) {
+        if self.enabled {
+            self.phases.lock().unwrap_or_else(PoisonError::into_inner).push((metric, elapsed));
+        }
+    }
+
+    /// The phases recorded since last taken.
+    pub fn take(&self) -> Vec<(Metric, Duration)> {
+        if !self.enabled {
+            return vec![];
+        }
+        std::mem::take(&mut *self.phases.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
@@ -0,0 +1,97 @@
+use std::{sync::{atomic::{AtomicU64, AtomicUsize, Ordering}, Arc}, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+use super::client::{HandlerContext, Result, TestClient, TestClientHandler};
+
+/// HandlerPool keeps a fixed number of handlers for a step and hands them out
+/// in turn, so a handler (e.g. a connection of a network backend) is reused by
+/// many requests instead of being made for every one.
+pub struct HandlerPool {
+    handlers: Vec<Arc<dyn TestClientHandler>>,
+    next: AtomicUsize,
+    checkouts: AtomicU64,
+    /// How long the setup of every handler took.
+    setups: Vec<Duration>,
+}
+
+/// PoolStats is how the handlers of a step were used.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PoolStats {
+    /// The number of handlers made.
+    pub size: usize,
+    /// The number of times a handler was handed out.
+    pub checkouts: u64,
+    /// Checkouts served by a handler which had been handed out before.
+    pub reuses: u64,
+    /// The mean and the longest setup of a handler, e.g. connecting and a
+    /// TLS handshake, apart from the requests.
+    #[serde(default)]
+    pub setup_mean_us: u64,
+    #[serde(default)]
+    pub setup_max_us: u64,
+}
+
+impl HandlerPool {
+    /// Make `size` handlers from the client, at least one, and set them up.
+    pub async fn new<C: TestClient>(client: &C, size: usize, timeout: Option<Duration>, connection_phases: bool, priority: bool, cancel: &CancellationToken) -> Result<Self> {
+        let workers = size.max(1);
+        let mut pool = Self {
+            handlers: (0..workers)
+                .map(|worker| client.handler(&HandlerContext { worker, workers, timeout, cancel: cancel.clone(), connection_phases, priority }))
+                .collect(),
+            next: AtomicUsize::new(0),
+            checkouts: AtomicU64::new(0),
+            setups: Vec::with_capacity(workers),
+        };
+        for (idx, hdlr) in pool.handlers.iter().enumerate() {
+            let start = Instant::now();
+            let set_up = hdlr.setup().await;
+            pool.setups.push(start.elapsed());
+            // The setup is reported on its own, not in the first step.
+            hdlr.take_phases();
+            if let Err(err) = set_up {
+                // Release the handlers already set up.
+                for hdlr in &pool.handlers[..idx] {
+                    let _ = hdlr.teardown().await;
+                }
+                return Err(err);
+            }
+        }
+        Ok(pool)
+    }
+
+    /// Tear all handlers down, even if some fail, returning the first error.
+    pub async fn teardown(&self) -> Result<()> {
+        let mut result = Ok(());
+        for hdlr in &self.handlers {
+            let torn_down = hdlr.teardown().await;
+            if result.is_ok() {
+                result = torn_down;
+            }
+        }
+        result
+    }
+
+    /// Hand out the next handler, round robin.
+    pub fn get(&self) -> Arc<dyn TestClientHandler> {
+        self.checkouts.fetch_add(1, Ordering::Relaxed);
+        let idx = self.next.fetch_add(1, Ordering::Relaxed) % self.handlers.len();
+        self.handlers[idx].clone()
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let checkouts = self.checkouts.load(Ordering::Relaxed);
+        let used = checkouts.min(self.handlers.len() as u64);
+        let setup_total: Duration = self.setups.iter().sum();
+        PoolStats {
+            size: self.handlers.len(),
+            checkouts,
+            reuses: checkouts - used,
+            setup_mean_us: (setup_total / self.setups.len().max(1) as u32).as_micros() as u64,
+            setup_max_us: self.setups.iter().max().map_or(0, |max| max.as_micros() as u64),
+        }
+    }
+}
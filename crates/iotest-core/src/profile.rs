@@ -0,0 +1,39 @@
+use std::{fs::{create_dir_all, File}, path::Path};
+
+use pprof::{ProfilerGuard, ProfilerGuardBuilder};
+
+use super::client::{Error, Result};
+
+/// How many times a second the stacks are sampled.
+const FREQUENCY: i32 = 997;
+
+/// StepProfiler samples the CPU of the whole process through a step, for a
+/// flamegraph of where the tester itself spends its time at the QPS, to tell
+/// whether it keeps up with the rate rather than the backend.
+pub struct StepProfiler {
+    guard: ProfilerGuard<'static>,
+}
+
+impl StepProfiler {
+    pub fn start() -> Result<Self> {
+        let guard = ProfilerGuardBuilder::default()
+            .frequency(FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(|err| Error::new(format!("start profiling: {}", err)))?;
+        Ok(Self { guard })
+    }
+
+    /// Stop sampling, and write the flamegraph as SVG to the path.
+    pub fn finish(self, path: &Path) -> Result<()> {
+        let report = self.guard.report().build()
+            .map_err(|err| Error::new(format!("build profile: {}", err)))?;
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+        }
+        let file = File::create(path)
+            .map_err(|err| Error::from_io_error(&format!("create {}", path.display()), err))?;
+        report.flamegraph(file)
+            .map_err(|err| Error::new(format!("write {}: {}", path.display(), err)))
+    }
+}
@@ -0,0 +1,125 @@
+use std::{fmt::Write, net::SocketAddr, sync::{Arc, Mutex, PoisonError}, time::Duration};
+
+use tokio::{io::{AsyncReadExt, AsyncWriteExt}, net::{TcpListener, TcpStream}};
+
+use super::{client::{Error, Result}, stats::{Metric, BUCKETS, BUCKETS_LEN}};
+
+pub const METRIC_TARGET_QPS: &str = "iotest_target_qps";
+pub const METRIC_OPERATIONS: &str = "iotest_operations_total";
+pub const METRIC_ERRORS: &str = "iotest_operation_errors_total";
+pub const METRIC_LATENCY: &str = "iotest_operation_latency_seconds";
+
+/// PrometheusExporter exposes the metrics of a run in the Prometheus text
+/// format, so long runs can be watched from an existing monitoring stack.
+pub struct PrometheusExporter {
+    metrics: Mutex<Metrics>,
+}
+
+struct Metrics {
+    target_qps: u64,
+    ops: Vec<OpMetrics>,
+}
+
+#[derive(Clone)]
+struct OpMetrics {
+    total: u64,
+    errors: u64,
+    /// The count of the latencies falling in every bucket (not cumulative).
+    buckets: Vec<u64>,
+    sum_seconds: f64,
+}
+
+impl PrometheusExporter {
+    /// Listen on the address and serve the metrics on every path.
+    pub async fn bind(addr: SocketAddr) -> Result<Arc<Self>> {
+        let listener = TcpListener::bind(addr).await
+            .map_err(|err| Error::from_io_error(&format!("bind {}", addr), err))?;
+        println!("PROMETHEUS METRICS");
+        println!("  URL:           http://{}/metrics", addr);
+        let exporter = Arc::new(Self {
+            metrics: Mutex::new(Metrics {
+                target_qps: 0,
+                ops: vec![OpMetrics {
+                    total: 0,
+                    errors: 0,
+                    buckets: vec![0; BUCKETS_LEN + 1],
+                    sum_seconds: 0.0,
+                }; Metric::ALL.len()],
+            }),
+        });
+        let accept_exporter = exporter.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, accept_exporter.clone()));
+            }
+        });
+        Ok(exporter)
+    }
+
+    pub fn set_target_qps(&self, qps: u64) {
+        self.metrics.lock().unwrap_or_else(PoisonError::into_inner).target_qps = qps;
+    }
+
+    pub fn record(&self, result: &[(Metric, Result<Duration>)]) {
+        let mut metrics = self.metrics.lock().unwrap_or_else(PoisonError::into_inner);
+        for (metric, latency) in result {
+            let idx = Metric::ALL.iter().position(|m| m == metric).unwrap();
+            let op_metrics = &mut metrics.ops[idx];
+            op_metrics.total += 1;
+            match latency {
+                Ok(latency) => {
+                    let micros = latency.as_micros() as f64;
+                    let bucket = BUCKETS.iter().position(|bound| micros <= *bound).unwrap_or(BUCKETS_LEN);
+                    op_metrics.buckets[bucket] += 1;
+                    op_metrics.sum_seconds += latency.as_secs_f64();
+                }
+                Err(_) => op_metrics.errors += 1,
+            }
+        }
+    }
+
+    fn render(&self) -> String {
+        let metrics = self.metrics.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut out = String::new();
+        writeln!(out, "# TYPE {} gauge", METRIC_TARGET_QPS).unwrap();
+        writeln!(out, "{} {}", METRIC_TARGET_QPS, metrics.target_qps).unwrap();
+        writeln!(out, "# TYPE {} counter", METRIC_OPERATIONS).unwrap();
+        for (metric, op_metrics) in Metric::ALL.iter().zip(metrics.ops.iter()) {
+            writeln!(out, "{}{{op=\"{}\"}} {}", METRIC_OPERATIONS, metric.name(), op_metrics.total).unwrap();
+        }
+        writeln!(out, "# TYPE {} counter", METRIC_ERRORS).unwrap();
+        for (metric, op_metrics) in Metric::ALL.iter().zip(metrics.ops.iter()) {
+            writeln!(out, "{}{{op=\"{}\"}} {}", METRIC_ERRORS, metric.name(), op_metrics.errors).unwrap();
+        }
+        writeln!(out, "# TYPE {} histogram", METRIC_LATENCY).unwrap();
+        for (metric, op_metrics) in Metric::ALL.iter().zip(metrics.ops.iter()) {
+            let mut cumulative = 0;
+            for (bound, count) in BUCKETS.iter().zip(op_metrics.buckets.iter()) {
+                cumulative += count;
+                writeln!(out, "{}_bucket{{op=\"{}\",le=\"{}\"}} {}", METRIC_LATENCY, metric.name(), bound / 1_000_000.0, cumulative).unwrap();
+            }
+            cumulative += op_metrics.buckets[BUCKETS_LEN];
+            writeln!(out, "{}_bucket{{op=\"{}\",le=\"+Inf\"}} {}", METRIC_LATENCY, metric.name(), cumulative).unwrap();
+            writeln!(out, "{}_sum{{op=\"{}\"}} {}", METRIC_LATENCY, metric.name(), op_metrics.sum_seconds).unwrap();
+            writeln!(out, "{}_count{{op=\"{}\"}} {}", METRIC_LATENCY, metric.name(), cumulative).unwrap();
+        }
+        out
+    }
+}
+
+async fn serve(mut stream: TcpStream, exporter: Arc<PrometheusExporter>) {
+    let mut buf = [0; 1024];
+    if stream.read(&mut buf).await.is_err() {
+        return;
+    }
+    let body = exporter.render();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\n\
+        Content-Type: text/plain; version=0.0.4\r\n\
+        Content-Length: {}\r\n\
+        Connection: close\r\n\r\n{}",
+        body.len(),
+        body,
+    );
+    let _ = stream.write_all(response.as_bytes()).await;
+}
@@ -0,0 +1,49 @@
+use std::collections::BTreeMap;
+
+use super::client::{Error, Result, TestClient};
+
+/// Settings are how to connect to a backend, e.g. `prefix` for localfs or
+/// `command` for plugin.
+pub type Settings = BTreeMap<String, String>;
+
+/// Constructor creates a client of a backend from its settings.
+pub type Constructor = fn(&Settings) -> Result<Box<dyn TestClient>>;
+
+/// Registry maps backend names to constructors of their clients, so the
+/// backend of a test can be chosen at runtime. The backends are registered by
+/// the binary, from the backend crates it is built with.
+pub struct Registry {
+    backends: BTreeMap<&'static str, Constructor>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self { backends: BTreeMap::new() }
+    }
+
+    /// Register a backend, replacing the one of the same name if any.
+    pub fn register(&mut self, name: &'static str, constructor: Constructor) {
+        self.backends.insert(name, constructor);
+    }
+
+    /// The names of the registered backends, sorted.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.backends.keys().copied().collect()
+    }
+
+    /// The backend to test if none is given: localfs if registered, or else
+    /// the first one.
+    pub fn default_name(&self) -> Option<&'static str> {
+        if self.backends.contains_key("localfs") {
+            return Some("localfs");
+        }
+        self.backends.keys().next().copied()
+    }
+
+    /// Create a client of the backend.
+    pub fn create(&self, name: &str, settings: &Settings) -> Result<Box<dyn TestClient>> {
+        let constructor = self.backends.get(name)
+            .ok_or_else(|| Error::new(format!("unknown backend: {}", name)))?;
+        constructor(settings)
+    }
+}
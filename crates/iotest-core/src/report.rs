@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use metrics_util::Histogram;
+
+use super::{client::Op, stats::Metric, summary::{MetricSummary, Summary, Verdict}, timeseries::TimeSeries};
+
+/// TestReport is everything a test results in: the summary, and the latency
+/// histograms and the time series it is summarized from. The tester doesn't
+/// print or draw it, its caller does.
+pub struct TestReport {
+    pub summary: Summary,
+    /// The histograms of every step, in order.
+    pub histograms: Vec<StepHistograms>,
+    /// The latencies second by second since the test started.
+    pub timeseries: TimeSeries,
+}
+
+/// StepHistograms is the latency histogram of every metric of a step.
+pub struct StepHistograms {
+    pub qps: u64,
+    pub payload_len: usize,
+    pub metrics: Vec<(Metric, Histogram)>,
+}
+
+/// Assertions on the results, to fail an integration test (e.g. a
+/// `#[tokio::test]`) on a latency regression or errors. They panic, telling
+/// the step which failed, and chain:
+///
+/// ```no_run
+/// # async fn run<C: iotest_core::TestClient>(client: C) {
+/// use std::time::Duration;
+/// use iotest_core::{Op, Tester};
+///
+/// Tester::builder(client)
+///     .qps_steps([100])
+///     .run()
+///     .await
+///     .assert_success()
+///     .assert_p99(Op::Write, Duration::from_millis(5));
+/// # }
+/// ```
+impl TestReport {
+    /// The test succeeded.
+    pub fn assert_success(&self) -> &Self {
+        self.assert_verdict(Verdict::Success)
+    }
+
+    pub fn assert_verdict(&self, verdict: Verdict) -> &Self {
+        assert_eq!(self.summary.verdict, verdict, "iotest verdict");
+        self
+    }
+
+    /// The p50 latency of the operation is under the limit in every step.
+    pub fn assert_p50(&self, op: Op, limit: Duration) -> &Self {
+        for (qps, summary) in self.metric(op) {
+            let p50 = Duration::from_micros(summary.p50_us);
+            assert!(p50 <= limit, "iotest: p50 of {} at {} QPS is {:?}, over {:?}", op.name(), qps, p50, limit);
+        }
+        self
+    }
+
+    /// The p99 latency of the operation is under the limit in every step.
+    pub fn assert_p99(&self, op: Op, limit: Duration) -> &Self {
+        for (qps, summary) in self.metric(op) {
+            let p99 = Duration::from_micros(summary.p99_us);
+            assert!(p99 <= limit, "iotest: p99 of {} at {} QPS is {:?}, over {:?}", op.name(), qps, p99, limit);
+        }
+        self
+    }
+
+    /// The ratio of failed (or timed out) operations is at most `max` in
+    /// every step.
+    pub fn assert_error_rate(&self, op: Op, max: f64) -> &Self {
+        for (qps, summary) in self.metric(op) {
+            let rate = summary.error_rate + summary.timeout_rate;
+            assert!(rate <= max, "iotest: error rate of {} at {} QPS is {:.4}, over {:.4}", op.name(), qps, rate, max);
+        }
+        self
+    }
+
+    /// The summary of the operation in every step, with the QPS of the step.
+    fn metric(&self, op: Op) -> impl Iterator<Item = (u64, &MetricSummary)> {
+        self.summary.steps.iter().filter_map(move |step| {
+            step.metrics.iter()
+                .find(|summary| summary.metric == Metric::Op(op))
+                .map(|summary| (step.qps, summary))
+        })
+    }
+}
@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use super::{client::Result, prometheus::PrometheusExporter, report::{StepHistograms, TestReport}, stats::{RequestInfo, TestResult}, summary::StepSummary};
+
+/// Reporter outputs the results of a test in some format. The tester calls
+/// every reporter it has as the test goes, so formats can be added or combined
+/// without touching the tester. All methods do nothing by default, but a step
+/// of a concurrency sweep starts as a step at the number of workers as QPS.
+/// The console, chart and JSON reporters are in `iotest-report`.
+pub trait Reporter: Send + Sync {
+    /// A step at the QPS is starting.
+    fn step_started(&self, _qps: u64) {}
+
+    /// A step of so many closed-loop workers is starting, in a concurrency
+    /// sweep.
+    fn concurrency_step_started(&self, workers: usize) {
+        self.step_started(workers as u64)
+    }
+
+    /// A request finished. It is called by the tasks issuing requests, so it
+    /// should be cheap.
+    fn request(&self, _request: &RequestInfo, _result: &TestResult) {}
+
+    /// A step finished.
+    fn step_finished(&self, _step: &StepSummary, _histograms: &StepHistograms) {}
+
+    /// The test finished. An error fails the test.
+    fn finish(&self, _report: &TestReport) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A shared reporter, e.g. to read what it collected after the test.
+impl<R> Reporter for Arc<R> where R: Reporter + ?Sized {
+    fn step_started(&self, qps: u64) {
+        (**self).step_started(qps)
+    }
+
+    fn concurrency_step_started(&self, workers: usize) {
+        (**self).concurrency_step_started(workers)
+    }
+
+    fn request(&self, request: &RequestInfo, result: &TestResult) {
+        (**self).request(request, result)
+    }
+
+    fn step_finished(&self, step: &StepSummary, histograms: &StepHistograms) {
+        (**self).step_finished(step, histograms)
+    }
+
+    fn finish(&self, report: &TestReport) -> Result<()> {
+        (**self).finish(report)
+    }
+}
+
+impl Reporter for PrometheusExporter {
+    fn step_started(&self, qps: u64) {
+        self.set_target_qps(qps);
+    }
+
+    fn request(&self, _request: &RequestInfo, result: &TestResult) {
+        self.record(result);
+    }
+}
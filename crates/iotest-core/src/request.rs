@@ -0,0 +1,496 @@
+use std::{future::Future, sync::{Arc, Mutex, PoisonError}};
+
+use rand::{rngs::StdRng, SeedableRng};
+use tokio::time::Duration;
+
+use super::{client::{Capability, Error, Op, Result, TestClientHandler}, clock::Clock, integrity::Integrity, options::Options, payload, retry::RetryPolicy, stats::{Metric, TestResult}, tenant::Tenant, versions::VersionTracker};
+
+/// The name of the extended attribute set on keys.
+const XATTR_NAME: &str = "user.iotest";
+
+/// The alignment of the offset of punched holes, a common block size.
+const HOLE_ALIGN: u64 = 4096;
+
+/// The suffix of the key a key is copied to.
+pub const COPY_SUFFIX: &str = ".copy";
+
+/// How often a key is read when probing visibility.
+const VISIBILITY_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// RequestOptions is the part of the options every request needs.
+#[derive(Clone)]
+pub struct RequestOptions {
+    timeout: Option<Duration>,
+    retry: Option<RetryPolicy>,
+    verify: bool,
+    check_size: bool,
+    cold_reads: bool,
+    xattr_len: Option<usize>,
+    hole_len: Option<usize>,
+    copy: bool,
+    versioned: bool,
+    verify_at_end: bool,
+    probe_visibility: Option<Duration>,
+    probe_delete_visibility: Option<Duration>,
+    /// Jitters the backoffs of retries, shared by the tenants.
+    rng: Arc<Mutex<StdRng>>,
+    /// How many times a key is read after its write.
+    reads: u32,
+    /// What the operations are timed, timed out and paced by.
+    clock: Arc<dyn Clock>,
+}
+
+impl RequestOptions {
+    /// The request options of a step, whose randomness is seeded by the
+    /// seed and whose time is taken from the clock.
+    pub fn new(options: &Options, seed: u64, clock: Arc<dyn Clock>) -> Arc<Self> {
+        Arc::new(Self {
+            timeout: options.timeout,
+            retry: options.retry.clone(),
+            verify: options.verify,
+            check_size: options.check_size,
+            cold_reads: options.cold_reads,
+            xattr_len: options.xattr_len,
+            hole_len: options.hole_len,
+            copy: options.copy,
+            versioned: options.versioned,
+            verify_at_end: options.verify_at_end,
+            probe_visibility: options.probe_visibility,
+            probe_delete_visibility: options.probe_delete_visibility,
+            rng: Arc::new(Mutex::new(StdRng::seed_from_u64(seed))),
+            reads: 1,
+            clock,
+        })
+    }
+
+    /// The options of the requests of the tenant.
+    pub fn for_tenant(&self, tenant: &Tenant) -> Arc<Self> {
+        Arc::new(Self { reads: tenant.reads, ..self.clone() })
+    }
+}
+
+/// Handlers are the handler a request writes by, and the one it reads by:
+/// another one of the pool with cross reads, e.g. on another connection, to
+/// tell whether the writes are visible beyond the connection they were made
+/// on.
+pub struct Handlers<'a> {
+    pub writer: &'a dyn TestClientHandler,
+    pub reader: &'a dyn TestClientHandler,
+}
+
+/// Attempt is how an operation went, all its attempts included.
+struct Attempt<T> {
+    result: Result<T>,
+    /// The time taken by all attempts and the backoffs between.
+    elapsed: Duration,
+    /// The latency (or the error) of the first attempt, if retries are
+    /// enabled.
+    first: Option<Result<Duration>>,
+}
+
+impl<T> Attempt<T> {
+    /// Record the first attempt, and the whole operation unless it succeeded
+    /// (which the caller may still check further). A failure is recorded with
+    /// its latency too.
+    fn record(self, op: Op, result: &mut TestResult) -> Option<(T, Duration)> {
+        if let Some(first) = self.first {
+            result.push((Metric::FirstAttempt(op), first));
+        }
+        match self.result {
+            Ok(value) => Some((value, self.elapsed)),
+            Err(err) => {
+                result.push((Metric::Op(op), Err(err)));
+                result.push((Metric::Failed(op), Ok(self.elapsed)));
+                None
+            }
+        }
+    }
+}
+
+/// Run the operation under the retry policy, cancelling every attempt after
+/// the timeout if any.
+async fn attempt<T, F, Fut>(options: &RequestOptions, op: Op, key: &str, mut run: F) -> Attempt<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let start = options.clock.now();
+    let mut first = None;
+    let mut retry = 0;
+    loop {
+        let attempt_start = options.clock.now();
+        let result = with_timeout(options, op, key, run()).await;
+        let Some(policy) = &options.retry else {
+            return Attempt { result, elapsed: options.clock.elapsed(start), first };
+        };
+        if retry == 0 {
+            first = Some(match &result {
+                Ok(_) => Ok(options.clock.elapsed(attempt_start)),
+                Err(err) => Err(err.clone()),
+            });
+        }
+        retry += 1;
+        match result {
+            Err(err) if retry < policy.attempts && policy.retry_on.contains(&err.class()) => {
+                let backoff = policy.backoff_before(retry, &mut *options.rng.lock().unwrap_or_else(PoisonError::into_inner));
+                options.clock.sleep(backoff).await;
+            }
+            result => return Attempt { result, elapsed: options.clock.elapsed(start), first },
+        }
+    }
+}
+
+/// Check that the key stores `len` bytes after a write, if enabled. A
+/// mismatch is a partial write.
+async fn check_size(hdlr: &dyn TestClientHandler, key: &str, len: usize, integrity: &Integrity, options: &RequestOptions) -> Result<()> {
+    if !options.check_size {
+        return Ok(());
+    }
+    let size = with_timeout(options, Op::Write, key, hdlr.size(key)).await?;
+    if size != len as u64 {
+        integrity.partial_write();
+        return Err(Error::new(format!("write {}: partial write: stored {} of {} bytes", key, size, len)));
+    }
+    Ok(())
+}
+
+/// Set, get and remove an extended attribute of `len` bytes on the key,
+/// measuring each, and checking the value got back. Stops at the first
+/// failure.
+async fn xattr(hdlr: &dyn TestClientHandler, key: &str, len: usize, options: &RequestOptions, result: &mut TestResult) {
+    let value = "x".repeat(len);
+    let start = options.clock.now();
+    let set = with_timeout(options, Op::Write, key, hdlr.set_xattr(key, XATTR_NAME, &value)).await;
+    let set_ok = set.is_ok();
+    result.push((Metric::SetXattr, set.map(|()| options.clock.elapsed(start))));
+    if !set_ok {
+        return;
+    }
+
+    let start = options.clock.now();
+    let got = with_timeout(options, Op::Read, key, hdlr.get_xattr(key, XATTR_NAME)).await;
+    let elapsed = options.clock.elapsed(start);
+    let got = match got {
+        Ok(got) if got != value => Err(Error::new(format!("get_xattr {}: got {} bytes, set {}", key, got.len(), len))),
+        got => got.map(|_| elapsed),
+    };
+    let got_ok = got.is_ok();
+    result.push((Metric::GetXattr, got));
+    if !got_ok {
+        return;
+    }
+
+    let start = options.clock.now();
+    let removed = with_timeout(options, Op::Write, key, hdlr.remove_xattr(key, XATTR_NAME)).await;
+    result.push((Metric::RemoveXattr, removed.map(|()| options.clock.elapsed(start))));
+}
+
+/// Punch a hole of up to `len` bytes in the middle of the key holding the
+/// value by the writer, then read it back over the hole by the reader,
+/// measuring both. The hole must read as zeros and the rest as written.
+async fn punch_hole(hdlrs: &Handlers<'_>, key: &str, value: &str, len: usize, options: &RequestOptions, result: &mut TestResult) {
+    let offset = value.len() as u64 / 2 / HOLE_ALIGN * HOLE_ALIGN;
+    let len = (len as u64).min(value.len() as u64 - offset);
+    let start = options.clock.now();
+    let punched = with_timeout(options, Op::Write, key, hdlrs.writer.punch_hole(key, offset, len)).await;
+    let punched_ok = punched.is_ok();
+    result.push((Metric::PunchHole, punched.map(|()| options.clock.elapsed(start))));
+    if !punched_ok {
+        return;
+    }
+
+    let start = options.clock.now();
+    let read = with_timeout(options, Op::Read, key, hdlrs.reader.read(key)).await;
+    let elapsed = options.clock.elapsed(start);
+    let (start, end) = (offset as usize, (offset + len) as usize);
+    let read = read.and_then(|read| {
+        let (read, value) = (read.as_bytes(), value.as_bytes());
+        if read.len() != value.len() {
+            return Err(Error::new(format!("read {}: {} bytes over the hole, {} written", key, read.len(), value.len())));
+        }
+        if read[start..end].iter().any(|&byte| byte != 0) {
+            return Err(Error::new(format!("read {}: data in the hole at {}..{}", key, start, end)));
+        }
+        if read[..start] != value[..start] || read[end..] != value[end..] {
+            return Err(Error::new(format!("read {}: changed out of the hole at {}..{}", key, start, end)));
+        }
+        Ok(elapsed)
+    });
+    result.push((Metric::ReadHole, read));
+}
+
+/// Copy the `seq`-th key to the key with the copy suffix, measuring the copy,
+/// then read the copy back, untimed, to verify it, and delete it.
+async fn copy(hdlr: &dyn TestClientHandler, key: &str, seq: u64, options: &RequestOptions, result: &mut TestResult) {
+    let to = format!("{}{}", key, COPY_SUFFIX);
+    let start = options.clock.now();
+    let copied = with_timeout(options, Op::Write, key, hdlr.copy(key, &to)).await;
+    let elapsed = options.clock.elapsed(start);
+    let copied = match copied {
+        Ok(()) => match with_timeout(options, Op::Read, &to, hdlr.read(&to)).await {
+            Ok(value) => payload::verify(&value, key, seq)
+                .map(|()| elapsed)
+                .map_err(|err| Error::new(format!("copy {}: {}", key, err.msg))),
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err),
+    };
+    result.push((Metric::Copy, copied));
+    if hdlr.capabilities().contains(Capability::Delete) {
+        let _ = hdlr.delete(&to).await;
+    }
+}
+
+/// Overwrite the `seq`-th key, untimed, then read the version written first
+/// back by its id, measuring the read, and verify it is the value of the
+/// request rather than the overwrite.
+async fn read_version(hdlrs: &Handlers<'_>, key: &str, version: &str, seq: u64, options: &RequestOptions, result: &mut TestResult) {
+    let overwrite = format!("{} overwritten by request {}", key, seq);
+    if let Err(err) = with_timeout(options, Op::Write, key, hdlrs.writer.write_versioned(key, &overwrite)).await {
+        result.push((Metric::ReadVersion, Err(err)));
+        return;
+    }
+    let start = options.clock.now();
+    let read = with_timeout(options, Op::Read, key, hdlrs.reader.read_version(key, version)).await;
+    let elapsed = options.clock.elapsed(start);
+    let read = read.and_then(|value| payload::verify(&value, key, seq)
+        .map(|()| elapsed)
+        .map_err(|err| Error::new(format!("read_version {} {}: {}", key, version, err.msg))));
+    result.push((Metric::ReadVersion, read));
+}
+
+/// Run the operation, cancelling it after the timeout if any, by the clock.
+/// The error is given the operation and the key as its context.
+async fn with_timeout<T>(options: &RequestOptions, op: Op, key: &str, fut: impl Future<Output = Result<T>>) -> Result<T> {
+    let result = match options.timeout {
+        Some(timeout) => {
+            let deadline = options.clock.now() + timeout;
+            tokio::select! {
+                // An operation done by the deadline is not timed out.
+                biased;
+                result = fut => result,
+                () = options.clock.sleep_until(deadline) => Err(Error::timeout(format!("{} {}: timed out after {:?}", op.name(), key, timeout))),
+            }
+        }
+        None => fut.await,
+    };
+    result.map_err(|err| err.with_context(op, key))
+}
+
+/// Issue the `seq`-th write-read request on the `idx`-th key of the shared key
+/// pool, which other requests may be writing and reading concurrently. The
+/// read value is checked not to be older than any write completed before the
+/// read started.
+pub async fn request_shared(hdlrs: &Handlers<'_>, key: &str, value: String, seq: u64, (versions, idx): (&VersionTracker, usize), integrity: &Integrity, options: &RequestOptions) -> TestResult {
+    let mut result: TestResult = Vec::with_capacity(2);
+    let (hdlr, reader) = (hdlrs.writer, hdlrs.reader);
+
+    let write = attempt(options, Op::Write, key, || hdlr.write(key, &value)).await;
+    let len = value.len();
+    drop(value);
+    if let Some(((), elapsed)) = write.record(Op::Write, &mut result) {
+        versions.complete(idx, seq);
+        // Concurrent writes of the same key are all of the same length.
+        result.push((Metric::Op(Op::Write), check_size(hdlr, key, len, integrity, options).await.map(|()| elapsed)));
+    }
+
+    let floor = versions.floor(idx);
+    let read = attempt(options, Op::Read, key, || reader.read(key)).await;
+    if let Some((value, elapsed)) = read.record(Op::Read, &mut result) {
+        match payload::inspect(&value, key) {
+            Ok(version) => {
+                // Stale reads are counted by the tracker and reported by
+                // step, not printed from the requests being timed.
+                versions.observe(idx, floor, version);
+                result.push((Metric::Op(Op::Read), Ok(elapsed)));
+            }
+            Err(err) => {
+                integrity.mismatch(err.torn);
+                result.push((Metric::Op(Op::Read), Err(Error::new(format!("read {}: {}", key, err.msg)))));
+            }
+        }
+    }
+    result
+}
+
+/// Issue the `seq`-th write-read-delete request on the key. If `retain`, the
+/// key is not deleted but kept to be scrubbed at the end. In verify-at-end
+/// mode, only the write is issued. Extended attributes of the key are set,
+/// got and removed after the read, it is copied, and unless retained, a hole
+/// is punched in it and it is written as a version to read back after an
+/// overwrite, if enabled. With cold reads, the key is evicted before the
+/// read, untimed. The delete is skipped if the handler can't delete.
+/// The key and its deletion are read by the reader of the handlers, the key
+/// as many times as the reads of the options, once by default.
+pub async fn request(hdlrs: &Handlers<'_>, key: &str, value: String, seq: u64, retain: bool, integrity: &Integrity, options: &RequestOptions) -> TestResult {
+    let mut result: TestResult = Vec::with_capacity(3);
+    let (hdlr, reader) = (hdlrs.writer, hdlrs.reader);
+
+    let versioned = options.versioned && hdlr.capabilities().contains(Capability::Versions);
+    let value_ref = &value;
+    let write = attempt(options, Op::Write, key, || async move {
+        if versioned {
+            hdlr.write_versioned(key, value_ref).await.map(Some)
+        } else {
+            hdlr.write(key, value_ref).await.map(|()| None)
+        }
+    }).await;
+    let write_end = options.clock.now();
+    let len = value.len();
+    drop(value);
+    let Some((version, elapsed)) = write.record(Op::Write, &mut result) else {
+        return result;
+    };
+    if let Err(err) = check_size(hdlr, key, len, integrity, options).await {
+        result.push((Metric::Op(Op::Write), Err(err)));
+        return result;
+    }
+    result.push((Metric::Op(Op::Write), Ok(elapsed)));
+    if options.verify_at_end {
+        return result;
+    }
+
+    if let Some(timeout) = options.probe_visibility {
+        let visibility = loop {
+            let read = with_timeout(options, Op::Read, key, reader.read(key)).await;
+            let visible = read.map_or(false, |value| payload::verify(&value, key, seq).is_ok());
+            let elapsed = options.clock.elapsed(write_end);
+            if visible {
+                break Ok(elapsed);
+            }
+            if elapsed >= timeout {
+                break Err(Error::new(format!("read {}: not visible after {:?}", key, timeout)));
+            }
+            options.clock.sleep(VISIBILITY_POLL_INTERVAL).await;
+        };
+        result.push((Metric::WriteVisibility, visibility));
+    }
+
+    if options.cold_reads && reader.capabilities().contains(Capability::Evict) {
+        if let Err(err) = with_timeout(options, Op::Read, key, reader.evict(key)).await {
+            result.push((Metric::Op(Op::Read), Err(err)));
+            return result;
+        }
+    }
+
+    let mut read_value = None;
+    for _ in 0..options.reads {
+        let read = attempt(options, Op::Read, key, || reader.read(key)).await;
+        if let Some((value, elapsed)) = read.record(Op::Read, &mut result) {
+            match if options.verify { payload::verify(&value, key, seq) } else { Ok(()) } {
+                Ok(()) => {
+                    result.push((Metric::Op(Op::Read), Ok(elapsed)));
+                    read_value = Some(value);
+                }
+                Err(err) => {
+                    integrity.mismatch(err.torn);
+                    result.push((Metric::Op(Op::Read), Err(Error::new(format!("read {}: {}", key, err.msg)))));
+                }
+            }
+        }
+    }
+
+    if let Some(len) = options.xattr_len {
+        if hdlr.capabilities().contains(Capability::Xattr) {
+            xattr(hdlr, key, len, options, &mut result).await;
+        }
+    }
+    if options.copy && hdlr.capabilities().contains(Capability::Copy) {
+        copy(hdlr, key, seq, options, &mut result).await;
+    }
+    // The hole would leave a retained key failing the scrub.
+    if let (Some(len), Some(value), false) = (options.hole_len, read_value, retain) {
+        if hdlr.capabilities().contains(Capability::PunchHole) {
+            punch_hole(hdlrs, key, &value, len, options, &mut result).await;
+        }
+    }
+    // The overwrite would leave a retained key failing the scrub.
+    if let Some(version) = version.filter(|_| !retain) {
+        read_version(hdlrs, key, &version, seq, options, &mut result).await;
+    }
+
+    if retain || !hdlr.capabilities().contains(Capability::Delete) {
+        return result;
+    }
+
+    let delete = attempt(options, Op::Delete, key, || hdlr.delete(key)).await;
+    let delete_end = options.clock.now();
+    let Some(((), elapsed)) = delete.record(Op::Delete, &mut result) else {
+        return result;
+    };
+
+    if let Some(window) = options.probe_delete_visibility {
+        // The key may stay readable for a while, measure how long.
+        result.push((Metric::Op(Op::Delete), Ok(elapsed)));
+        let visibility = loop {
+            let read = with_timeout(options, Op::Read, key, reader.read(key)).await;
+            let elapsed = options.clock.elapsed(delete_end);
+            match read {
+                Err(err) if err.is_not_found() => break Ok(elapsed),
+                // A failed read doesn't tell whether the key is gone.
+                Err(err) => break Err(err),
+                Ok(_) => (),
+            }
+            if elapsed >= window {
+                break Err(Error::new(format!("read {}: still readable {:?} after delete", key, window)));
+            }
+            options.clock.sleep(VISIBILITY_POLL_INTERVAL).await;
+        };
+        result.push((Metric::DeleteVisibility, visibility));
+    } else {
+        let check = match with_timeout(options, Op::Read, key, reader.read(key)).await {
+            Err(err) if err.is_not_found() => Ok(elapsed),
+            Err(err) => Err(err),
+            Ok(_) => Err(Error::new(format!("read {}: should fail after delete", key))),
+        };
+        result.push((Metric::Op(Op::Delete), check));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{client::ErrorClass, clock::TokioClock};
+
+    /// SlowHandler takes so long to write, and reads at once.
+    struct SlowHandler(Duration);
+
+    #[async_trait]
+    impl TestClientHandler for SlowHandler {
+        async fn write(&self, _key: &str, _value: &str) -> Result<()> {
+            tokio::time::sleep(self.0).await;
+            Ok(())
+        }
+
+        async fn read(&self, _key: &str) -> Result<String> {
+            Ok(String::new())
+        }
+    }
+
+    async fn write_by(hdlr: &SlowHandler, timeout: Duration) -> TestResult {
+        let options = RequestOptions::new(&Options { timeout: Some(timeout), verify: false, ..Options::default() }, 0, Arc::new(TokioClock::new()));
+        let hdlrs = Handlers { writer: hdlr, reader: hdlr };
+        request(&hdlrs, "key", String::from("value"), 0, false, &Integrity::new(), &options).await
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn times_a_slow_write_out() {
+        let result = write_by(&SlowHandler(Duration::from_millis(100)), Duration::from_millis(50)).await;
+        let write = result.iter().find(|(metric, _)| *metric == Metric::Op(Op::Write)).unwrap();
+        assert!(matches!(&write.1, Err(err) if err.class() == ErrorClass::Timeout));
+        let failed = result.iter().find(|(metric, _)| *metric == Metric::Failed(Op::Write)).unwrap();
+        assert_eq!(failed.1.as_ref().ok(), Some(&Duration::from_millis(50)));
+        // A failed write is not read back.
+        assert!(!result.iter().any(|(metric, _)| *metric == Metric::Op(Op::Read)));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn measures_a_write_done_in_time() {
+        let result = write_by(&SlowHandler(Duration::from_millis(30)), Duration::from_millis(50)).await;
+        let write = result.iter().find(|(metric, _)| *metric == Metric::Op(Op::Write)).unwrap();
+        assert_eq!(write.1.as_ref().ok(), Some(&Duration::from_millis(30)));
+    }
+}
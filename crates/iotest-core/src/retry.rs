@@ -0,0 +1,47 @@
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::client::ErrorClass;
+
+/// RetryPolicy is how the tester retries failed operations, like a real
+/// client against a throttling backend does.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// The max attempts of an operation, the first one included.
+    pub attempts: u32,
+
+    /// The backoff before the first retry, doubled for every retry after.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+
+    /// Randomly shorten every backoff by up to this ratio.
+    pub jitter: f64,
+
+    /// The classes of errors worth a retry. A missing key won't show up by
+    /// retrying, so not found isn't retried by default.
+    pub retry_on: Vec<ErrorClass>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+            jitter: 0.5,
+            retry_on: ErrorClass::ALL.into_iter().filter(|class| *class != ErrorClass::NotFound).collect(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The backoff before the `retry`-th retry (from 1), jittered by the rng.
+    pub fn backoff_before(&self, retry: u32, rng: &mut impl Rng) -> Duration {
+        let backoff = self.backoff
+            .saturating_mul(1 << (retry - 1).min(16))
+            .min(self.max_backoff);
+        backoff.mul_f64(1.0 - self.jitter * rng.gen::<f64>())
+    }
+}
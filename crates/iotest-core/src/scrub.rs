@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+
+use super::{client::TestClientHandler, payload};
+
+/// At most so many problems are kept in the summary.
+const MAX_PROBLEMS: usize = 100;
+
+/// ScrubSummary is what re-reading the surviving keys at the end of a test
+/// results in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScrubSummary {
+    pub checked: u64,
+    pub ok: u64,
+    pub missing: u64,
+    /// Keys which failed to be read other than by being missing, e.g. by a
+    /// broken connection, so whether they survived is unknown.
+    #[serde(default)]
+    pub unreadable: u64,
+    pub corrupted: u64,
+    /// Keys whose value mixes blocks of different requests.
+    pub torn: u64,
+    pub problems: Vec<String>,
+}
+
+impl ScrubSummary {
+    pub fn is_clean(&self) -> bool {
+        self.missing == 0 && self.unreadable == 0 && self.corrupted == 0 && self.torn == 0
+    }
+}
+
+/// Re-read every surviving key, each with the sequence number of the request
+/// which wrote it, and validate its value.
+pub async fn scrub(hdlr: &dyn TestClientHandler, keys: &[(String, u64)]) -> ScrubSummary {
+    println!("SCRUB:");
+    println!("  KEYS:          {}", keys.len());
+    let summary = read_back(hdlr, keys).await;
+    println!("  OK:            {}", summary.ok);
+    println!("  MISSING:       {}", summary.missing);
+    println!("  UNREADABLE:    {}", summary.unreadable);
+    println!("  CORRUPTED:     {}", summary.corrupted);
+    println!("  TORN:          {}", summary.torn);
+    for problem in &summary.problems {
+        println!("    {}", problem);
+    }
+    summary
+}
+
+/// Re-read every key and validate its value, without printing anything.
+pub async fn read_back(hdlr: &dyn TestClientHandler, keys: &[(String, u64)]) -> ScrubSummary {
+    let mut summary = ScrubSummary {
+        checked: 0,
+        ok: 0,
+        missing: 0,
+        unreadable: 0,
+        corrupted: 0,
+        torn: 0,
+        problems: vec![],
+    };
+    for (key, seq) in keys {
+        summary.checked += 1;
+        let problem = match hdlr.read(key).await {
+            Ok(value) => match payload::verify(&value, key, *seq) {
+                Ok(()) => {
+                    summary.ok += 1;
+                    continue;
+                }
+                Err(err) if err.torn => {
+                    summary.torn += 1;
+                    format!("torn {}: {}", key, err.msg)
+                }
+                Err(err) => {
+                    summary.corrupted += 1;
+                    format!("corrupted {}: {}", key, err.msg)
+                }
+            },
+            Err(err) if err.is_not_found() => {
+                summary.missing += 1;
+                format!("missing {}: {}", key, err)
+            }
+            Err(err) => {
+                summary.unreadable += 1;
+                format!("unreadable {}: {}", key, err)
+            }
+        };
+        if summary.problems.len() < MAX_PROBLEMS {
+            summary.problems.push(problem);
+        }
+    }
+    summary
+}
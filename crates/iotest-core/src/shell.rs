@@ -0,0 +1,20 @@
+use tokio::process::Command;
+
+/// A command running the line by the shell of the platform: `sh -c` on Unix,
+/// `cmd /C` on Windows.
+pub fn shell(line: &str) -> Command {
+    #[cfg(windows)]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    };
+    #[cfg(not(windows))]
+    let mut command = {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(line);
+    command
+}
@@ -0,0 +1,196 @@
+use std::{fmt, result, str::FromStr, time::Duration};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{stats::Metric, summary::MetricSummary};
+
+/// Stat is what of a metric an objective bounds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stat {
+    P50,
+    P99,
+    ErrorRate,
+    TimeoutRate,
+}
+
+impl Stat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stat::P50 => "p50",
+            Stat::P99 => "p99",
+            Stat::ErrorRate => "error_rate",
+            Stat::TimeoutRate => "timeout_rate",
+        }
+    }
+
+    fn is_latency(&self) -> bool {
+        matches!(self, Stat::P50 | Stat::P99)
+    }
+
+    /// The stat of the metric, in µs for latencies and as a ratio for rates.
+    fn of(&self, metric: &MetricSummary) -> f64 {
+        match self {
+            Stat::P50 => metric.p50_us as f64,
+            Stat::P99 => metric.p99_us as f64,
+            Stat::ErrorRate => metric.error_rate,
+            Stat::TimeoutRate => metric.timeout_rate,
+        }
+    }
+
+    fn format(&self, value: f64) -> String {
+        if self.is_latency() {
+            format!("{}µs", value)
+        } else {
+            format!("{}%", value * 100.0)
+        }
+    }
+}
+
+/// Objective is a bound on a stat of a metric every step must keep, e.g.
+/// `write.p99 < 20ms` or `error_rate < 0.1%`. Without a metric, it bounds
+/// every operation. Latencies are in `us`, `ms` or `s`, rates in `%` or as a
+/// ratio.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Objective {
+    pub metric: Option<Metric>,
+    pub stat: Stat,
+    /// `<` or `<=`, else `>` or `>=`.
+    pub below: bool,
+    pub inclusive: bool,
+    /// In µs for latencies and as a ratio for rates.
+    pub threshold: f64,
+}
+
+impl Objective {
+    /// The p99 latency of every operation stays within the limit, as
+    /// `--slo-p99-ms` asks.
+    pub fn p99_within(limit: Duration) -> Self {
+        Self {
+            metric: None,
+            stat: Stat::P99,
+            below: true,
+            inclusive: true,
+            threshold: limit.as_micros() as f64,
+        }
+    }
+
+    /// The metric is bounded by the objective.
+    pub fn applies_to(&self, metric: Metric) -> bool {
+        match self.metric {
+            Some(bounded) => bounded == metric,
+            None => matches!(metric, Metric::Op(_)),
+        }
+    }
+
+    /// The value of the stat of the metric if it breaks the objective.
+    pub fn check(&self, metric: &MetricSummary) -> Option<f64> {
+        let value = self.stat.of(metric);
+        let kept = match (self.below, self.inclusive) {
+            (true, false) => value < self.threshold,
+            (true, true) => value <= self.threshold,
+            (false, false) => value > self.threshold,
+            (false, true) => value >= self.threshold,
+        };
+        (!kept).then_some(value)
+    }
+
+    fn operator(&self) -> &'static str {
+        match (self.below, self.inclusive) {
+            (true, false) => "<",
+            (true, true) => "<=",
+            (false, false) => ">",
+            (false, true) => ">=",
+        }
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(metric) = self.metric {
+            write!(f, "{}.", metric.name())?;
+        }
+        write!(f, "{} {} {}", self.stat.name(), self.operator(), self.stat.format(self.threshold))
+    }
+}
+
+impl FromStr for Objective {
+    type Err = String;
+
+    fn from_str(s: &str) -> result::Result<Self, String> {
+        let (at, operator) = ["<=", ">=", "<", ">"].iter()
+            .find_map(|operator| s.find(operator).map(|at| (at, *operator)))
+            .ok_or_else(|| format!("{}: expect e.g. `write.p99 < 20ms`", s))?;
+        let (lhs, rhs) = (s[..at].trim(), s[at + operator.len()..].trim());
+        let (metric, stat) = match lhs.rsplit_once('.') {
+            Some((metric, stat)) => {
+                let metric = Metric::ALL.iter()
+                    .find(|known| known.name() == metric)
+                    .copied()
+                    .ok_or_else(|| format!("{}: unknown metric: {}", s, metric))?;
+                (Some(metric), stat)
+            }
+            None => (None, lhs),
+        };
+        let stat = [Stat::P50, Stat::P99, Stat::ErrorRate, Stat::TimeoutRate].into_iter()
+            .find(|known| known.name() == stat)
+            .ok_or_else(|| format!("{}: unknown stat {}, not one of p50, p99, error_rate and timeout_rate", s, stat))?;
+        let threshold = if stat.is_latency() {
+            let (number, micros) = [("us", 1.0), ("µs", 1.0), ("ms", 1e3), ("s", 1e6)].iter()
+                .find_map(|(unit, micros)| rhs.strip_suffix(unit).map(|number| (number, *micros)))
+                .ok_or_else(|| format!("{}: the latency needs a unit of us, ms or s", s))?;
+            number.trim().parse::<f64>().map_err(|err| format!("{}: {}", s, err))? * micros
+        } else {
+            match rhs.strip_suffix('%') {
+                Some(number) => number.trim().parse::<f64>().map_err(|err| format!("{}: {}", s, err))? / 100.0,
+                None => rhs.parse::<f64>().map_err(|err| format!("{}: {}", s, err))?,
+            }
+        };
+        Ok(Self {
+            metric,
+            stat,
+            below: operator.starts_with('<'),
+            inclusive: operator.ends_with('='),
+            threshold,
+        })
+    }
+}
+
+impl Serialize for Objective {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for Objective {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Violation is an objective a metric broke in a step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Violation {
+    pub objective: Objective,
+    pub metric: Metric,
+    /// The value of the stat, in µs for latencies and as a ratio for rates.
+    pub value: f64,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} was {}, expected {}", self.metric.name(), self.objective.stat.name(), self.objective.stat.format(self.value), self.objective)
+    }
+}
+
+/// The objectives the metrics of a step break.
+pub fn evaluate(objectives: &[Objective], metrics: &[MetricSummary]) -> Vec<Violation> {
+    let mut violations = vec![];
+    for objective in objectives {
+        for metric in metrics.iter().filter(|metric| objective.applies_to(metric.metric)) {
+            if let Some(value) = objective.check(metric) {
+                violations.push(Violation { objective: objective.clone(), metric: metric.metric, value });
+            }
+        }
+    }
+    violations
+}
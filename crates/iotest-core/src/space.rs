@@ -0,0 +1,115 @@
+use std::{sync::{atomic::{AtomicU64, Ordering}, Arc, Mutex}, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{task::JoinHandle, time::{interval, Instant, MissedTickBehavior}};
+use tokio_util::sync::CancellationToken;
+
+use super::{client::{Op, Result, TestClientHandler}, stats::Metric};
+
+/// SpaceSample is the space the data of a test took on the storage at a
+/// point of the test, next to the bytes the test wrote.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpaceSample {
+    pub at_ms: u64,
+    /// The bytes the storage reported using.
+    pub used: u64,
+    /// The bytes written successfully so far.
+    pub written: u64,
+    /// The bytes written and not deleted since.
+    pub live: u64,
+}
+
+impl SpaceSample {
+    /// How many times the live bytes the storage uses, e.g. by blocks, by
+    /// metadata or by deleted data not reclaimed yet.
+    pub fn amplification(&self) -> Option<f64> {
+        (self.live > 0).then(|| self.used as f64 / self.live as f64)
+    }
+}
+
+/// SpaceCounter counts the bytes the requests wrote and deleted.
+#[derive(Debug, Default)]
+pub struct SpaceCounter {
+    written: AtomicU64,
+    deleted: AtomicU64,
+}
+
+impl SpaceCounter {
+    /// Count the value of the length written or deleted by the request.
+    pub fn record(&self, result: &[(Metric, Result<Duration>)], len: usize) {
+        for (metric, latency) in result {
+            match (metric, latency) {
+                (Metric::Op(Op::Write), Ok(_)) => self.written.fetch_add(len as u64, Ordering::Relaxed),
+                (Metric::Op(Op::Delete), Ok(_)) => self.deleted.fetch_add(len as u64, Ordering::Relaxed),
+                _ => continue,
+            };
+        }
+    }
+
+    fn sample(&self, at: Duration, used: u64) -> SpaceSample {
+        let written = self.written.load(Ordering::Relaxed);
+        SpaceSample {
+            at_ms: at.as_millis() as u64,
+            used,
+            written,
+            live: written.saturating_sub(self.deleted.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// SpaceMonitor asks a handler how much space the data takes at an interval
+/// in the background, to show the space used growing with the bytes written
+/// and shrinking, maybe late, after deletes.
+pub struct SpaceMonitor {
+    counter: Arc<SpaceCounter>,
+    samples: Arc<Mutex<Vec<SpaceSample>>>,
+    stop: CancellationToken,
+    handle: JoinHandle<()>,
+}
+
+impl SpaceMonitor {
+    /// Sample every `every`, relative to `start`, until finished. A backend
+    /// which can't tell the space it uses isn't sampled at all.
+    pub fn start(hdlr: Arc<dyn TestClientHandler>, every: Duration, start: Instant) -> Self {
+        let counter = Arc::new(SpaceCounter::default());
+        let samples = Arc::new(Mutex::new(vec![]));
+        let stop = CancellationToken::new();
+        let handle = {
+            let (counter, samples, stop) = (counter.clone(), samples.clone(), stop.clone());
+            tokio::spawn(async move {
+                let mut ticks = interval(every);
+                ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                loop {
+                    let stopped = tokio::select! {
+                        _ = ticks.tick() => false,
+                        _ = stop.cancelled() => true,
+                    };
+                    match hdlr.space_used().await {
+                        Ok(Some(used)) => samples.lock().unwrap().push(counter.sample(start.elapsed(), used)),
+                        Ok(None) => {
+                            println!("SPACE: not reported by the backend");
+                            return;
+                        }
+                        Err(err) => println!("SPACE: {}", err),
+                    }
+                    if stopped {
+                        return;
+                    }
+                }
+            })
+        };
+        Self { counter, samples, stop, handle }
+    }
+
+    /// The counter the requests are to be recorded by.
+    pub fn counter(&self) -> Arc<SpaceCounter> {
+        self.counter.clone()
+    }
+
+    /// Take a last sample, and get all of them.
+    pub async fn finish(self) -> Vec<SpaceSample> {
+        self.stop.cancel();
+        let _ = self.handle.await;
+        std::mem::take(&mut *self.samples.lock().unwrap())
+    }
+}
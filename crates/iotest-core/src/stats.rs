@@ -0,0 +1,321 @@
+use std::{collections::BTreeMap, f64::consts::SQRT_2, result, time::Duration};
+
+use metrics_util::Histogram;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{client::{ErrorClass, Op, Result}, summary::MetricSummary};
+
+pub const BUCKETS: &[f64] = &[
+    16., 16. * SQRT_2, 32., 32. * SQRT_2,
+    64., 64. * SQRT_2, 128., 128. * SQRT_2,
+    256., 256. * SQRT_2, 512., 512. * SQRT_2,
+    1024., 1024. * SQRT_2, 2048., 2048. * SQRT_2,
+    4096., 4096. * SQRT_2, 8192., 8192. * SQRT_2,
+    16384., 16384. * SQRT_2, 32768., 32768. * SQRT_2,
+    65536., 65536. * SQRT_2, 131072., 131072. * SQRT_2,
+    262144., 262144. * SQRT_2, 524288., 524288. * SQRT_2,
+    1048576.,
+];
+pub const BUCKETS_LEN: usize = BUCKETS.len();
+
+pub fn create_histogram() -> Histogram {
+    Histogram::new(BUCKETS).unwrap()
+}
+
+/// Metric is something measured by requests: the latency of an operation,
+/// or a probe derived from operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Metric {
+    Op(Op),
+
+    /// The delay from a write being acknowledged until the key is readable.
+    WriteVisibility,
+
+    /// How long a key stays readable after a delete is acknowledged.
+    DeleteVisibility,
+
+    /// The latency of the first attempt of an operation, while `Op` is the
+    /// latency of all its attempts. Only measured if retries are enabled.
+    FirstAttempt(Op),
+
+    /// The latency of the operations which failed or timed out, all their
+    /// attempts included, while `Op` counts them as errors. A slow failure
+    /// hurts differently than an instant rejection.
+    Failed(Op),
+
+    /// The latencies of setting, getting and removing an extended attribute
+    /// of a written key. Only measured if enabled.
+    SetXattr,
+    GetXattr,
+    RemoveXattr,
+
+    /// The latency of punching a hole in a read key, and of reading it back
+    /// over the hole. Only measured if enabled.
+    PunchHole,
+    ReadHole,
+
+    /// The latency of copying a read key to another one. Only measured if
+    /// enabled.
+    Copy,
+
+    /// The latency of reading a written version of a key by its id, after
+    /// the key was overwritten, to compare with reading the latest. Only
+    /// measured if enabled.
+    ReadVersion,
+
+    /// The time spent compressing a value before its write, and
+    /// decompressing it after its read, with client-side compression. The
+    /// latencies of the writes and the reads include it.
+    Compress,
+    Decompress,
+
+    /// The phases of connecting to a network backend: resolving its name,
+    /// the TCP connect and the TLS handshake, and the time from sending a
+    /// request to the first byte of its response. Only measured if enabled,
+    /// by backends which can.
+    Dns,
+    Connect,
+    TlsHandshake,
+    FirstByte,
+
+    /// The delay from when a request was due until its task began, e.g.
+    /// behind a generator falling behind or a busy runtime, apart from the
+    /// latencies of its operations.
+    QueueDelay,
+}
+
+impl Metric {
+    pub const ALL: [Metric; 25] = [
+        Metric::Op(Op::Write),
+        Metric::Op(Op::Read),
+        Metric::Op(Op::Delete),
+        Metric::WriteVisibility,
+        Metric::DeleteVisibility,
+        Metric::FirstAttempt(Op::Write),
+        Metric::FirstAttempt(Op::Read),
+        Metric::FirstAttempt(Op::Delete),
+        Metric::Failed(Op::Write),
+        Metric::Failed(Op::Read),
+        Metric::Failed(Op::Delete),
+        Metric::SetXattr,
+        Metric::GetXattr,
+        Metric::RemoveXattr,
+        Metric::PunchHole,
+        Metric::ReadHole,
+        Metric::Copy,
+        Metric::ReadVersion,
+        Metric::Compress,
+        Metric::Decompress,
+        Metric::Dns,
+        Metric::Connect,
+        Metric::TlsHandshake,
+        Metric::FirstByte,
+        Metric::QueueDelay,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Metric::Op(op) => op.name(),
+            Metric::WriteVisibility => "write_visibility",
+            Metric::DeleteVisibility => "delete_visibility",
+            Metric::FirstAttempt(Op::Write) => "write_first_attempt",
+            Metric::FirstAttempt(Op::Read) => "read_first_attempt",
+            Metric::FirstAttempt(Op::Delete) => "delete_first_attempt",
+            Metric::Failed(Op::Write) => "write_failed",
+            Metric::Failed(Op::Read) => "read_failed",
+            Metric::Failed(Op::Delete) => "delete_failed",
+            Metric::SetXattr => "set_xattr",
+            Metric::GetXattr => "get_xattr",
+            Metric::RemoveXattr => "remove_xattr",
+            Metric::PunchHole => "punch_hole",
+            Metric::ReadHole => "read_hole",
+            Metric::Copy => "copy",
+            Metric::ReadVersion => "read_version",
+            Metric::Compress => "compress",
+            Metric::Decompress => "decompress",
+            Metric::Dns => "dns",
+            Metric::Connect => "connect",
+            Metric::TlsHandshake => "tls_handshake",
+            Metric::FirstByte => "first_byte",
+            Metric::QueueDelay => "queue_delay",
+        }
+    }
+
+    fn index(&self) -> usize {
+        Self::ALL.iter().position(|metric| metric == self).unwrap()
+    }
+}
+
+impl Serialize for Metric {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for Metric {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Metric::ALL.iter()
+            .find(|metric| metric.name() == name)
+            .copied()
+            .ok_or_else(|| de::Error::custom(format!("unknown metric: {}", name)))
+    }
+}
+
+/// RequestInfo is what a request was issued on, for reporters logging every
+/// request.
+#[derive(Debug, Clone)]
+pub struct RequestInfo {
+    /// When the request was due, since the Unix epoch.
+    pub timestamp: Duration,
+    /// The QPS of the step.
+    pub qps: u64,
+    pub namespace: usize,
+    /// The request was tagged high priority.
+    pub priority: bool,
+    pub key: String,
+    /// The length of the value written.
+    pub len: usize,
+}
+
+/// TestResult is what a request measured: the latency of every operation it
+/// issued and probe it made, or the error they stopped at.
+pub type TestResult = Vec<(Metric, Result<Duration>)>;
+
+/// StepStats collects the results of all requests in a step (or in a part of
+/// a step).
+pub struct StepStats {
+    metrics: Vec<MetricStats>,
+}
+
+impl StepStats {
+    pub fn new() -> Self {
+        Self {
+            metrics: Metric::ALL.iter().map(|_| MetricStats::new()).collect(),
+        }
+    }
+
+    pub fn record(&mut self, result: &[(Metric, Result<Duration>)]) {
+        for (metric, latency) in result {
+            let stats = &mut self.metrics[metric.index()];
+            match latency {
+                Ok(latency) => stats.record(*latency),
+                Err(err) if err.class() == ErrorClass::Timeout => stats.record_timeout(),
+                Err(err) => stats.record_error(err.class()),
+            }
+        }
+    }
+
+    /// Take the histograms of the metrics in the summaries.
+    pub fn into_histograms(self, summaries: &[MetricSummary]) -> Vec<(Metric, Histogram)> {
+        Metric::ALL.iter()
+            .zip(self.metrics)
+            .filter(|(metric, _)| summaries.iter().any(|summary| summary.metric == **metric))
+            .map(|(metric, stats)| (*metric, stats.histogram))
+            .collect()
+    }
+
+    /// The bytes the histograms take.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.metrics.iter().map(MetricStats::allocated_bytes).sum()
+    }
+
+    /// Summarize every metric of a step lasting `duration`. The operations are
+    /// always there, while the probes are only there if they were made.
+    pub fn summarize(&mut self, duration: Duration) -> Vec<MetricSummary> {
+        Metric::ALL.iter()
+            .zip(self.metrics.iter_mut())
+            .filter(|(metric, stats)| matches!(metric, Metric::Op(_)) || stats.count() > 0)
+            .map(|(metric, stats)| stats.summarize(*metric, duration))
+            .collect()
+    }
+}
+
+/// MetricStats collects the latencies, the errors and the timeouts of a
+/// metric. The latencies go in the buckets of a histogram, so a step takes
+/// as much memory however many requests it issues.
+pub struct MetricStats {
+    histogram: Histogram,
+    /// The longest latency, for the percentiles past the last bucket.
+    max: u64,
+    errors: BTreeMap<ErrorClass, u64>,
+    timeouts: u64,
+}
+
+impl MetricStats {
+    pub fn new() -> Self {
+        Self {
+            histogram: create_histogram(),
+            max: 0,
+            errors: BTreeMap::new(),
+            timeouts: 0,
+        }
+    }
+
+    /// Record a success.
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros() as u64;
+        self.histogram.record(micros as f64);
+        self.max = self.max.max(micros);
+    }
+
+    /// Record a failure of the class.
+    pub fn record_error(&mut self, class: ErrorClass) {
+        *self.errors.entry(class).or_default() += 1;
+    }
+
+    fn errors(&self) -> u64 {
+        self.errors.values().sum()
+    }
+
+    /// Record a timeout.
+    pub fn record_timeout(&mut self) {
+        self.timeouts += 1;
+    }
+
+    pub fn count(&self) -> u64 {
+        self.histogram.count() + self.errors() + self.timeouts
+    }
+
+    /// The bytes the histogram takes.
+    pub fn allocated_bytes(&self) -> u64 {
+        ((BUCKETS_LEN + 1) * std::mem::size_of::<u64>()) as u64
+    }
+
+    /// Summarize the collected latencies, errors and timeouts of a step lasting
+    /// `duration`.
+    pub fn summarize(&mut self, metric: Metric, duration: Duration) -> MetricSummary {
+        let total = self.count();
+        let rate = |n: u64| if total == 0 { 0.0 } else { n as f64 / total as f64 };
+        MetricSummary {
+            metric,
+            count: total,
+            errors: self.errors(),
+            error_rate: rate(self.errors()),
+            errors_by_class: self.errors.clone(),
+            timeouts: self.timeouts,
+            timeout_rate: rate(self.timeouts),
+            p50_us: percentile(&self.histogram, self.max, 0.50),
+            p99_us: percentile(&self.histogram, self.max, 0.99),
+            achieved_qps: self.histogram.count() as f64 / duration.as_secs_f64(),
+        }
+    }
+}
+
+/// Get the q-th percentile from the histogram, interpolated in the bucket it
+/// falls in. Past the last bucket, it is the longest latency.
+fn percentile(histogram: &Histogram, max: u64, q: f64) -> u64 {
+    let rank = ((histogram.count() as f64) * q).ceil().max(1.0) as u64;
+    let (mut lower, mut below) = (0.0, 0);
+    for (bound, count) in histogram.buckets() {
+        if !bound.is_finite() {
+            break;
+        }
+        if count >= rank {
+            let share = (rank - below) as f64 / (count - below).max(1) as f64;
+            return ((lower + (bound - lower) * share) as u64).min(max);
+        }
+        (lower, below) = (bound, count);
+    }
+    max
+}
@@ -0,0 +1,346 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use super::{calibrate::Overhead, client::{Error, ErrorClass, Op, Result}, health::HealthSummary, hooks::HookEvent, metadata::Metadata, options::Options, pool::PoolStats, scrub::ScrubSummary, slo::{self, Violation}, space::SpaceSample, stats::Metric, timeseries::Stall, usage::TesterUsage};
+
+/// Verdict is the overall outcome of a test.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Success,
+    SloViolation,
+    ExcessiveErrors,
+    /// The run was aborted before all steps finished, with nothing else
+    /// wrong.
+    Cancelled,
+}
+
+impl Verdict {
+    /// The process exit code for the verdict. The harness failure uses
+    /// `EXIT_HARNESS_FAILURE` since there is no summary at all.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Verdict::Success => 0,
+            Verdict::SloViolation => 1,
+            Verdict::ExcessiveErrors => 2,
+            Verdict::Cancelled => 4,
+        }
+    }
+}
+
+pub const EXIT_HARNESS_FAILURE: i32 = 3;
+
+/// Summary is what a whole test results in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Summary {
+    pub metadata: Metadata,
+    pub verdict: Verdict,
+    pub steps: Vec<StepSummary>,
+    pub scrub: Option<ScrubSummary>,
+    pub hooks: Vec<HookEvent>,
+    /// The space the data took on the storage through the test.
+    #[serde(default)]
+    pub space: Vec<SpaceSample>,
+    /// The runs of seconds the operations stalled in.
+    #[serde(default)]
+    pub stalls: Vec<Stall>,
+    /// The workers a concurrency sweep saturated the backend at, if it did.
+    #[serde(default)]
+    pub knee: Option<usize>,
+    /// How available the backend was by its health checks, if checked.
+    #[serde(default)]
+    pub health: Option<HealthSummary>,
+}
+
+/// StepSummary is what a step with a fixed QPS, or a fixed number of
+/// closed-loop workers, results in.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StepSummary {
+    /// The QPS of the step, or the QPS achieved by its workers.
+    pub qps: u64,
+    /// The closed-loop workers of the step, in a concurrency sweep.
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// The length of the values written in the step.
+    #[serde(default)]
+    pub payload_len: usize,
+    pub duration_ms: u64,
+    pub missed_sleep: u64,
+    /// The requests the step was to issue on schedule, at the QPS of it or of
+    /// its tenants, out of which sleeps were missed.
+    #[serde(default)]
+    pub scheduled: u64,
+    /// Requests whose task panicked, so nothing of them was recorded.
+    pub panicked: u64,
+    /// Writes acknowledged while storing a different size than the value.
+    pub partial_writes: u64,
+    /// Reads returning a value which fails verification, except torn ones.
+    pub content_mismatches: u64,
+    /// Reads returning a value mixing blocks written by different requests.
+    pub torn_reads: u64,
+    /// Reads returning an older version than one read before, if the keys are
+    /// shared.
+    pub stale_reads: u64,
+    /// The read-back of all keys written in the step, in verify-at-end mode.
+    pub readback: Option<ScrubSummary>,
+    /// How the handlers were reused by requests.
+    #[serde(default)]
+    pub pool: PoolStats,
+    /// The step was cut short since the run was cancelled.
+    #[serde(default)]
+    pub cancelled: bool,
+    /// The overhead of the tester at the QPS, if calibrated.
+    #[serde(default)]
+    pub overhead: Vec<Overhead>,
+    /// The overhead is subtracted from the p50 and p99 of the metrics.
+    #[serde(default)]
+    pub overhead_subtracted: bool,
+    pub metrics: Vec<MetricSummary>,
+    /// The objectives of the options the metrics broke.
+    #[serde(default)]
+    pub violations: Vec<Violation>,
+    /// The metrics of every namespace, if the test ran several; `metrics`
+    /// are of all of them.
+    #[serde(default)]
+    pub namespaces: Vec<NamespaceSummary>,
+    /// The metrics of the high priority requests and of the bulk, if some
+    /// requests were tagged.
+    #[serde(default)]
+    pub priority: Option<PrioritySummary>,
+    /// The metrics of the requests by the size class of their values, if
+    /// the values were of several.
+    #[serde(default)]
+    pub sizes: Vec<SizeSummary>,
+    /// What the tester itself took to run the step.
+    #[serde(default)]
+    pub usage: TesterUsage,
+}
+
+/// NamespaceSummary is the metrics of the requests in a namespace.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NamespaceSummary {
+    pub name: String,
+    pub metrics: Vec<MetricSummary>,
+}
+
+/// PrioritySummary is the metrics of the requests tagged high priority and
+/// of the bulk of them, to tell whether the storage serves them better.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PrioritySummary {
+    /// The ratio of requests tagged.
+    pub ratio: f64,
+    pub high: Vec<MetricSummary>,
+    pub bulk: Vec<MetricSummary>,
+}
+
+/// SizeSummary is the metrics of the requests whose values are longer than
+/// half the length of the class, and as long at most.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SizeSummary {
+    /// The length of the class, a power of two.
+    pub len: usize,
+    pub metrics: Vec<MetricSummary>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MetricSummary {
+    pub metric: Metric,
+    pub count: u64,
+    pub errors: u64,
+    pub error_rate: f64,
+    /// The errors by class, timeouts excluded.
+    #[serde(default)]
+    pub errors_by_class: BTreeMap<ErrorClass, u64>,
+    pub timeouts: u64,
+    pub timeout_rate: f64,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub achieved_qps: f64,
+}
+
+impl StepSummary {
+    /// The successful writes per second of the step, a request per write.
+    pub fn throughput(&self) -> f64 {
+        let secs = (self.duration_ms as f64 / 1000.0).max(f64::EPSILON);
+        self.metrics.iter()
+            .find(|metric| metric.metric == Metric::Op(Op::Write))
+            .map_or(0.0, |metric| metric.count.saturating_sub(metric.errors + metric.timeouts) as f64 / secs)
+    }
+
+    /// The bytes per second the operation moved in the step, by its
+    /// successful operations and the payload length.
+    pub fn bandwidth(&self, metric: &MetricSummary) -> f64 {
+        let ok = metric.count.saturating_sub(metric.errors + metric.timeouts) as f64;
+        let secs = (self.duration_ms as f64 / 1000.0).max(f64::EPSILON);
+        ok * self.payload_len as f64 / secs
+    }
+
+    /// Subtract the overhead of the tester from the p50 and p99 of every
+    /// metric it was calibrated for. The histograms are left as measured.
+    pub fn subtract_overhead(&mut self) {
+        for metric in &mut self.metrics {
+            if let Some(overhead) = self.overhead.iter().find(|overhead| overhead.metric == metric.metric) {
+                metric.p50_us = metric.p50_us.saturating_sub(overhead.p50_us);
+                metric.p99_us = metric.p99_us.saturating_sub(overhead.p99_us);
+            }
+        }
+        self.overhead_subtracted = true;
+    }
+}
+
+impl Summary {
+    pub fn new(metadata: Metadata, mut steps: Vec<StepSummary>, scrub: Option<ScrubSummary>, hooks: Vec<HookEvent>, space: Vec<SpaceSample>, stalls: Vec<Stall>, options: &Options) -> Self {
+        let mut verdict = Verdict::Success;
+        for step in &mut steps {
+            step.violations = slo::evaluate(&options.objectives, &step.metrics);
+            if !step.violations.is_empty() {
+                verdict = Verdict::SloViolation;
+            }
+        }
+        for metric in steps.iter().flat_map(|step| step.metrics.iter()) {
+            if matches!(metric.metric, Metric::FirstAttempt(_) | Metric::Failed(_) | Metric::QueueDelay) {
+                // Failed first attempts are fine as long as a retry succeeds,
+                // failures count as errors of their operation, and the queue
+                // delay is of the tester, not the storage.
+                continue;
+            }
+            if metric.error_rate + metric.timeout_rate > options.max_error_rate {
+                verdict = Verdict::ExcessiveErrors;
+                break;
+            }
+        }
+        if steps.iter().any(|step| step.readback.as_ref().map_or(false, |readback| !readback.is_clean())) {
+            verdict = Verdict::ExcessiveErrors;
+        }
+        if steps.iter().any(|step| step.stale_reads > 0 || step.panicked > 0 || step.partial_writes > 0 || step.content_mismatches > 0 || step.torn_reads > 0) {
+            verdict = Verdict::ExcessiveErrors;
+        }
+        if scrub.as_ref().map_or(false, |scrub| !scrub.is_clean()) {
+            verdict = Verdict::ExcessiveErrors;
+        }
+        let finished = steps.len() == options.steps().len() && !steps.iter().any(|step| step.cancelled);
+        if verdict == Verdict::Success && !finished {
+            verdict = Verdict::Cancelled;
+        }
+        let knee = knee(&steps);
+        Self { metadata, verdict, steps, scrub, hooks, space, stalls, knee, health: None }
+    }
+
+    /// Print one table with a row per step, so the whole test can be read at a
+    /// glance. A metric measured in some steps only, e.g. the failed
+    /// operations, is left blank in the others. The payload length of every
+    /// step is shown in a size sweep.
+    pub fn print_table(&self) {
+        const METRIC_WIDTH: usize = 9 + 1 + 9 + 1 + 7 + 1 + 7 + 1 + 8;
+        let mut metrics: Vec<Metric> = vec![];
+        for summary in self.steps.iter().flat_map(|step| step.metrics.iter()) {
+            if !metrics.contains(&summary.metric) {
+                metrics.push(summary.metric);
+            }
+        }
+        let size_sweep = !self.metadata.options.size_sweep.is_empty();
+
+        println!("SUMMARY:");
+        let mut line = format!("  {:>6}", "");
+        if size_sweep {
+            line += &format!(" {:>10}", "");
+        }
+        for metric in &metrics {
+            line += &format!(" | {:^width$}", metric.name().to_uppercase(), width = METRIC_WIDTH);
+        }
+        println!("{}", line);
+        let mut line = format!("  {:>6}", "QPS");
+        if size_sweep {
+            line += &format!(" {:>10}", "LEN");
+        }
+        for _ in &metrics {
+            line += &format!(" | {:>9} {:>9} {:>7} {:>7} {:>8}", "P50", "P99", "ERR%", "TMO%", "QPS");
+        }
+        println!("{}", line);
+        println!("  {}", "-".repeat(line.len() - 2));
+        for step in &self.steps {
+            let mut line = format!("  {:>6}", step.qps);
+            if size_sweep {
+                line += &format!(" {:>10}", step.payload_len);
+            }
+            for metric in &metrics {
+                line += &match step.metrics.iter().find(|summary| summary.metric == *metric) {
+                    Some(summary) => format!(" | {:>9} {:>9} {:>7.2} {:>7.2} {:>8.1}",
+                        format_micros(summary.p50_us),
+                        format_micros(summary.p99_us),
+                        summary.error_rate * 100.0,
+                        summary.timeout_rate * 100.0,
+                        summary.achieved_qps,
+                    ),
+                    None => format!(" | {:width$}", "", width = METRIC_WIDTH),
+                };
+            }
+            println!("{}", line);
+        }
+        for step in &self.steps {
+            for violation in &step.violations {
+                println!("  VIOLATED:      {} at {} QPS", violation, step.qps);
+            }
+        }
+        if let Some(step) = self.knee.and_then(|knee| self.steps.iter().find(|step| step.concurrency == Some(knee))) {
+            let p99 = step.metrics.iter().find(|metric| metric.metric == Metric::Op(Op::Write)).map_or(0, |metric| metric.p99_us);
+            println!("  KNEE:          {} workers, {:.1} writes/s, write p99 {}", step.concurrency.unwrap_or_default(), step.throughput(), format_micros(p99));
+        }
+        for stall in &self.stalls {
+            println!("  STALL:         {} p99 {} for {}s at {}s, median {}", stall.metric.name(), format_micros(stall.max_p99_us), stall.seconds, stall.second, format_micros(stall.median_us));
+        }
+        if let Some(last) = self.space.last() {
+            let peak = self.space.iter().map(|sample| sample.used).max().unwrap_or(0);
+            println!("  SPACE USED:    {:.1}MB, peak {:.1}MB, of {:.1}MB live, {:.1}MB written", last.used as f64 / 1e6, peak as f64 / 1e6, last.live as f64 / 1e6, last.written as f64 / 1e6);
+        }
+        if let Some(health) = &self.health {
+            println!("  AVAILABILITY:  {:.2}% of {} health checks, p50 {}, p99 {}", health.availability * 100.0, health.checks.len(), format_micros(health.p50_us), format_micros(health.p99_us));
+            for outage in &health.outages {
+                println!("  OUTAGE:        {:.1}s to {:.1}s, {} checks failed", outage.from_ms as f64 / 1000.0, outage.to_ms as f64 / 1000.0, outage.checks);
+            }
+        }
+    }
+
+    /// Read a summary written by `write_to`.
+    pub fn read_from(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .map_err(|err| Error::from_io_error(&format!("read {}", path.display()), err))?;
+        serde_json::from_str(&json)
+            .map_err(|err| Error::new(format!("parse {}: {}", path.display(), err)))
+    }
+
+    /// Write the summary as JSON to the path.
+    pub fn write_to(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|err| Error::new(format!("serialize summary: {}", err)))?;
+        fs::write(path, json)
+            .map_err(|err| Error::from_io_error(&format!("write {}", path.display()), err))
+    }
+}
+
+/// The workers of the step a concurrency sweep saturated at: the last one
+/// whose next step gained less than half the throughput its added workers
+/// would have linearly.
+fn knee(steps: &[StepSummary]) -> Option<usize> {
+    let points: Vec<(usize, f64)> = steps.iter()
+        .filter_map(|step| step.concurrency.map(|workers| (workers, step.throughput())))
+        .collect();
+    points.windows(2)
+        .find(|pair| {
+            let ((workers, throughput), (next_workers, next_throughput)) = (pair[0], pair[1]);
+            let linear = throughput * (next_workers as f64 / workers.max(1) as f64 - 1.0);
+            next_workers > workers && next_throughput - throughput < linear / 2.0
+        })
+        .map(|pair| pair[0].0)
+}
+
+fn format_micros(us: u64) -> String {
+    if us < 1000 {
+        format!("{}µs", us)
+    } else if us < 1_000_000 {
+        format!("{:.2}ms", us as f64 / 1000.0)
+    } else {
+        format!("{:.2}s", us as f64 / 1_000_000.0)
+    }
+}
@@ -0,0 +1,261 @@
+use std::{panic::{self, AssertUnwindSafe}, sync::{mpsc, Arc, Mutex, PoisonError}, thread};
+
+use async_trait::async_trait;
+use tokio::{sync::oneshot, task};
+
+use super::client::{unsupported, Capabilities, Capability, Error, Result, TestClientHandler};
+
+/// SyncClientHandler is a blocking `TestClientHandler`, e.g. on `std::fs` or
+/// the bindings of a C library. Wrap it in a `SyncClientAdapter` to test it.
+pub trait SyncClientHandler: Send + Sync + 'static {
+    /// What the handler supports besides writing and reading.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
+    /// Set the handler up before its first request.
+    fn setup(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Release what the setup took, after the last request of the handler.
+    fn teardown(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Write a object.
+    fn write(&self, key: &str, value: &str) -> Result<()>;
+
+    /// Read a object. A missing key must fail with `Error::not_found`.
+    fn read(&self, key: &str) -> Result<String>;
+
+    /// Delete a object, with `Capability::Delete`.
+    fn delete(&self, key: &str) -> Result<()> {
+        Err(unsupported(Capability::Delete, key))
+    }
+
+    /// Get the stored size of a object, with `Capability::Stat`.
+    fn size(&self, key: &str) -> Result<u64> {
+        self.read(key).map(|value| value.len() as u64)
+    }
+
+    /// List the keys starting with the prefix, with `Capability::List`.
+    fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        Err(unsupported(Capability::List, prefix))
+    }
+
+    /// Append the value to a object, with `Capability::Append`.
+    fn append(&self, key: &str, _value: &str) -> Result<()> {
+        Err(unsupported(Capability::Append, key))
+    }
+
+    /// Drop the object from the caches, with `Capability::Evict`.
+    fn evict(&self, key: &str) -> Result<()> {
+        Err(unsupported(Capability::Evict, key))
+    }
+
+    /// Set the extended attribute of a object, with `Capability::Xattr`.
+    fn set_xattr(&self, key: &str, _name: &str, _value: &str) -> Result<()> {
+        Err(unsupported(Capability::Xattr, key))
+    }
+
+    /// Get the extended attribute of a object, with `Capability::Xattr`.
+    fn get_xattr(&self, key: &str, _name: &str) -> Result<String> {
+        Err(unsupported(Capability::Xattr, key))
+    }
+
+    /// Remove the extended attribute of a object, with `Capability::Xattr`.
+    fn remove_xattr(&self, key: &str, _name: &str) -> Result<()> {
+        Err(unsupported(Capability::Xattr, key))
+    }
+
+    /// Punch a hole in a object, with `Capability::PunchHole`.
+    fn punch_hole(&self, key: &str, _offset: u64, _len: u64) -> Result<()> {
+        Err(unsupported(Capability::PunchHole, key))
+    }
+
+    /// Copy a object to the other key, with `Capability::Copy`.
+    fn copy(&self, key: &str, _to: &str) -> Result<()> {
+        Err(unsupported(Capability::Copy, key))
+    }
+
+    /// Write a object as a new version, returning the id of the version,
+    /// with `Capability::Versions`.
+    fn write_versioned(&self, key: &str, _value: &str) -> Result<String> {
+        Err(unsupported(Capability::Versions, key))
+    }
+
+    /// Read the version of a object by its id, with `Capability::Versions`.
+    fn read_version(&self, key: &str, _version: &str) -> Result<String> {
+        Err(unsupported(Capability::Versions, key))
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// SyncPool is a pool of threads dedicated to blocking handlers, so they
+/// don't share the blocking pool of the runtime with e.g. file IO. It can be
+/// shared by the adapters of many handlers, and stops once all are dropped.
+#[derive(Clone)]
+pub struct SyncPool {
+    jobs: Arc<Mutex<mpsc::Sender<Job>>>,
+}
+
+impl SyncPool {
+    pub fn new(threads: usize) -> Self {
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let queue = Arc::new(Mutex::new(queue));
+        for i in 0..threads.max(1) {
+            let queue = queue.clone();
+            thread::Builder::new()
+                .name(format!("iotest-sync-{}", i))
+                .spawn(move || loop {
+                    let job = queue.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                })
+                .expect("spawn sync pool thread");
+        }
+        Self { jobs: Arc::new(Mutex::new(jobs)) }
+    }
+
+    fn spawn(&self, job: Job) -> bool {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner).send(job).is_ok()
+    }
+}
+
+/// SyncClientAdapter runs a blocking handler as a `TestClientHandler`: every
+/// operation runs on the blocking pool of the runtime, or on a `SyncPool`.
+///
+/// A blocking operation can't be cancelled, so one timed out by the tester
+/// still holds its thread until it returns.
+pub struct SyncClientAdapter<H> {
+    handler: Arc<H>,
+    pool: Option<SyncPool>,
+}
+
+impl<H: SyncClientHandler> SyncClientAdapter<H> {
+    /// Run the handler on `spawn_blocking`.
+    pub fn new(handler: H) -> Self {
+        Self { handler: Arc::new(handler), pool: None }
+    }
+
+    /// Run the handler on the pool instead.
+    pub fn with_pool(mut self, pool: SyncPool) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Run the operation on a thread, failing if it panics.
+    async fn run<T, F>(&self, op: &str, key: &str, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&H) -> Result<T> + Send + 'static,
+    {
+        let handler = self.handler.clone();
+        let panicked = || Error::new(format!("{} {}: handler panicked", op, key));
+        match &self.pool {
+            None => task::spawn_blocking(move || f(&handler)).await.map_err(|_| panicked())?,
+            Some(pool) => {
+                let (tx, rx) = oneshot::channel();
+                let job = Box::new(move || {
+                    // Keep the thread of the pool on a panic.
+                    if let Ok(result) = panic::catch_unwind(AssertUnwindSafe(|| f(&handler))) {
+                        let _ = tx.send(result);
+                    }
+                });
+                if !pool.spawn(job) {
+                    return Err(Error::new(format!("{} {}: sync pool stopped", op, key)));
+                }
+                rx.await.map_err(|_| panicked())?
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<H: SyncClientHandler> TestClientHandler for SyncClientAdapter<H> {
+    fn capabilities(&self) -> Capabilities {
+        self.handler.capabilities()
+    }
+
+    async fn setup(&self) -> Result<()> {
+        self.run("setup", "", |handler| handler.setup()).await
+    }
+
+    async fn teardown(&self) -> Result<()> {
+        self.run("teardown", "", |handler| handler.teardown()).await
+    }
+
+    async fn write(&self, key: &str, value: &str) -> Result<()> {
+        let (owned_key, value) = (key.to_string(), value.to_string());
+        self.run("write", key, move |handler| handler.write(&owned_key, &value)).await
+    }
+
+    async fn read(&self, key: &str) -> Result<String> {
+        let owned_key = key.to_string();
+        self.run("read", key, move |handler| handler.read(&owned_key)).await
+    }
+
+    async fn delete(&self, key: &str) -> Result<()> {
+        let owned_key = key.to_string();
+        self.run("delete", key, move |handler| handler.delete(&owned_key)).await
+    }
+
+    async fn size(&self, key: &str) -> Result<u64> {
+        let owned_key = key.to_string();
+        self.run("stat", key, move |handler| handler.size(&owned_key)).await
+    }
+
+    async fn list(&self, prefix: &str) -> Result<Vec<String>> {
+        let owned_prefix = prefix.to_string();
+        self.run("list", prefix, move |handler| handler.list(&owned_prefix)).await
+    }
+
+    async fn append(&self, key: &str, value: &str) -> Result<()> {
+        let (owned_key, value) = (key.to_string(), value.to_string());
+        self.run("append", key, move |handler| handler.append(&owned_key, &value)).await
+    }
+
+    async fn evict(&self, key: &str) -> Result<()> {
+        let owned_key = key.to_string();
+        self.run("evict", key, move |handler| handler.evict(&owned_key)).await
+    }
+
+    async fn set_xattr(&self, key: &str, name: &str, value: &str) -> Result<()> {
+        let (owned_key, name, value) = (key.to_string(), name.to_string(), value.to_string());
+        self.run("set_xattr", key, move |handler| handler.set_xattr(&owned_key, &name, &value)).await
+    }
+
+    async fn get_xattr(&self, key: &str, name: &str) -> Result<String> {
+        let (owned_key, name) = (key.to_string(), name.to_string());
+        self.run("get_xattr", key, move |handler| handler.get_xattr(&owned_key, &name)).await
+    }
+
+    async fn remove_xattr(&self, key: &str, name: &str) -> Result<()> {
+        let (owned_key, name) = (key.to_string(), name.to_string());
+        self.run("remove_xattr", key, move |handler| handler.remove_xattr(&owned_key, &name)).await
+    }
+
+    async fn punch_hole(&self, key: &str, offset: u64, len: u64) -> Result<()> {
+        let owned_key = key.to_string();
+        self.run("punch_hole", key, move |handler| handler.punch_hole(&owned_key, offset, len)).await
+    }
+
+    async fn copy(&self, key: &str, to: &str) -> Result<()> {
+        let (owned_key, to) = (key.to_string(), to.to_string());
+        self.run("copy", key, move |handler| handler.copy(&owned_key, &to)).await
+    }
+
+    async fn write_versioned(&self, key: &str, value: &str) -> Result<String> {
+        let (owned_key, value) = (key.to_string(), value.to_string());
+        self.run("write_versioned", key, move |handler| handler.write_versioned(&owned_key, &value)).await
+    }
+
+    async fn read_version(&self, key: &str, version: &str) -> Result<String> {
+        let (owned_key, version) = (key.to_string(), version.to_string());
+        self.run("read_version", key, move |handler| handler.read_version(&owned_key, &version)).await
+    }
+}
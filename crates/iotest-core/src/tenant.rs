@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Tenant is an application sharing the storage with the others, e.g. a
+/// noisy neighbor: its requests go to a key namespace of its own, at a QPS,
+/// of a payload length and with reads of its own, and are summarized apart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tenant {
+    pub name: String,
+    /// The QPS of the tenant, or the QPS of the step if none.
+    pub qps: Option<u64>,
+    /// The length of the values of the tenant, or the payload length of the
+    /// step if none.
+    pub payload_len: Option<usize>,
+    /// How many times every key is read after its write: 0 for a tenant
+    /// only writing, more for one mostly reading.
+    pub reads: u32,
+}
+
+impl Tenant {
+    /// A tenant at the QPS and payload length of the step, reading keys once.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into(), qps: None, payload_len: None, reads: 1 }
+    }
+}
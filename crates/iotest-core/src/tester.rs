@@ -0,0 +1,969 @@
+use std::{collections::BTreeMap, fs, sync::Arc};
+
+use chrono::Utc;
+use indicatif::{ProgressBar, ProgressStyle};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::{sync::{mpsc, Semaphore}, time::{Duration, Instant}};
+use tokio_util::sync::CancellationToken;
+
+use super::{builder::TesterBuilder, cache::drop_page_cache, checkpoint::Checkpoint, calibrate::{NoopClient, Overhead}, client::{Capability, Error, Result, TestClient}, clock::{Clock, TokioClock}, grafana, health::{HealthMonitor, HealthSummary}, hooks::Hooks, integrity::Integrity, live::LiveStream, metadata::Metadata, options::Options, payload::Payload, pool::HandlerPool, prometheus::PrometheusExporter, report::{StepHistograms, TestReport}, reporter::Reporter, request::{request, request_shared, Handlers, RequestOptions}, scrub::{read_back, scrub, ScrubSummary}, space::{SpaceCounter, SpaceMonitor}, stats::{Metric, RequestInfo, StepStats, TestResult}, summary::{NamespaceSummary, PrioritySummary, SizeSummary, StepSummary, Summary}, tenant::Tenant, timeseries::TimeSeries, usage::{InFlight, TesterUsage}, versions::VersionTracker};
+#[cfg(feature = "profile")]
+use super::profile::StepProfiler;
+
+/// Tester is used to test storage. It uses the client to read / write / delete
+/// something from storage.
+pub struct Tester<C> where C: TestClient {
+    client: C,
+    payload: Arc<Payload>,
+    /// The length of the values the payload makes.
+    payload_len: usize,
+    /// Makes every random choice of the test, seeded by the options.
+    rng: StdRng,
+    seq: u64,
+    /// How many keys the client generated.
+    keys: u64,
+    /// The keys kept until the end to be scrubbed, with their sequence numbers.
+    retained: Vec<(String, u64)>,
+    /// The keys written in the current step but not read yet, in
+    /// verify-at-end mode.
+    unverified: Vec<(String, u64)>,
+    /// The shared key pool, if requests target the same keys concurrently.
+    versions: Option<Arc<VersionTracker>>,
+    integrity: Arc<Integrity>,
+    /// When the test started, requests are timed since then.
+    run_start: Instant,
+    /// Paces and timestamps the requests.
+    clock: Arc<dyn Clock>,
+    timeseries: TimeSeries,
+    /// Whether the step being run is the warm-up, kept out of the time
+    /// series and the exported metrics.
+    warming_up: bool,
+    /// Counts the bytes written and deleted, if the space is sampled.
+    space: Option<Arc<SpaceCounter>>,
+    options: Options,
+    live: Option<LiveStream>,
+    /// Where the results go, as the test goes.
+    reporters: Vec<Arc<dyn Reporter>>,
+    /// Cancelled to abort the run: no more requests are issued, and the ones
+    /// in flight are drained.
+    cancel: CancellationToken,
+}
+
+impl<C> Tester<C> where C: TestClient {
+    pub fn new(client: C, len: usize) -> Self {
+        Self::with_options(client, Options { payload_len: len, ..Options::default() })
+    }
+
+    pub fn builder(client: C) -> TesterBuilder<C> {
+        TesterBuilder::new(client)
+    }
+
+    pub fn with_options(client: C, mut options: Options) -> Self {
+        // Record the seed picked, so the run can be reproduced.
+        let seed = *options.seed.get_or_insert_with(rand::random);
+        let mut rng = StdRng::seed_from_u64(seed);
+        Self {
+            client,
+            payload: Arc::new(Payload::with_rng(options.payload_len, &mut rng)),
+            payload_len: options.payload_len,
+            rng,
+            seq: 0,
+            keys: 0,
+            retained: vec![],
+            unverified: vec![],
+            integrity: Arc::new(Integrity::new()),
+            versions: None,
+            run_start: Instant::now(),
+            clock: Arc::new(TokioClock::new()),
+            timeseries: TimeSeries::new(),
+            warming_up: false,
+            space: None,
+            options,
+            live: None,
+            reporters: vec![],
+            cancel: CancellationToken::new(),
+        }
+    }
+
+    /// Abort the run by the token, e.g. on an user interrupt.
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Take the time from the clock, e.g. a simulated one in tests.
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Report the results to the reporter too.
+    pub fn with_reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.reporters.push(Arc::new(reporter));
+        self
+    }
+
+    /// The token cancelling the run.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    /// Make the values of the length from now on.
+    fn use_payload_len(&mut self, len: usize) {
+        if len != self.payload_len {
+            self.payload = Arc::new(Payload::with_rng(len, &mut self.rng));
+            self.payload_len = len;
+        }
+    }
+
+    /// Make a pool of handlers, set up.
+    async fn pool(&self, size: usize) -> Result<HandlerPool> {
+        HandlerPool::new(&self.client, size, self.options.timeout, self.options.connection_phases, false, &self.cancel).await
+    }
+
+    /// Make the handlers of the high priority requests.
+    async fn priority_pool(&self, size: usize) -> Result<HandlerPool> {
+        HandlerPool::new(&self.client, size, self.options.timeout, self.options.connection_phases, true, &self.cancel).await
+    }
+
+    /// Run the test, then clean the data it wrote up unless asked to keep it,
+    /// even if the test failed or was cancelled.
+    pub async fn test(&mut self) -> Result<TestReport> {
+        let tested = self.run().await;
+        println!("CLEANUP:");
+        let resumable = self.options.checkpoint.is_some() && (tested.is_err() || self.cancel.is_cancelled());
+        if self.options.keep_data {
+            println!("  DATA:          kept");
+        } else if resumable {
+            println!("  DATA:          kept to resume");
+        } else {
+            match self.client.cleanup() {
+                Ok(()) => println!("  DATA:          removed"),
+                Err(err) => println!("  FAILED:        {}", err),
+            }
+        }
+        tested
+    }
+
+    async fn run(&mut self) -> Result<TestReport> {
+        let mut checkpoint = Checkpoint::new(self.options.seed.unwrap_or_default(), Utc::now());
+        if let Some(path) = self.options.checkpoint.clone() {
+            if let Some(mut resumed) = Checkpoint::read(&path)? {
+                self.resume(&mut resumed)?;
+                checkpoint = resumed;
+            }
+        }
+        let started_at = checkpoint.started_at;
+        if let Some(seed) = self.options.seed {
+            println!("SEED: {}", seed);
+        }
+        self.run_start = self.clock.now();
+        let hooks = Hooks::schedule(&self.options.hooks, self.run_start, self.clock.clone());
+        if let Some(addr) = self.options.live {
+            self.live = Some(LiveStream::bind(addr).await?);
+        }
+        if let Some(addr) = self.options.prometheus {
+            self.reporters.push(PrometheusExporter::bind(addr).await?);
+            grafana::write_dashboard(&self.options.grafana_dashboard)?;
+        }
+
+        // Init the client, and skip the keys of the run resumed.
+        self.client.init();
+        for _ in 0..checkpoint.keys {
+            self.gen_unique_key();
+        }
+
+        // Try write-read-delete ops.
+        self.test_try().await?;
+
+        if let Some(shared_keys) = self.options.shared_keys {
+            let keys = match checkpoint.shared_keys.take() {
+                Some(keys) => keys,
+                None => (0..shared_keys).map(|_| self.gen_unique_key()).collect(),
+            };
+            self.versions = Some(Arc::new(VersionTracker::new(keys)));
+        }
+
+        // Sample the space with a handler of its own, through the steps and
+        // the cleanup after them.
+        let mut space_monitor = None;
+        if let Some(every) = self.options.space_interval {
+            let pool = self.pool(1).await?;
+            let monitor = SpaceMonitor::start(pool.get(), every, self.run_start);
+            self.space = Some(monitor.counter());
+            space_monitor = Some((pool, monitor));
+        }
+        // Check the health with a handler of its own, too.
+        let mut health_monitor = None;
+        if let Some(every) = self.options.health_interval {
+            let pool = self.pool(1).await?;
+            let monitor = HealthMonitor::start(pool.get(), every, self.run_start);
+            health_monitor = Some((pool, monitor));
+        }
+
+        #[cfg(not(feature = "profile"))]
+        if self.options.profile_dir.is_some() {
+            return Err(Error::new("profile: iotest-core is built without the profile feature"));
+        }
+
+        // The overhead is calibrated at QPS, not for closed-loop workers.
+        let overheads = match self.options.calibrate.filter(|_| self.options.concurrency_sweep.is_empty()) {
+            Some(duration) => self.calibrate(duration).await?,
+            None => vec![],
+        };
+
+        // Warm up at the first step, and throw the results away.
+        if let (Some(warm_up), Some(&(qps, payload_len))) = (self.options.warm_up, self.options.steps().first()) {
+            println!("WARM UP:");
+            self.use_payload_len(payload_len);
+            let step_duration = std::mem::replace(&mut self.options.step_duration, warm_up);
+            self.warming_up = true;
+            let warmed_up = self.test_step(qps).await;
+            self.warming_up = false;
+            self.options.step_duration = step_duration;
+            warmed_up?;
+        }
+
+        // Test, after the steps of the run resumed.
+        let mut histograms = vec![];
+        for (qps, payload_len) in self.options.steps().into_iter().skip(checkpoint.steps.len()) {
+            if self.cancel.is_cancelled() {
+                println!("CANCELLED: skip QPS {}", qps);
+                continue;
+            }
+            self.use_payload_len(payload_len);
+            if self.options.drop_caches {
+                drop_page_cache(self.options.drop_caches_command.as_deref()).await?;
+                println!("DROPPED CACHES");
+            }
+            #[cfg(feature = "profile")]
+            let profiler = match &self.options.profile_dir {
+                Some(_) => Some(StepProfiler::start()?),
+                None => None,
+            };
+            let (mut step, step_histograms) = self.test_step(qps).await?;
+            #[cfg(feature = "profile")]
+            if let (Some(profiler), Some(dir)) = (profiler, &self.options.profile_dir) {
+                let path = dir.join(format!("profile-{}.svg", self.step_name(&step)));
+                match profiler.finish(&path) {
+                    Ok(()) => println!("PROFILE: {}", path.display()),
+                    Err(err) => println!("PROFILE: {}", err),
+                }
+            }
+            if let Some((_, overhead)) = overheads.iter().find(|(calibrated, _)| *calibrated == (qps, payload_len)) {
+                step.overhead = overhead.clone();
+                if self.options.subtract_overhead {
+                    step.subtract_overhead();
+                }
+            }
+            for reporter in &self.reporters {
+                reporter.step_finished(&step, &step_histograms);
+            }
+            let finished = !step.cancelled;
+            checkpoint.steps.push(step);
+            histograms.push(step_histograms);
+            if let (Some(path), true) = (&self.options.checkpoint, finished) {
+                checkpoint.seq = self.seq;
+                checkpoint.keys = self.keys;
+                checkpoint.retained = self.retained.clone();
+                checkpoint.shared_keys = self.versions.as_ref().map(|versions| versions.keys().to_vec());
+                checkpoint.write_to(path)?;
+                println!("CHECKPOINT: {}", path.display());
+            }
+        }
+        // Cancelled, a checkpointed run keeps its keys to be resumed.
+        let resumable = self.options.checkpoint.is_some() && self.cancel.is_cancelled();
+
+        // Scrub the surviving keys, and clean them up.
+        let mut scrub_summary = None;
+        if self.options.scrub_ratio.is_some() && !resumable {
+            let pool = self.pool(1).await?;
+            let hdlr = pool.get();
+            scrub_summary = Some(scrub(&*hdlr, &self.retained).await);
+            for (key, _) in self.retained.drain(..) {
+                let _ = hdlr.delete(&key).await;
+            }
+            pool.teardown().await?;
+        }
+
+        // Clean the shared key pool up.
+        if let Some(versions) = self.versions.take().filter(|_| !resumable) {
+            let pool = self.pool(1).await?;
+            let hdlr = pool.get();
+            for key in versions.keys() {
+                let _ = hdlr.delete(key).await;
+            }
+            pool.teardown().await?;
+        }
+
+        let mut space = vec![];
+        if let Some((pool, monitor)) = space_monitor {
+            space = monitor.finish().await;
+            self.space = None;
+            pool.teardown().await?;
+        }
+        let mut health = None;
+        if let Some((pool, monitor)) = health_monitor {
+            health = HealthSummary::new(monitor.finish().await);
+            pool.teardown().await?;
+        }
+
+        let events = hooks.finish();
+        let backend = self.client.name();
+        let metadata = Metadata::collect(backend, self.client.describe(), &self.options, started_at);
+        let stalls = match self.options.stall_factor {
+            Some(factor) if factor > 0.0 => self.timeseries.stalls(factor, self.options.stall_secs.max(1)),
+            _ => vec![],
+        };
+        let mut summary = Summary::new(metadata, checkpoint.steps, scrub_summary, events, space, stalls, &self.options);
+        summary.health = health;
+        let report = TestReport {
+            summary,
+            histograms,
+            timeseries: std::mem::replace(&mut self.timeseries, TimeSeries::new()),
+        };
+        for reporter in &self.reporters {
+            reporter.finish(&report)?;
+        }
+        // Finished, there is nothing to resume anymore.
+        if let (Some(path), false) = (&self.options.checkpoint, resumable) {
+            let _ = fs::remove_file(path);
+        }
+        Ok(report)
+    }
+
+    /// Go on from the checkpoint, with its seed, its sequence numbers and its
+    /// retained keys, after its steps, which must be the first ones of the
+    /// options.
+    fn resume(&mut self, checkpoint: &mut Checkpoint) -> Result<()> {
+        let steps = self.options.steps();
+        let finished: Vec<_> = checkpoint.steps.iter()
+            .map(|step| (step.concurrency.map_or(step.qps, |workers| workers as u64), step.payload_len))
+            .collect();
+        if !steps.starts_with(&finished) {
+            return Err(Error::new("resume: the checkpoint is of other steps than the options"));
+        }
+        println!("RESUME: after {} of {} steps", finished.len(), steps.len());
+        self.options.seed = Some(checkpoint.seed);
+        // The random choices go on from the seed, if not as they would have.
+        self.rng = StdRng::seed_from_u64(checkpoint.seed.wrapping_add(finished.len() as u64));
+        self.payload = Arc::new(Payload::with_rng(self.payload_len, &mut self.rng));
+        self.seq = checkpoint.seq;
+        self.retained = std::mem::take(&mut checkpoint.retained);
+        Ok(())
+    }
+
+    pub async fn test_try(&mut self) -> Result<()> {
+        println!("TRY WRITE-READ-DELETE OPS");
+        let key = self.gen_unique_key();
+        let pool = self.pool(1).await?;
+        let hdlr = pool.get();
+        let capabilities = hdlr.capabilities();
+        let mut names = vec!["write", "read"];
+        names.extend(capabilities.iter().map(|capability| capability.name()));
+        println!("  SUPPORTS:      {}", names.join(", "));
+        if self.options.cold_reads && !capabilities.contains(Capability::Evict) {
+            println!("  COLD READS:    not supported, reads may hit the caches");
+        }
+        if self.options.xattr_len.is_some() && !capabilities.contains(Capability::Xattr) {
+            println!("  XATTR:         not supported, skipped");
+        }
+        if self.options.hole_len.is_some() && !capabilities.contains(Capability::PunchHole) {
+            println!("  PUNCH HOLE:    not supported, skipped");
+        }
+        if self.options.copy && !capabilities.contains(Capability::Copy) {
+            println!("  COPY:          not supported, skipped");
+        }
+        if self.options.versioned && !capabilities.contains(Capability::Versions) {
+            println!("  VERSIONED:     not supported, skipped");
+        }
+        let tried: Result<()> = async {
+            hdlr.write(&key, &String::from("Hello World")).await?;
+            let value = hdlr.read(&key).await?;
+            if value != "Hello World" {
+                return Err(Error::new(format!("read {}: unexpected value", key)));
+            }
+            if !capabilities.contains(Capability::Delete) {
+                return Ok(());
+            }
+            hdlr.delete(&key).await?;
+            // The backend must report the key as not found, not fail otherwise.
+            match hdlr.read(&key).await {
+                Err(err) if err.is_not_found() => Ok(()),
+                Err(err) => Err(Error::new(format!("read {}: should be not found after delete, got: {}", key, err)).with_source(err)),
+                Ok(_) => Err(Error::new(format!("read {}: should fail after delete", key))),
+            }
+        }.await;
+        pool.teardown().await?;
+        tried
+    }
+
+    /// Test a step of the options: at the QPS, or by so many workers in a
+    /// concurrency sweep.
+    async fn test_step(&mut self, step: u64) -> Result<(StepSummary, StepHistograms)> {
+        if self.options.concurrency_sweep.is_empty() {
+            self.test_qps(step).await
+        } else {
+            self.test_concurrency(step as usize).await
+        }
+    }
+
+    pub async fn test_qps(&mut self, qps: u64) -> Result<(StepSummary, StepHistograms)> {
+        self.test_load(qps, None).await
+    }
+
+    /// Test by so many closed-loop workers, every one issuing its next
+    /// request once its last one finished, for the step duration. The pool
+    /// has as many handlers as workers, handed out round-robin, so a worker
+    /// may not issue all its requests on the same handler.
+    pub async fn test_concurrency(&mut self, workers: usize) -> Result<(StepSummary, StepHistograms)> {
+        self.test_load(workers as u64, Some(workers.max(1))).await
+    }
+
+    /// Issue requests at the QPS, or by the workers if given, when the QPS
+    /// is the number of workers to the live stream and the reporters.
+    async fn test_load(&mut self, qps: u64, workers: Option<usize>) -> Result<(StepSummary, StepHistograms)> {
+        // Cross reads need another handler to read by.
+        let cross_read = self.options.cross_read;
+        let pool_size = workers.unwrap_or(self.options.pool_size);
+        let pool_size = if cross_read { pool_size.max(2) } else { pool_size };
+        let pool = self.pool(pool_size).await?;
+        // High priority requests go by handlers of their own.
+        let priority_ratio = self.options.priority_ratio.filter(|ratio| *ratio > 0.0);
+        let priority_pool = match priority_ratio {
+            Some(_) => Some(self.priority_pool(pool_size).await?),
+            None => None,
+        };
+        let request_options = RequestOptions::new(&self.options, self.rng.gen(), self.clock.clone());
+
+        // Every namespace is a tenant, at the QPS, payload length and reads
+        // of the step unless the tenant tells otherwise.
+        let tenants = self.tenants();
+        let namespaces = tenants.len();
+        let rates: Vec<u64> = tenants.iter().map(|tenant| tenant.qps.unwrap_or(qps)).collect();
+        // Values of lengths drawn up to the max are cut from a payload as
+        // long, except the ones of tenants of their own lengths.
+        let len_range = self.options.payload_len_max
+            .filter(|max| *max > self.payload_len)
+            .map(|max| (self.payload_len, max));
+        let long_payload = len_range.map(|(_, max)| Arc::new(Payload::with_rng(max, &mut self.rng)));
+        let mut payloads = vec![];
+        let mut tenant_options = vec![];
+        for tenant in &tenants {
+            payloads.push(match (tenant.payload_len, &long_payload) {
+                (Some(len), _) if len != self.payload_len => (Arc::new(Payload::with_rng(len, &mut self.rng)), None),
+                (_, Some(long_payload)) => (long_payload.clone(), len_range),
+                _ => (self.payload.clone(), None),
+            });
+            tenant_options.push(request_options.for_tenant(tenant));
+        }
+
+        // Test.
+        let ttime_s = self.options.step_duration.as_secs();
+        let begin_time = self.clock.now();
+        let mut missed_sleep = 0;
+        // When every namespace issues its next request, and how many it has
+        // left to issue.
+        let intervals: Vec<Duration> = rates.iter().map(|rate| Duration::from_micros(1_000_000 / (*rate).max(1))).collect();
+        let mut due: Vec<Instant> = intervals.iter().map(|interval| begin_time + *interval).collect();
+        let mut remaining: Vec<u64> = rates.iter().map(|rate| ttime_s * rate).collect();
+        let total: u64 = remaining.iter().sum();
+        // Results are aggregated as requests finish, instead of keeping every
+        // request of the step until its end.
+        let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+        let mut aggregate = StepAggregate::new(
+            if namespaces > 1 || !self.options.tenants.is_empty() { namespaces } else { 0 },
+            priority_ratio.is_some(),
+            self.options.payload_len_max.is_some() || !self.options.size_sweep.is_empty(),
+        );
+        let in_flight = Arc::new(InFlight::default());
+        let mut requests = 0;
+        let (bar, template) = match (self.options.progress, workers) {
+            (false, _) => (ProgressBar::hidden(), "{prefix}{wide_bar} {pos}/{len}"),
+            (true, None) => (ProgressBar::new(total), "{prefix}{wide_bar} {pos}/{len}"),
+            // How many requests the workers issue is not known beforehand.
+            (true, Some(_)) => (ProgressBar::no_length(), "{prefix}{spinner} {pos}"),
+        };
+        let bar = bar
+            .with_prefix("  BAR: ")
+            .with_style(
+                ProgressStyle::with_template(template).unwrap()
+            );
+        bar.tick();
+        let live = self.live.as_ref().filter(|_| !self.warming_up).map(|live| live.start_step(qps));
+        // The warm-up is not reported.
+        let reporters = Arc::new(if self.warming_up { vec![] } else { self.reporters.clone() });
+        for reporter in reporters.iter() {
+            match workers {
+                Some(workers) => reporter.concurrency_step_started(workers),
+                None => reporter.step_started(qps),
+            }
+        }
+        // A worker is a permit, held by its request in flight.
+        let permits = workers.map(|workers| Arc::new(Semaphore::new(workers)));
+        loop {
+            // The namespaces due to issue a request, and when.
+            let (this_start_time, batch): (Instant, Vec<usize>) = match workers {
+                None => {
+                    let Some(next) = (0..namespaces).filter(|&ns| remaining[ns] > 0).map(|ns| due[ns]).min() else {
+                        break;
+                    };
+                    // Sleep to make sure the qps is right.
+                    if next > self.clock.now() {
+                        tokio::select! {
+                            _ = self.clock.sleep_until(next) => {}
+                            _ = self.cancel.cancelled() => {}
+                        }
+                    } else {
+                        missed_sleep += 1;
+                    }
+                    let batch: Vec<usize> = (0..namespaces).filter(|&ns| remaining[ns] > 0 && due[ns] == next).collect();
+                    for &ns in &batch {
+                        remaining[ns] -= 1;
+                        due[ns] += intervals[ns];
+                    }
+                    (next, batch)
+                }
+                Some(_) => {
+                    let now = self.clock.now();
+                    if now.duration_since(begin_time) >= self.options.step_duration {
+                        break;
+                    }
+                    (now, (0..namespaces).collect())
+                }
+            };
+            if self.cancel.is_cancelled() {
+                break;
+            }
+
+            // Query, once in every namespace due.
+            for namespace in batch {
+                // Wait for a worker to be free.
+                let permit = match &permits {
+                    Some(permits) => tokio::select! {
+                        permit = permits.clone().acquire_owned() => permit.ok(),
+                        _ = self.cancel.cancelled() => break,
+                    },
+                    None => None,
+                };
+                bar.inc(1);
+                let (payload, range) = payloads[namespace].clone();
+                let value_len = range.map(|(min, max)| draw_len(&mut self.rng, min, max));
+                let seq = self.seq;
+                self.seq += 1;
+                let shared = self.versions.as_ref().map(|versions| {
+                    (versions.clone(), self.rng.gen_range(0..versions.keys().len()))
+                });
+                let key = match &shared {
+                    Some((versions, idx)) => versions.keys()[*idx].clone(),
+                    None => self.gen_key(namespace),
+                };
+                let retain = shared.is_none() && self.options.scrub_ratio
+                    .map_or(false, |ratio| self.rng.gen::<f64>() < ratio);
+                if retain {
+                    self.retained.push((key.clone(), seq));
+                } else if shared.is_none() && self.options.verify_at_end {
+                    self.unverified.push((key.clone(), seq));
+                }
+                let request_options = tenant_options[namespace].clone();
+                let integrity = self.integrity.clone();
+                let recorder = live.as_ref().map(|live| live.recorder());
+                let reporters = reporters.clone();
+                let space = self.space.clone();
+                let offset = self.clock.now().saturating_duration_since(self.run_start);
+                let priority = priority_ratio.map_or(false, |ratio| self.rng.gen::<f64>() < ratio);
+                let from = match &priority_pool {
+                    Some(priority_pool) if priority => priority_pool,
+                    _ => &pool,
+                };
+                let hdlr = from.get();
+                let reader = if cross_read { from.get() } else { hdlr.clone() };
+                // A worker issues its request once free, not on a schedule.
+                let due_at = match permit {
+                    Some(_) => self.clock.now(),
+                    None => this_start_time,
+                };
+                let clock = self.clock.clone();
+                let done_tx = done_tx.clone();
+                let in_flight_guard = in_flight.enter();
+                tokio::spawn(async move {
+                    let _permit = permit;
+                    let _in_flight = in_flight_guard;
+                    let queue_delay = clock.now().saturating_duration_since(due_at);
+                    let timestamp = clock.wall(due_at);
+                    let value = match value_len {
+                        Some(len) => payload.value_of_len(&key, seq, len),
+                        None => payload.value(&key, seq),
+                    };
+                    let len = value.len();
+                    let hdlrs = Handlers { writer: &*hdlr, reader: &*reader };
+                    let mut result = match shared {
+                        Some((versions, idx)) => request_shared(&hdlrs, &key, value, seq, (&versions, idx), &integrity, &request_options).await,
+                        None => request(&hdlrs, &key, value, seq, retain, &integrity, &request_options).await,
+                    };
+                    result.extend(hdlr.take_phases().into_iter().map(|(metric, elapsed)| (metric, Ok(elapsed))));
+                    if !Arc::ptr_eq(&hdlr, &reader) {
+                        result.extend(reader.take_phases().into_iter().map(|(metric, elapsed)| (metric, Ok(elapsed))));
+                    }
+                    result.push((Metric::QueueDelay, Ok(queue_delay)));
+                    if let Some(space) = space {
+                        space.record(&result, len);
+                    }
+                    if let Some(recorder) = recorder {
+                        recorder.record(&result);
+                    }
+                    let info = RequestInfo { timestamp, qps, namespace, priority, key, len };
+                    for reporter in reporters.iter() {
+                        reporter.request(&info, &result);
+                    }
+                    let _ = done_tx.send(Done { offset, namespace, priority, len, result });
+                });
+                requests += 1;
+            }
+
+            // Aggregate the requests done so far.
+            while let Ok(done) = done_rx.try_recv() {
+                if !self.warming_up {
+                    self.timeseries.record(done.offset, &done.result);
+                }
+                aggregate.record(&done);
+            }
+        }
+        bar.finish();
+        let cancelled = self.cancel.is_cancelled();
+        if cancelled {
+            println!("  CANCELLED:     drain {} requests", in_flight.now());
+        }
+
+        // Aggregate the rest, as the last requests finish. A panicked request
+        // never reports back: it is counted but doesn't throw the step away.
+        drop(done_tx);
+        while let Some(done) = done_rx.recv().await {
+            if !self.warming_up {
+                self.timeseries.record(done.offset, &done.result);
+            }
+            aggregate.record(&done);
+        }
+        let panicked = (requests - aggregate.done) as u64;
+        if let Some(live) = live {
+            live.finish();
+        }
+        let end_time = self.clock.now();
+        let stale_reads = self.versions.as_ref().map_or(0, |versions| versions.take_stale_reads());
+        let usage = TesterUsage::now(in_flight.peak(), aggregate.allocated_bytes() + self.timeseries.allocated_bytes());
+        if let Err(err) = pool.teardown().await {
+            println!("  TEARDOWN:      {}", err);
+        }
+        if let Some(priority_pool) = &priority_pool {
+            if let Err(err) = priority_pool.teardown().await {
+                println!("  TEARDOWN:      {}", err);
+            }
+        }
+        let pool = pool.stats();
+        let integrity = self.integrity.take();
+        let readback = if self.options.verify_at_end {
+            Some(self.read_back().await?)
+        } else {
+            None
+        };
+        let StepAggregate { mut stats, namespaces: mut namespace_stats, priority: priority_stats, sizes: size_stats, .. } = aggregate;
+        let metrics = stats.summarize(end_time.duration_since(begin_time));
+        let namespace_summaries = namespace_stats.iter_mut()
+            .enumerate()
+            .map(|(namespace, stats)| NamespaceSummary {
+                name: tenants[namespace].name.clone(),
+                metrics: stats.summarize(end_time.duration_since(begin_time)),
+            })
+            .collect();
+        let priority = match (priority_ratio, priority_stats) {
+            (Some(ratio), Some((mut high, mut bulk))) => Some(PrioritySummary {
+                ratio,
+                high: high.summarize(end_time.duration_since(begin_time)),
+                bulk: bulk.summarize(end_time.duration_since(begin_time)),
+            }),
+            _ => None,
+        };
+        let sizes = match size_stats {
+            Some(size_stats) if size_stats.len() > 1 => size_stats.into_iter()
+                .map(|(len, mut stats)| SizeSummary { len, metrics: stats.summarize(end_time.duration_since(begin_time)) })
+                .collect(),
+            _ => vec![],
+        };
+        let histograms = StepHistograms { qps, payload_len: self.payload_len, metrics: stats.into_histograms(&metrics) };
+        let step = StepSummary {
+            // The workers go as fast as the backend lets them.
+            qps: match workers {
+                Some(_) => (requests as f64 / (end_time.duration_since(begin_time)).as_secs_f64().max(f64::EPSILON)).round() as u64,
+                None => qps,
+            },
+            concurrency: workers,
+            payload_len: self.payload_len,
+            duration_ms: (end_time.duration_since(begin_time)).as_millis() as u64,
+            missed_sleep,
+            scheduled: total,
+            panicked,
+            partial_writes: integrity.partial_writes,
+            content_mismatches: integrity.content_mismatches,
+            torn_reads: integrity.torn_reads,
+            stale_reads,
+            readback,
+            pool,
+            cancelled,
+            overhead: vec![],
+            overhead_subtracted: false,
+            metrics,
+            violations: vec![],
+            namespaces: namespace_summaries,
+            priority,
+            sizes,
+            usage,
+        };
+        Ok((step, histograms))
+    }
+
+    /// Measure the overhead of the tester at every step for the duration,
+    /// issuing the same requests to a backend doing nothing.
+    async fn calibrate(&self, duration: Duration) -> Result<Vec<((u64, usize), Vec<Overhead>)>> {
+        println!("CALIBRATE:");
+        let options = Options {
+            step_duration: duration,
+            warm_up: None,
+            retry: None,
+            verify: false,
+            check_size: false,
+            probe_visibility: None,
+            probe_delete_visibility: None,
+            shared_keys: None,
+            scrub_ratio: None,
+            verify_at_end: false,
+            hooks: vec![],
+            live: None,
+            prometheus: None,
+            calibrate: None,
+            space_interval: None,
+            health_interval: None,
+            profile_dir: None,
+            stall_factor: None,
+            ..self.options.clone()
+        };
+        let mut tester = Tester::with_options(NoopClient::new(), options)
+            .with_cancellation(self.cancel.clone())
+            .with_clock(self.clock.clone());
+        let mut overheads = vec![];
+        for (qps, payload_len) in self.options.steps() {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            tester.use_payload_len(payload_len);
+            let (step, _) = tester.test_step(qps).await?;
+            let overhead = Overhead::of(&step.metrics);
+            for overhead in &overhead {
+                println!("  {} OVERHEAD: p50 {}µs, p99 {}µs", overhead.metric.name().to_uppercase(), overhead.p50_us, overhead.p99_us);
+            }
+            overheads.push(((qps, payload_len), overhead));
+        }
+        Ok(overheads)
+    }
+
+    /// The name of the step in the names of files, by its QPS or workers and
+    /// its payload length in a size sweep.
+    #[cfg(feature = "profile")]
+    fn step_name(&self, step: &StepSummary) -> String {
+        let mut name = match step.concurrency {
+            Some(workers) => format!("workers-{}", workers),
+            None => format!("qps-{}", step.qps),
+        };
+        if !self.options.size_sweep.is_empty() {
+            name += &format!("-len-{}", step.payload_len);
+        }
+        name
+    }
+
+    /// The tenants of the options, or a tenant per namespace at the step.
+    fn tenants(&self) -> Vec<Tenant> {
+        if !self.options.tenants.is_empty() {
+            return self.options.tenants.clone();
+        }
+        (0..self.options.namespaces.max(1)).map(|idx| Tenant::new(namespace_name(idx))).collect()
+    }
+
+    /// Generate a key in the namespace, if the test has several.
+    fn gen_key(&mut self, namespace: usize) -> String {
+        if !self.options.tenants.is_empty() {
+            self.keys += 1;
+            self.client.gen_namespaced_key(&self.options.tenants[namespace].name)
+        } else if self.options.namespaces > 1 {
+            self.keys += 1;
+            self.client.gen_namespaced_key(&namespace_name(namespace))
+        } else {
+            self.gen_unique_key()
+        }
+    }
+
+    /// Generate a key, counted to be skipped when resumed.
+    fn gen_unique_key(&mut self) -> String {
+        self.keys += 1;
+        self.client.gen_unique_key()
+    }
+
+    /// Read back and delete all keys written in the step, out of the measured
+    /// time.
+    async fn read_back(&mut self) -> Result<ScrubSummary> {
+        let pool = self.pool(1).await?;
+        let hdlr = pool.get();
+        let readback = read_back(&*hdlr, &self.unverified).await;
+        for (key, _) in self.unverified.drain(..) {
+            let _ = hdlr.delete(&key).await;
+        }
+        pool.teardown().await?;
+        Ok(readback)
+    }
+}
+
+/// Done is what a request reports back to its step when it finishes.
+struct Done {
+    offset: Duration,
+    namespace: usize,
+    priority: bool,
+    len: usize,
+    result: TestResult,
+}
+
+/// StepAggregate folds the results of the requests of a step as they finish.
+struct StepAggregate {
+    stats: StepStats,
+    /// By namespace, if the step has several.
+    namespaces: Vec<StepStats>,
+    /// The high priority requests, and the bulk.
+    priority: Option<(StepStats, StepStats)>,
+    /// By the size class of the values, if their lengths vary.
+    sizes: Option<BTreeMap<usize, StepStats>>,
+    /// The requests folded.
+    done: usize,
+}
+
+impl StepAggregate {
+    fn new(namespaces: usize, priority: bool, sizes: bool) -> Self {
+        Self {
+            stats: StepStats::new(),
+            namespaces: (0..namespaces).map(|_| StepStats::new()).collect(),
+            priority: priority.then(|| (StepStats::new(), StepStats::new())),
+            sizes: sizes.then(BTreeMap::new),
+            done: 0,
+        }
+    }
+
+    fn record(&mut self, done: &Done) {
+        self.stats.record(&done.result);
+        if let Some(stats) = self.namespaces.get_mut(done.namespace) {
+            stats.record(&done.result);
+        }
+        if let Some((high, bulk)) = &mut self.priority {
+            let stats = if done.priority { high } else { bulk };
+            stats.record(&done.result);
+        }
+        if let Some(sizes) = &mut self.sizes {
+            sizes.entry(done.len.next_power_of_two()).or_insert_with(StepStats::new).record(&done.result);
+        }
+        self.done += 1;
+    }
+
+    fn allocated_bytes(&self) -> u64 {
+        let priority = self.priority.iter().flat_map(|(high, bulk)| [high, bulk]);
+        self.namespaces.iter().chain(priority).chain(self.sizes.iter().flat_map(BTreeMap::values)).chain([&self.stats])
+            .map(StepStats::allocated_bytes)
+            .sum()
+    }
+}
+
+/// Draw a length between `min` and `max`, log-uniformly so every size class
+/// gets about as many requests.
+fn draw_len(rng: &mut StdRng, min: usize, max: usize) -> usize {
+    let (low, high) = ((min.max(1) as f64).ln(), (max as f64).ln());
+    ((low + rng.gen::<f64>() * (high - low)).exp().round() as usize).clamp(min, max)
+}
+
+/// The name of the `idx`-th namespace.
+fn namespace_name(idx: usize) -> String {
+    format!("ns{}", idx)
+}
+
+#[cfg(test)]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::client::Op;
+
+    /// OversleepClock wakes up late from every sleep, like a busy runtime.
+    struct OversleepClock {
+        inner: TokioClock,
+        late: Duration,
+    }
+
+    #[async_trait]
+    impl Clock for OversleepClock {
+        fn now(&self) -> Instant {
+            self.inner.now()
+        }
+
+        fn wall(&self, at: Instant) -> Duration {
+            self.inner.wall(at)
+        }
+
+        async fn sleep_until(&self, deadline: Instant) {
+            tokio::time::sleep_until(deadline + self.late).await
+        }
+    }
+
+    fn options(qps: u64, step_duration: Duration) -> Options {
+        Options {
+            payload_len: 1024,
+            seed: Some(1),
+            qps_steps: vec![qps],
+            step_duration,
+            verify: false,
+            progress: false,
+            ..Options::default()
+        }
+    }
+
+    fn writes(step: &StepSummary) -> u64 {
+        step.metrics.iter()
+            .find(|summary| summary.metric == Metric::Op(Op::Write))
+            .map_or(0, |summary| summary.count)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paces_requests_at_the_qps() {
+        let mut tester = Tester::with_options(NoopClient::new(), options(10, Duration::from_secs(2)));
+        let report = tester.test().await.unwrap();
+        let step = &report.summary.steps[0];
+        assert_eq!(writes(step), 20);
+        assert_eq!(step.missed_sleep, 0);
+        assert_eq!(step.duration_ms, 2000);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn counts_requests_due_before_waking_up_as_missed_sleeps() {
+        // Waking up 150ms late at 10 QPS, every other request is already due.
+        let clock = Arc::new(OversleepClock { inner: TokioClock::new(), late: Duration::from_millis(150) });
+        let mut tester = Tester::with_options(NoopClient::new(), options(10, Duration::from_secs(2)))
+            .with_clock(clock);
+        let report = tester.test().await.unwrap();
+        let step = &report.summary.steps[0];
+        assert_eq!(writes(step), 20);
+        assert_eq!(step.missed_sleep, 10);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throws_the_warm_up_away() {
+        let options = Options { warm_up: Some(Duration::from_secs(1)), ..options(10, Duration::from_secs(2)) };
+        let start = Instant::now();
+        let mut tester = Tester::with_options(NoopClient::new(), options);
+        let report = tester.test().await.unwrap();
+        assert_eq!(report.summary.steps.len(), 1);
+        assert_eq!(writes(&report.summary.steps[0]), 20);
+        assert!(start.elapsed() >= Duration::from_secs(3));
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{client::Result, stats::Metric};
+
+/// Interval is what a metric measured in a second of a test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Interval {
+    /// The second since the test started.
+    pub second: u64,
+    pub metric: Metric,
+    /// The successful operations.
+    pub count: u64,
+    pub p50_us: u64,
+    pub p99_us: u64,
+    pub max_us: u64,
+}
+
+/// Stall is a run of seconds in which the p99 latency of an operation was
+/// far over its median of the whole test, e.g. a compaction or a GC pause of
+/// the backend.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Stall {
+    pub metric: Metric,
+    /// The first second of the stall since the test started.
+    pub second: u64,
+    pub seconds: u64,
+    /// The worst p99 of the seconds of the stall.
+    pub max_p99_us: u64,
+    /// The median of the metric over the whole test.
+    pub median_us: u64,
+}
+
+/// TimeSeries collects the latencies of a test second by second, since the
+/// test started.
+pub struct TimeSeries {
+    /// The latencies in microseconds, by second and by metric.
+    seconds: Vec<Vec<Vec<u64>>>,
+}
+
+impl TimeSeries {
+    pub fn new() -> Self {
+        Self { seconds: vec![] }
+    }
+
+    /// Record the result of a request issued at the offset.
+    pub fn record(&mut self, offset: Duration, result: &[(Metric, Result<Duration>)]) {
+        let second = offset.as_secs() as usize;
+        while self.seconds.len() <= second {
+            self.seconds.push(Metric::ALL.iter().map(|_| vec![]).collect());
+        }
+        for (metric, latency) in result {
+            if let Ok(latency) = latency {
+                let idx = Metric::ALL.iter().position(|m| m == metric).unwrap();
+                self.seconds[second][idx].push(latency.as_micros() as u64);
+            }
+        }
+    }
+
+    /// The bytes the latencies take.
+    pub fn allocated_bytes(&self) -> u64 {
+        let latencies: usize = self.seconds.iter().flatten().map(|latencies| latencies.capacity()).sum();
+        (latencies * std::mem::size_of::<u64>()) as u64
+    }
+
+    /// The number of seconds recorded.
+    pub fn seconds(&self) -> usize {
+        self.seconds.len()
+    }
+
+    /// The p99 latency in milliseconds of every second, by metric. Seconds
+    /// without a successful operation of a metric have no point.
+    pub fn p99(&self) -> Vec<(Metric, Vec<(f64, f64)>)> {
+        let mut series = vec![];
+        for (idx, metric) in Metric::ALL.iter().enumerate() {
+            let mut points = vec![];
+            for (second, metrics) in self.seconds.iter().enumerate() {
+                if metrics[idx].is_empty() {
+                    continue;
+                }
+                let mut latencies = metrics[idx].clone();
+                latencies.sort_unstable();
+                points.push((second as f64, nearest_rank(&latencies, 0.99) as f64 / 1000.0));
+            }
+            if !points.is_empty() {
+                series.push((*metric, points));
+            }
+        }
+        series
+    }
+
+    /// The count and the latencies of every second, by metric. Seconds
+    /// without a successful operation of a metric have no interval.
+    pub fn intervals(&self) -> Vec<Interval> {
+        let mut intervals = vec![];
+        for (second, metrics) in self.seconds.iter().enumerate() {
+            for (metric, latencies) in Metric::ALL.iter().zip(metrics) {
+                if latencies.is_empty() {
+                    continue;
+                }
+                let mut latencies = latencies.clone();
+                latencies.sort_unstable();
+                intervals.push(Interval {
+                    second: second as u64,
+                    metric: *metric,
+                    count: latencies.len() as u64,
+                    p50_us: nearest_rank(&latencies, 0.50),
+                    p99_us: nearest_rank(&latencies, 0.99),
+                    max_us: latencies[latencies.len() - 1],
+                });
+            }
+        }
+        intervals
+    }
+}
+
+impl TimeSeries {
+    /// The stalls of the operations: at least `min_secs` seconds in a row
+    /// whose p99 is over `factor` times the median of the test. A second
+    /// without a successful operation ends a stall, since it is likely
+    /// between steps.
+    pub fn stalls(&self, factor: f64, min_secs: u64) -> Vec<Stall> {
+        let mut stalls = vec![];
+        for (idx, metric) in Metric::ALL.iter().enumerate() {
+            if !matches!(metric, Metric::Op(_)) {
+                continue;
+            }
+            let mut all: Vec<u64> = self.seconds.iter().flat_map(|metrics| metrics[idx].iter().copied()).collect();
+            if all.is_empty() {
+                continue;
+            }
+            all.sort_unstable();
+            let median_us = nearest_rank(&all, 0.50);
+            let threshold = median_us as f64 * factor;
+            let mut stall: Option<Stall> = None;
+            for (second, metrics) in self.seconds.iter().enumerate() {
+                let p99 = (!metrics[idx].is_empty()).then(|| {
+                    let mut latencies = metrics[idx].clone();
+                    latencies.sort_unstable();
+                    nearest_rank(&latencies, 0.99)
+                });
+                match (p99.filter(|p99| *p99 as f64 > threshold), &mut stall) {
+                    (Some(p99), Some(stall)) => {
+                        stall.seconds += 1;
+                        stall.max_p99_us = stall.max_p99_us.max(p99);
+                    }
+                    (Some(p99), None) => {
+                        stall = Some(Stall { metric: *metric, second: second as u64, seconds: 1, max_p99_us: p99, median_us });
+                    }
+                    (None, _) => {
+                        stalls.extend(stall.take().filter(|stall| stall.seconds >= min_secs));
+                    }
+                }
+            }
+            stalls.extend(stall.filter(|stall| stall.seconds >= min_secs));
+        }
+        stalls.sort_by_key(|stall| stall.second);
+        stalls
+    }
+}
+
+/// The q-th percentile of sorted latencies, by the nearest rank.
+fn nearest_rank(sorted: &[u64], q: f64) -> u64 {
+    sorted[((sorted.len() as f64 * q).ceil() as usize).max(1) - 1]
+}
@@ -0,0 +1,213 @@
+use std::{fs::File, io::BufReader, path::{Path, PathBuf}, sync::Arc};
+
+use tokio::net::TcpStream;
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{
+        self,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        crypto::{ring, CryptoProvider},
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime},
+        server::WebPkiClientVerifier,
+        ClientConfig, DigitallySignedStruct, RootCertStore, ServerConfig, SignatureScheme,
+    },
+    TlsAcceptor, TlsConnector,
+};
+
+use super::{client::{Error, Result}, registry::Settings};
+
+/// TlsConfig is how a network backend connects over TLS: whom to trust, what
+/// to present, and whether to check the server at all. Every backend reads it
+/// from the same settings, `tls`, `tls_ca`, `tls_cert`, `tls_key`,
+/// `tls_insecure` and `tls_server_name`.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// A PEM bundle of the CAs to trust instead of the web PKI roots.
+    pub ca_bundle: Option<PathBuf>,
+    /// A PEM certificate chain and its key, to authenticate as a client.
+    pub client_cert: Option<PathBuf>,
+    pub client_key: Option<PathBuf>,
+    /// Accept any certificate of the server, e.g. a self-signed one.
+    pub insecure_skip_verify: bool,
+    /// The name to verify the server as [default: the host connected to].
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    /// The TLS config in the settings, if TLS is asked for: by `tls=true` or
+    /// any other of the TLS settings.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>> {
+        let enabled = match settings.get("tls").map(String::as_str) {
+            Some("true") => true,
+            Some("false") => return Ok(None),
+            Some(other) => return Err(Error::new(format!("tls: not a bool: {}", other))),
+            None => settings.keys().any(|key| key.starts_with("tls_")),
+        };
+        if !enabled {
+            return Ok(None);
+        }
+        let insecure_skip_verify = match settings.get("tls_insecure").map(String::as_str) {
+            None | Some("false") => false,
+            Some("true") => true,
+            Some(other) => return Err(Error::new(format!("tls_insecure: not a bool: {}", other))),
+        };
+        Ok(Some(Self {
+            ca_bundle: settings.get("tls_ca").map(PathBuf::from),
+            client_cert: settings.get("tls_cert").map(PathBuf::from),
+            client_key: settings.get("tls_key").map(PathBuf::from),
+            insecure_skip_verify,
+            server_name: settings.get("tls_server_name").cloned(),
+        }))
+    }
+
+    /// Build the connector of the config, loading the certificates, once for
+    /// every connection.
+    pub fn connector(&self) -> Result<TlsConnector> {
+        let provider = Arc::new(ring::default_provider());
+        let builder = ClientConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(|err| tls_error("tls", err))?;
+        let builder = if self.insecure_skip_verify {
+            builder.dangerous().with_custom_certificate_verifier(Arc::new(SkipVerification(provider)))
+        } else {
+            let mut roots = RootCertStore::empty();
+            match &self.ca_bundle {
+                Some(path) => {
+                    for cert in load_certs(path)? {
+                        roots.add(cert).map_err(|err| tls_error(&format!("tls ca {}", path.display()), err))?;
+                    }
+                }
+                None => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned()),
+            }
+            builder.with_root_certificates(roots)
+        };
+        let config = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => builder.with_client_auth_cert(load_certs(cert)?, load_key(key)?)
+                .map_err(|err| tls_error("tls client cert", err))?,
+            (None, None) => builder.with_no_client_auth(),
+            _ => return Err(Error::new("tls: a client cert needs its key and the other way round")),
+        };
+        Ok(TlsConnector::from(Arc::new(config)))
+    }
+
+    /// Shake hands over a connection to the host, verifying the server as
+    /// the host unless `server_name` is given.
+    pub async fn handshake(&self, connector: &TlsConnector, host: &str, stream: TcpStream) -> Result<TlsStream<TcpStream>> {
+        let name = self.server_name.as_deref().unwrap_or(host);
+        let name = ServerName::try_from(name.trim_start_matches('[').trim_end_matches(']').to_string())
+            .map_err(|err| tls_error("tls server name", err))?;
+        connector.connect(name, stream).await
+            .map_err(|err| Error::from_io_error(&format!("tls handshake {}", host), err))
+    }
+
+    pub fn describe(&self) -> String {
+        let mut parts = vec![];
+        if let Some(path) = &self.ca_bundle {
+            parts.push(format!("ca {}", path.display()));
+        }
+        if let Some(path) = &self.client_cert {
+            parts.push(format!("client cert {}", path.display()));
+        }
+        if self.insecure_skip_verify {
+            parts.push("insecure".to_string());
+        }
+        if let Some(name) = &self.server_name {
+            parts.push(format!("as {}", name));
+        }
+        if parts.is_empty() {
+            "on".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+}
+
+/// Build the acceptor of a server presenting the certificate, requiring the
+/// clients to present one signed by the CAs if given.
+pub fn tls_acceptor(cert: &Path, key: &Path, client_ca: Option<&Path>) -> Result<TlsAcceptor> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|err| tls_error("tls", err))?;
+    let builder = match client_ca {
+        Some(path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(path)? {
+                roots.add(cert).map_err(|err| tls_error(&format!("tls ca {}", path.display()), err))?;
+            }
+            let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|err| tls_error("tls client verifier", err))?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let config = builder.with_single_cert(load_certs(cert)?, load_key(key)?)
+        .map_err(|err| tls_error("tls cert", err))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn tls_error(prefix: &str, err: impl std::error::Error + Send + Sync + 'static) -> Error {
+    Error::new(format!("{}: {}", prefix, err)).with_source(err)
+}
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let prefix = format!("load certs {}", path.display());
+    let file = File::open(path).map_err(|err| Error::from_io_error(&prefix, err))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<std::io::Result<Vec<_>>>()
+        .map_err(|err| Error::from_io_error(&prefix, err))?;
+    if certs.is_empty() {
+        return Err(Error::new(format!("{}: no certificate", prefix)));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let prefix = format!("load key {}", path.display());
+    let file = File::open(path).map_err(|err| Error::from_io_error(&prefix, err))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|err| Error::from_io_error(&prefix, err))?
+        .ok_or_else(|| Error::new(format!("{}: no private key", prefix)))
+}
+
+/// SkipVerification accepts any certificate of the server, for
+/// `insecure_skip_verify`. The handshake is still checked to be signed by
+/// the certificate presented.
+#[derive(Debug)]
+struct SkipVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for SkipVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
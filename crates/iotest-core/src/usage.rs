@@ -0,0 +1,72 @@
+use std::{fs, sync::{atomic::{AtomicU64, Ordering}, Arc}};
+
+use serde::{Deserialize, Serialize};
+
+/// TesterUsage is what the tester itself took to run a step, to tell when
+/// the load generator rather than the backend is the limit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TesterUsage {
+    /// The most requests in flight at once.
+    pub peak_in_flight: u64,
+    /// The memory resident of the process at the end of the step, and the
+    /// most since it started, if the platform tells.
+    pub rss_bytes: Option<u64>,
+    pub peak_rss_bytes: Option<u64>,
+    /// The bytes the histograms of the step take, and the time series of the
+    /// whole test so far.
+    pub stats_bytes: u64,
+}
+
+impl TesterUsage {
+    /// The usage at the end of a step with the peak and the stats of the step.
+    pub fn now(peak_in_flight: u64, stats_bytes: u64) -> Self {
+        let (rss_bytes, peak_rss_bytes) = match memory() {
+            Some((rss, peak)) => (Some(rss), Some(peak)),
+            None => (None, None),
+        };
+        Self { peak_in_flight, rss_bytes, peak_rss_bytes, stats_bytes }
+    }
+}
+
+/// The resident memory of the process and its peak, from procfs.
+fn memory() -> Option<(u64, u64)> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let field = |name: &str| status.lines()
+        .find_map(|line| line.strip_prefix(name))
+        .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+        .map(|kb| kb * 1024);
+    Some((field("VmRSS:")?, field("VmHWM:")?))
+}
+
+/// InFlight counts the requests in flight, and the most at once.
+#[derive(Debug, Default)]
+pub struct InFlight {
+    now: AtomicU64,
+    peak: AtomicU64,
+}
+
+impl InFlight {
+    /// Count a request in flight until the guard is dropped, even if its task
+    /// panics.
+    pub fn enter(self: &Arc<Self>) -> InFlightGuard {
+        let now = self.now.fetch_add(1, Ordering::Relaxed) + 1;
+        self.peak.fetch_max(now, Ordering::Relaxed);
+        InFlightGuard(self.clone())
+    }
+
+    pub fn now(&self) -> u64 {
+        self.now.load(Ordering::Relaxed)
+    }
+
+    pub fn peak(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+}
+
+pub struct InFlightGuard(Arc<InFlight>);
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.0.now.fetch_sub(1, Ordering::Relaxed);
+    }
+}
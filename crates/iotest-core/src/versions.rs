@@ -0,0 +1,54 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// VersionTracker tracks, for every key of a shared key pool, the newest
+/// version written so far. Every write is tagged with a monotonically
+/// increasing version (the sequence number of its request), so a read
+/// returning an older version than one whose write had completed before the
+/// read started is a stale read.
+pub struct VersionTracker {
+    keys: Vec<String>,
+    /// The newest version of every key whose write completed, plus 1 (0 means
+    /// never written).
+    completed: Vec<AtomicU64>,
+    stale_reads: AtomicU64,
+}
+
+impl VersionTracker {
+    pub fn new(keys: Vec<String>) -> Self {
+        Self {
+            completed: keys.iter().map(|_| AtomicU64::new(0)).collect(),
+            keys,
+            stale_reads: AtomicU64::new(0),
+        }
+    }
+
+    pub fn keys(&self) -> &[String] {
+        &self.keys
+    }
+
+    /// Complete a write of the version of the key.
+    pub fn complete(&self, idx: usize, version: u64) {
+        self.completed[idx].fetch_max(version + 1, Ordering::AcqRel);
+    }
+
+    /// The version every read of the key starting now should return at
+    /// least, plus 1.
+    pub fn floor(&self, idx: usize) -> u64 {
+        self.completed[idx].load(Ordering::Acquire)
+    }
+
+    /// Observe a read of the key which started with the floor. Return whether
+    /// the read was stale.
+    pub fn observe(&self, idx: usize, floor: u64, version: u64) -> bool {
+        let stale = version + 1 < floor;
+        if stale {
+            self.stale_reads.fetch_add(1, Ordering::Relaxed);
+        }
+        stale
+    }
+
+    /// Take the count of stale reads observed since the last take.
+    pub fn take_stale_reads(&self) -> u64 {
+        self.stale_reads.swap(0, Ordering::Relaxed)
+    }
+}
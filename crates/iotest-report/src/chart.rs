@@ -0,0 +1,624 @@
+use std::{cmp::max, fs::create_dir_all, path::{Path, PathBuf}};
+
+use iotest_core::{Error, HealthSummary, HookEvent, Metric, MetricSummary, Op, Result, SpaceSample, Stall, StepHistograms, StepSummary, Summary, TestReport, TimeSeries, BUCKETS, BUCKETS_LEN};
+use metrics_util::Histogram;
+use plotters::{backend::BitMapBackend, chart::{ChartBuilder, SeriesLabelPosition}, drawing::IntoDrawingArea, element::{PathElement, Text}, prelude::{IntoSegmentedCoord, SegmentValue}, series::{self, LineSeries}, style::{text_anchor::{HPos, Pos, VPos}, Color, IntoFont, Palette, Palette99, TextStyle, BLACK, RED, WHITE}};
+
+/// Draw the histogram of every metric and step, the operations of every step
+/// overlaid, the latencies over time with the hooks marked, and the space
+/// used and the health checks if made, into the directory.
+pub fn draw(report: &TestReport, dir: &Path) -> Result<()> {
+    println!("CHARTS:");
+    let sweep = !report.summary.metadata.options.size_sweep.is_empty();
+    for histograms in &report.histograms {
+        let mut step_name = format!("qps-{}", histograms.qps);
+        if sweep {
+            step_name += &format!("-len-{}", histograms.payload_len);
+        }
+        for (metric, histogram) in &histograms.metrics {
+            let name = format!("{}-{}", metric.name(), step_name);
+            if let Some(picname) = draw_histogram(dir, &name, histogram)? {
+                println!("  See also: {}", picname.display());
+            }
+        }
+        if let Some(picname) = draw_ops(dir, &format!("ops-{}", step_name), histograms)? {
+            println!("  See also: {}", picname.display());
+        }
+    }
+    for step in report.summary.steps.iter().filter(|step| !step.sizes.is_empty()) {
+        let mut name = format!("sizes-qps-{}", step.qps);
+        if sweep {
+            name += &format!("-len-{}", step.payload_len);
+        }
+        let picname = draw_sizes(step, dir, &name)?;
+        println!("  See also: {}", picname.display());
+    }
+    let picname = draw_timeseries(&report.timeseries, dir, "timeseries", &report.summary.hooks, &report.summary.stalls)?;
+    println!("  See also: {}", picname.display());
+    if sweep {
+        let picname = draw_size_sweep(&report.summary, dir)?;
+        println!("  See also: {}", picname.display());
+    }
+    if !report.summary.metadata.options.concurrency_sweep.is_empty() {
+        let picname = draw_concurrency_sweep(&report.summary, dir)?;
+        println!("  See also: {}", picname.display());
+    }
+    if !report.summary.space.is_empty() {
+        let picname = draw_space(&report.summary.space, dir)?;
+        println!("  See also: {}", picname.display());
+    }
+    if let Some(health) = &report.summary.health {
+        let picname = draw_health(health, dir)?;
+        println!("  See also: {}", picname.display());
+    }
+    Ok(())
+}
+
+fn bucket_name(idx: i32) -> String {
+    if (idx as usize) >= BUCKETS.len() {
+        return "+inf".to_string()
+    }
+    let time = BUCKETS[idx as usize];
+    if time < 1000.0 {
+        return format!("{:.2}µs", time);
+    } else {
+        return format!("{:.2}ms", time / 1000.0);
+    }
+}
+
+/// Draw the histogram, if there are successful operations in it.
+pub fn draw_histogram(dir: &Path, name: &str, histogram: &Histogram) -> Result<Option<PathBuf>> {
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let sum = histogram.count();
+    if sum == 0 {
+        return Ok(None);
+    }
+
+    // Init the context to draw chart.
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, ((128 + 64) * 10, 960))
+        .into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(64)
+        .x_label_area_size(128)
+        .y_label_area_size(64 + 32)
+        .caption(name, ("sans-serif", 48))
+        .build_cartesian_2d((0..(BUCKETS_LEN as i32)).into_segmented(), 0..10000)
+        .map_err(plot_error)?;
+
+    // Build the data to draw the chart
+    let mut data = vec![];
+    let mut before = 0;
+    let mut max_height = 0;
+    for bar in histogram.buckets().into_iter().enumerate() {
+        let height = ((bar.1.1 - before) * 10000 + sum - 1) / sum;
+        data.push((
+            bar.0,
+            height,
+        ));
+        max_height = max(max_height, height as u64);
+        before = bar.1.1;
+    }
+    for bar in &mut data {
+        bar.1 = bar.1 * 8000 / max_height;
+    }
+
+    // Print into the chart.
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .y_desc("precent")
+        .x_desc("bucket")
+        .x_labels(BUCKETS_LEN)
+        .x_label_formatter(&|v: &SegmentValue<i32>| {
+            match *v {
+                SegmentValue::CenterOf(v) => {
+                    bucket_name(v)
+                }
+                _ => panic!("should be CenterOf(i32)"),
+            }
+        })
+        .y_label_formatter(&|v: &i32| {
+            format!("{:.2}%", *v * (max_height as i32) / 8000 / 100)
+        })
+        .y_label_style(("scan-serif", 24))
+        .x_label_style(
+            TextStyle::from(("scan-serif", 24).into_font())
+                .pos(Pos::new(HPos::Left, VPos::Center))
+                .transform(plotters::style::FontTransform::Rotate90)
+        )
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    chart.draw_series(
+        series::Histogram::vertical(&chart)
+            .style(RED.mix(0.5).filled())
+            .data(data.iter().map(|d| (d.0 as i32, d.1 as i32))),
+    ).map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(Some(picname))
+}
+
+/// The share in percent of the operations of the histogram in every bucket.
+fn bucket_shares(histogram: &Histogram) -> Vec<(i32, f64)> {
+    let sum = histogram.count().max(1) as f64;
+    let mut before = 0;
+    let mut shares = vec![];
+    for (idx, (_, count)) in histogram.buckets().into_iter().enumerate() {
+        shares.push((idx as i32, (count - before) as f64 * 100.0 / sum));
+        before = count;
+    }
+    shares
+}
+
+/// Draw the histograms of the writes, reads and deletes of a step overlaid,
+/// to compare their costs at a glance, if there are successful operations.
+pub fn draw_ops(dir: &Path, name: &str, histograms: &StepHistograms) -> Result<Option<PathBuf>> {
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let series: Vec<_> = Op::ALL.iter()
+        .filter_map(|op| histograms.metrics.iter().find(|(metric, _)| *metric == Metric::Op(*op)))
+        .filter(|(_, histogram)| histogram.count() > 0)
+        .map(|(metric, histogram)| (metric.name(), bucket_shares(histogram)))
+        .collect();
+    if series.is_empty() {
+        return Ok(None);
+    }
+    let max_y = series.iter()
+        .flat_map(|(_, shares)| shares.iter().map(|share| share.1))
+        .fold(1.0, f64::max) * 1.1;
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, ((128 + 64) * 10, 960)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(64)
+        .x_label_area_size(128)
+        .y_label_area_size(64 + 32)
+        .caption(name, ("sans-serif", 48))
+        .build_cartesian_2d(0..(BUCKETS_LEN as i32), 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .y_desc("precent")
+        .x_desc("bucket")
+        .x_labels(BUCKETS_LEN)
+        .x_label_formatter(&|idx: &i32| bucket_name(*idx))
+        .y_label_formatter(&|share: &f64| format!("{:.2}%", share))
+        .y_label_style(("scan-serif", 24))
+        .x_label_style(
+            TextStyle::from(("scan-serif", 24).into_font())
+                .pos(Pos::new(HPos::Left, VPos::Center))
+                .transform(plotters::style::FontTransform::Rotate90)
+        )
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    for (i, (label, shares)) in series.into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(shares, color.stroke_width(3)))
+            .map_err(plot_error)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(3)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperRight)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(Some(picname))
+}
+
+/// Draw the p99 latency of every second, with the hook events and the stalls
+/// marked.
+pub fn draw_timeseries(timeseries: &TimeSeries, dir: &Path, name: &str, events: &[HookEvent], stalls: &[Stall]) -> Result<PathBuf> {
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let series = timeseries.p99();
+    let max_x = timeseries.seconds().max(1) as f64;
+    let max_y = series.iter()
+        .flat_map(|(_, points)| points.iter().map(|point| point.1))
+        .fold(1.0, f64::max) * 1.1;
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, (1920, 960)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(64)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption(name, ("sans-serif", 48))
+        .build_cartesian_2d(0.0..max_x, 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("time (s)")
+        .y_desc("p99 (ms)")
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    for (i, (metric, points)) in series.into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(metric.name())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    for event in events {
+        let x = event.at_ms as f64 / 1000.0;
+        chart.draw_series(std::iter::once(PathElement::new(vec![(x, 0.0), (x, max_y)], BLACK.stroke_width(2))))
+            .map_err(plot_error)?;
+        chart.draw_series(std::iter::once(Text::new(
+            event.command.clone(),
+            (x, max_y * 0.95),
+            ("sans-serif", 20).into_font(),
+        ))).map_err(plot_error)?;
+    }
+    for stall in stalls {
+        let (from, to) = (stall.second as f64, (stall.second + stall.seconds) as f64);
+        chart.draw_series(std::iter::once(PathElement::new(vec![(from, max_y * 0.9), (to, max_y * 0.9)], RED.stroke_width(4))))
+            .map_err(plot_error)?;
+        chart.draw_series(std::iter::once(Text::new(
+            format!("{} stall", stall.metric.name()),
+            (from, max_y * 0.88),
+            ("sans-serif", 20).into_font().color(&RED),
+        ))).map_err(plot_error)?;
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(picname)
+}
+
+/// Draw the p50 and p99 latencies of writes and reads by the size class of
+/// the values of a step, to show how the latency scales with the size.
+pub fn draw_sizes(step: &StepSummary, dir: &Path, name: &str) -> Result<PathBuf> {
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let max_x = step.sizes.len().max(1) as i32;
+    let mut latencies = vec![];
+    for op in [Op::Write, Op::Read] {
+        let metric = |idx: usize| step.sizes[idx].metrics.iter().find(|metric| metric.metric == Metric::Op(op) && metric.count > metric.errors);
+        let series = |value: &dyn Fn(&MetricSummary) -> u64| -> Vec<(i32, f64)> {
+            (0..step.sizes.len()).filter_map(|idx| metric(idx).map(|m| (idx as i32, value(m) as f64 / 1000.0))).collect()
+        };
+        latencies.push((format!("{} p50", op.name()), series(&|m| m.p50_us)));
+        latencies.push((format!("{} p99", op.name()), series(&|m| m.p99_us)));
+    }
+    let max_y = latencies.iter().flat_map(|(_, points)| points.iter().map(|point| point.1)).fold(1.0, f64::max) * 1.1;
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, (1920, 960)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let x_label = |idx: &i32| step.sizes.get(*idx as usize).map(|size| format!("<={}", format_len(size.len))).unwrap_or_default();
+    let mut chart = ChartBuilder::on(&area)
+        .margin(64)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption(format!("latency by value size at {} QPS", step.qps), ("sans-serif", 48))
+        .build_cartesian_2d(0..max_x, 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("value size")
+        .y_desc("latency (ms)")
+        .x_labels(step.sizes.len().min(20))
+        .x_label_formatter(&x_label)
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    for (i, (label, points)) in latencies.into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(picname)
+}
+
+/// Draw the p50 and p99 latencies of writes and reads above, and their
+/// bandwidth below, by the payload length of the steps of a size sweep.
+pub fn draw_size_sweep(summary: &Summary, dir: &Path) -> Result<PathBuf> {
+    let name = "size-sweep";
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let lens: Vec<usize> = summary.steps.iter().map(|step| step.payload_len).collect();
+    let max_x = lens.len().max(1) as i32;
+    let series = |value: &dyn Fn(usize) -> Option<f64>| -> Vec<(i32, f64)> {
+        (0..lens.len()).filter_map(|idx| value(idx).map(|y| (idx as i32, y))).collect()
+    };
+    let metric = |idx: usize, op: Op| {
+        summary.steps[idx].metrics.iter().find(|metric| metric.metric == Metric::Op(op))
+    };
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, (1920, 1440)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let (upper, lower) = area.split_vertically(720);
+    let x_label = |idx: &i32| lens.get(*idx as usize).map(|len| format_len(*len)).unwrap_or_default();
+
+    // Latencies.
+    let mut latencies = vec![];
+    for op in [Op::Write, Op::Read] {
+        latencies.push((format!("{} p50", op.name()), series(&|idx| metric(idx, op).map(|m| m.p50_us as f64 / 1000.0))));
+        latencies.push((format!("{} p99", op.name()), series(&|idx| metric(idx, op).map(|m| m.p99_us as f64 / 1000.0))));
+    }
+    let max_y = latencies.iter().flat_map(|(_, points)| points.iter().map(|point| point.1)).fold(1.0, f64::max) * 1.1;
+    let mut chart = ChartBuilder::on(&upper)
+        .margin(64)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption("latency by payload length", ("sans-serif", 48))
+        .build_cartesian_2d(0..max_x, 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("payload length")
+        .y_desc("latency (ms)")
+        .x_labels(lens.len().min(20))
+        .x_label_formatter(&x_label)
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    for (i, (label, points)) in latencies.into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+
+    // Bandwidth.
+    let mut bandwidths = vec![];
+    for op in [Op::Write, Op::Read] {
+        bandwidths.push((op.name(), series(&|idx| metric(idx, op).map(|m| summary.steps[idx].bandwidth(m) / 1e6))));
+    }
+    let max_y = bandwidths.iter().flat_map(|(_, points)| points.iter().map(|point| point.1)).fold(1.0, f64::max) * 1.1;
+    let mut chart = ChartBuilder::on(&lower)
+        .margin(64)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption("bandwidth by payload length", ("sans-serif", 48))
+        .build_cartesian_2d(0..max_x, 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("payload length")
+        .y_desc("bandwidth (MB/s)")
+        .x_labels(lens.len().min(20))
+        .x_label_formatter(&x_label)
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    for (i, (label, points)) in bandwidths.into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(picname)
+}
+
+/// Draw the throughput above, and the p99 latencies of writes and reads
+/// below, by the workers of the steps of a concurrency sweep, with the knee
+/// marked.
+pub fn draw_concurrency_sweep(summary: &Summary, dir: &Path) -> Result<PathBuf> {
+    let name = "concurrency-sweep";
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let steps: Vec<_> = summary.steps.iter()
+        .filter_map(|step| step.concurrency.map(|workers| (workers, step)))
+        .collect();
+    let max_x = steps.len().max(1) as i32;
+    let x_label = |idx: &i32| steps.get(*idx as usize).map(|(workers, _)| workers.to_string()).unwrap_or_default();
+    let knee = steps.iter().position(|(workers, _)| summary.knee == Some(*workers)).map(|idx| idx as i32);
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, (1920, 1440)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let (upper, lower) = area.split_vertically(720);
+
+    let throughput: Vec<(i32, f64)> = steps.iter().enumerate().map(|(idx, (_, step))| (idx as i32, step.throughput())).collect();
+    let mut latencies = vec![];
+    for op in [Op::Write, Op::Read] {
+        let points: Vec<(i32, f64)> = steps.iter().enumerate()
+            .filter_map(|(idx, (_, step))| {
+                step.metrics.iter()
+                    .find(|metric| metric.metric == Metric::Op(op))
+                    .map(|metric| (idx as i32, metric.p99_us as f64 / 1000.0))
+            })
+            .collect();
+        latencies.push((format!("{} p99", op.name()), points));
+    }
+    let charts = [
+        ("throughput by workers", "writes/s", vec![("writes/s".to_string(), throughput)], upper),
+        ("p99 latency by workers", "p99 (ms)", latencies, lower),
+    ];
+    for (caption, y_desc, series, part) in charts {
+        let max_y = series.iter().flat_map(|(_, points)| points.iter().map(|point| point.1)).fold(1.0, f64::max) * 1.1;
+        let mut chart = ChartBuilder::on(&part)
+            .margin(64)
+            .x_label_area_size(64)
+            .y_label_area_size(96)
+            .caption(caption, ("sans-serif", 48))
+            .build_cartesian_2d(0..max_x, 0.0..max_y)
+            .map_err(plot_error)?;
+        chart
+            .configure_mesh()
+            .x_desc("workers")
+            .y_desc(y_desc)
+            .x_labels(steps.len().min(20))
+            .x_label_formatter(&x_label)
+            .axis_desc_style(("sans-serif", 32))
+            .draw()
+            .map_err(plot_error)?;
+        for (i, (label, points)) in series.into_iter().enumerate() {
+            let color = Palette99::pick(i).to_rgba();
+            chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+                .map_err(plot_error)?
+                .label(label)
+                .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+        }
+        if let Some(knee) = knee {
+            chart.draw_series(std::iter::once(PathElement::new(vec![(knee, 0.0), (knee, max_y)], RED.stroke_width(2))))
+                .map_err(plot_error)?;
+            chart.draw_series(std::iter::once(Text::new("knee", (knee, max_y * 0.95), ("sans-serif", 20).into_font().color(&RED))))
+                .map_err(plot_error)?;
+        }
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()
+            .map_err(plot_error)?;
+    }
+    area.present().map_err(plot_error)?;
+    Ok(picname)
+}
+
+/// Draw the space used on the storage over time, next to the bytes written
+/// and the bytes live: the gap to the live bytes is the space amplification,
+/// and a drop after the live bytes is a late reclamation.
+pub fn draw_space(samples: &[SpaceSample], dir: &Path) -> Result<PathBuf> {
+    let name = "space";
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let point = |sample: &SpaceSample, bytes: u64| (sample.at_ms as f64 / 1000.0, bytes as f64 / 1e6);
+    let series = [
+        ("used", samples.iter().map(|sample| point(sample, sample.used)).collect::<Vec<_>>()),
+        ("written", samples.iter().map(|sample| point(sample, sample.written)).collect()),
+        ("live", samples.iter().map(|sample| point(sample, sample.live)).collect()),
+    ];
+    let max_x = samples.iter().map(|sample| sample.at_ms as f64 / 1000.0).fold(1.0, f64::max);
+    let max_y = series.iter()
+        .flat_map(|(_, points)| points.iter().map(|point| point.1))
+        .fold(1.0, f64::max) * 1.1;
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, (1920, 960)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(64)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption(name, ("sans-serif", 48))
+        .build_cartesian_2d(0.0..max_x, 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("time (s)")
+        .y_desc("space (MB)")
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    for (i, (label, points)) in series.into_iter().enumerate() {
+        let color = Palette99::pick(i).to_rgba();
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(label)
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(picname)
+}
+
+/// Draw the latency of the health checks over time, with the outages marked
+/// in red.
+pub fn draw_health(health: &HealthSummary, dir: &Path) -> Result<PathBuf> {
+    let name = "health";
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", name, err));
+    let points: Vec<(f64, f64)> = health.checks.iter()
+        .filter(|check| check.is_healthy())
+        .map(|check| (check.at_ms as f64 / 1000.0, check.latency_us as f64 / 1000.0))
+        .collect();
+    let max_x = health.checks.iter().map(|check| check.at_ms as f64 / 1000.0).fold(1.0, f64::max);
+    let max_y = points.iter().map(|point| point.1).fold(1.0, f64::max) * 1.2;
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let picname = dir.join(format!("{}.png", name));
+    let area = BitMapBackend::new(&picname, (1920, 960)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(64)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption(format!("{} ({:.2}% available)", name, health.availability * 100.0), ("sans-serif", 48))
+        .build_cartesian_2d(0.0..max_x, 0.0..max_y)
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("time (s)")
+        .y_desc("latency (ms)")
+        .axis_desc_style(("sans-serif", 32))
+        .draw()
+        .map_err(plot_error)?;
+    let color = Palette99::pick(0).to_rgba();
+    chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+        .map_err(plot_error)?;
+    for outage in &health.outages {
+        let (from, to) = (outage.from_ms as f64 / 1000.0, outage.to_ms as f64 / 1000.0);
+        chart.draw_series(std::iter::once(PathElement::new(vec![(from, max_y * 0.9), (to, max_y * 0.9)], RED.stroke_width(4))))
+            .map_err(plot_error)?;
+        chart.draw_series(std::iter::once(Text::new(
+            "outage",
+            (from, max_y * 0.88),
+            ("sans-serif", 20).into_font().color(&RED),
+        ))).map_err(plot_error)?;
+    }
+    area.present().map_err(plot_error)?;
+    Ok(picname)
+}
+
+/// The length in the largest binary unit it is a whole number of, e.g. 64K.
+pub fn format_len(len: usize) -> String {
+    for (unit, size) in [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)] {
+        if len >= size && len % size == 0 {
+            return format!("{}{}", len / size, unit);
+        }
+    }
+    len.to_string()
+}
@@ -0,0 +1,157 @@
+use std::{fs::create_dir_all, path::{Path, PathBuf}};
+
+use iotest_core::{Error, Metric, Op, Result, Summary};
+use plotters::{backend::BitMapBackend, chart::{ChartBuilder, SeriesLabelPosition}, drawing::IntoDrawingArea, element::PathElement, series::LineSeries, style::{Color, BLACK, BLUE, CYAN, GREEN, MAGENTA, RED, WHITE}};
+
+/// Compare the p50 and p99 latencies of the backends tested by the same run,
+/// and draw the p99 of every metric with a line per backend.
+pub fn compare_backends(summaries: &[Summary], dir: &Path) -> Result<()> {
+    println!("COMPARE BACKENDS:");
+    let mut line = format!("  {:>6} {:<20}", "QPS", "METRIC");
+    for summary in summaries {
+        line += &format!(" {:>21}", format!("{} P50/P99", summary.metadata.label().to_uppercase()));
+    }
+    println!("{}", line);
+    let Some(first) = summaries.first() else {
+        return Ok(());
+    };
+    for step in &first.steps {
+        for metric in &step.metrics {
+            let mut line = format!("  {:>6} {:<20}", step.qps, metric.metric.name());
+            for summary in summaries {
+                let found = summary.steps.iter()
+                    .find(|other| other.qps == step.qps)
+                    .and_then(|other| other.metrics.iter().find(|other| other.metric == metric.metric));
+                line += &match found {
+                    Some(found) => format!(" {:>21}", format!("{} / {}", found.p50_us, found.p99_us)),
+                    None => format!(" {:>21}", "-"),
+                };
+            }
+            println!("{}", line);
+        }
+    }
+    println!("  (latencies in µs)");
+    // Where to place the workload: the lowest p99 over the steps all ran.
+    for metric in first.steps.first().map(|step| step.metrics.iter().map(|metric| metric.metric).collect::<Vec<_>>()).unwrap_or_default() {
+        if let Some((label, mean_p99)) = fastest(summaries, metric) {
+            println!("  {:<15}{} ({}µs p99 on average)", format!("FASTEST {}:", metric.name().to_uppercase()), label, mean_p99);
+        }
+    }
+    for metric in first.steps.first().map(|step| step.metrics.iter().map(|metric| metric.metric).collect::<Vec<_>>()).unwrap_or_default() {
+        let path = plot_backends(dir, metric, summaries)?;
+        println!("  See also: {}", path.display());
+    }
+    Ok(())
+}
+
+/// Print the reads of a cold run and of a warm one of the same backend side
+/// by side, step by step, with how much slower cold reads are.
+pub fn compare_cold_warm(cold: &Summary, warm: &Summary) {
+    println!("COLD VS WARM:");
+    println!("  COLD:          {}", cold.metadata.label());
+    println!("  WARM:          {}", warm.metadata.label());
+    println!("  {:>6} {:<20} {:>21} {:>21} {:>13}", "QPS", "METRIC", "COLD P50/P99", "WARM P50/P99", "P99 COLD/WARM");
+    let metric = Metric::Op(Op::Read);
+    for step in &cold.steps {
+        let Some(cold_read) = step.metrics.iter().find(|found| found.metric == metric) else {
+            continue;
+        };
+        let warm_read = warm.steps.iter()
+            .find(|other| other.qps == step.qps && other.payload_len == step.payload_len)
+            .and_then(|other| other.metrics.iter().find(|found| found.metric == metric));
+        let (warm_latencies, ratio) = match warm_read {
+            Some(warm_read) => (
+                format!("{} / {}", warm_read.p50_us, warm_read.p99_us),
+                format!("{:.2}x", cold_read.p99_us as f64 / warm_read.p99_us.max(1) as f64),
+            ),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        println!("  {:>6} {:<20} {:>21} {:>21} {:>13}", step.qps, metric.name(), format!("{} / {}", cold_read.p50_us, cold_read.p99_us), warm_latencies, ratio);
+    }
+    println!("  (latencies in µs)");
+}
+
+/// The backend of the lowest mean p99 of the metric over the QPS steps every
+/// backend ran, and that mean.
+fn fastest(summaries: &[Summary], metric: Metric) -> Option<(&str, u64)> {
+    let p99 = |summary: &Summary, qps: u64| {
+        summary.steps.iter()
+            .find(|step| step.qps == qps)
+            .and_then(|step| step.metrics.iter().find(|found| found.metric == metric))
+            .map(|found| found.p99_us)
+    };
+    let qps_steps: Vec<u64> = summaries.first()?.steps.iter()
+        .map(|step| step.qps)
+        .filter(|&qps| summaries.iter().all(|summary| p99(summary, qps).is_some()))
+        .collect();
+    if qps_steps.is_empty() {
+        return None;
+    }
+    summaries.iter()
+        .map(|summary| {
+            let total: u64 = qps_steps.iter().filter_map(|&qps| p99(summary, qps)).sum();
+            (summary.metadata.label(), total / qps_steps.len() as u64)
+        })
+        .min_by_key(|(_, mean_p99)| *mean_p99)
+}
+
+/// Draw the p99 of the metric over the QPS steps, a line per backend.
+fn plot_backends(dir: &Path, metric: Metric, summaries: &[Summary]) -> Result<PathBuf> {
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let path = dir.join(format!("compare-{}.png", metric.name()));
+    let plot_error = |err| Error::new(format!("plot {}: {:?}", path.display(), err));
+    let qps_steps: Vec<u64> = summaries.first()
+        .map(|summary| summary.steps.iter().map(|step| step.qps).collect())
+        .unwrap_or_default();
+    let p99 = |summary: &Summary, qps: u64| {
+        summary.steps.iter()
+            .find(|step| step.qps == qps)
+            .and_then(|step| step.metrics.iter().find(|found| found.metric == metric))
+            .map(|found| found.p99_us)
+    };
+    let max_p99 = summaries.iter()
+        .flat_map(|summary| qps_steps.iter().filter_map(move |&qps| p99(summary, qps)))
+        .max()
+        .unwrap_or(0);
+    let area = BitMapBackend::new(&path, (1280, 720)).into_drawing_area();
+    area.fill(&WHITE).map_err(plot_error)?;
+    let mut chart = ChartBuilder::on(&area)
+        .margin(32)
+        .x_label_area_size(64)
+        .y_label_area_size(96)
+        .caption(format!("{} p99 latency by backend", metric.name()), ("sans-serif", 40))
+        .build_cartesian_2d(0..(qps_steps.len().max(1) as i32), 0.0..(max_p99 as f64 / 1000.0 * 1.1 + 1.0))
+        .map_err(plot_error)?;
+    chart
+        .configure_mesh()
+        .x_desc("qps")
+        .y_desc("p99 (ms)")
+        .x_labels(qps_steps.len().min(20))
+        .x_label_formatter(&|idx: &i32| {
+            qps_steps.get(*idx as usize).map(|qps| qps.to_string()).unwrap_or_default()
+        })
+        .axis_desc_style(("sans-serif", 24))
+        .draw()
+        .map_err(plot_error)?;
+
+    let colors = [RED, BLUE, GREEN, MAGENTA, CYAN];
+    for (i, summary) in summaries.iter().enumerate() {
+        let color = colors[i % colors.len()];
+        let points = qps_steps.iter().enumerate().filter_map(|(idx, &qps)| {
+            p99(summary, qps).map(|p99_us| (idx as i32, p99_us as f64 / 1000.0))
+        });
+        chart.draw_series(LineSeries::new(points, color.stroke_width(2)))
+            .map_err(plot_error)?
+            .label(summary.metadata.label())
+            .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    }
+    chart
+        .configure_series_labels()
+        .position(SeriesLabelPosition::UpperLeft)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .draw()
+        .map_err(plot_error)?;
+    area.present().map_err(plot_error)?;
+    Ok(path)
+}
@@ -0,0 +1,195 @@
+use std::time::Duration;
+
+use iotest_core::{Metric, Op, StepHistograms, StepSummary, Summary, TestReport};
+use metrics_util::Histogram;
+
+use super::chart::format_len;
+
+/// Print how every step went with the histograms, the errors and the timeouts
+/// of it, then the summary table.
+pub fn print(report: &TestReport) {
+    for (step, histograms) in report.summary.steps.iter().zip(&report.histograms) {
+        print_step(step, histograms);
+    }
+    report.summary.print_table();
+    if !report.summary.metadata.options.size_sweep.is_empty() {
+        print_size_sweep(&report.summary);
+    }
+    if !report.summary.metadata.options.concurrency_sweep.is_empty() {
+        print_concurrency_sweep(&report.summary);
+    }
+}
+
+/// Print the latencies and the bandwidth of writes and reads by payload
+/// length, of a size sweep.
+pub fn print_size_sweep(summary: &Summary) {
+    println!("SIZE SWEEP:");
+    let mut line = format!("  {:>10}", "LEN");
+    for op in [Op::Write, Op::Read] {
+        let name = op.name().to_uppercase();
+        line += &format!(" | {:>9} {:>9} {:>10}", format!("{} P50", name), format!("{} P99", name), "MB/S");
+    }
+    println!("{}", line);
+    println!("  {}", "-".repeat(line.len() - 2));
+    for step in &summary.steps {
+        let mut line = format!("  {:>10}", step.payload_len);
+        for op in [Op::Write, Op::Read] {
+            line += &match step.metrics.iter().find(|metric| metric.metric == Metric::Op(op)) {
+                Some(metric) => format!(" | {:>9} {:>9} {:>10.2}", metric.p50_us, metric.p99_us, step.bandwidth(metric) / 1e6),
+                None => format!(" | {:>9} {:>9} {:>10}", "-", "-", "-"),
+            };
+        }
+        println!("{}", line);
+    }
+    println!("  (latencies in µs)");
+}
+
+/// Print the throughput and the p99 latencies of writes and reads by the
+/// workers, of a concurrency sweep, with the knee marked.
+pub fn print_concurrency_sweep(summary: &Summary) {
+    println!("CONCURRENCY SWEEP:");
+    let line = format!("  {:>7} | {:>10} | {:>9} {:>9}", "WORKERS", "WRITES/S", "WRITE P99", "READ P99");
+    println!("{}", line);
+    println!("  {}", "-".repeat(line.len() - 2));
+    for step in &summary.steps {
+        let Some(workers) = step.concurrency else {
+            continue;
+        };
+        let p99 = |op| step.metrics.iter()
+            .find(|metric| metric.metric == Metric::Op(op))
+            .map_or("-".to_string(), |metric| metric.p99_us.to_string());
+        let knee = if summary.knee == Some(workers) { " <- knee" } else { "" };
+        println!("  {:>7} | {:>10.1} | {:>9} {:>9}{}", workers, step.throughput(), p99(Op::Write), p99(Op::Read), knee);
+    }
+    println!("  (latencies in µs)");
+}
+
+/// Print how a step went with the histograms, the errors and the timeouts of
+/// it. The counts of inconsistencies are printed if any were found.
+pub fn print_step(step: &StepSummary, histograms: &StepHistograms) {
+    println!("RESULT:");
+    if let Some(workers) = step.concurrency {
+        println!("  WORKERS:       {}", workers);
+    }
+    println!("  QPS:           {}", step.qps);
+    println!("  PAYLOAD LEN:   {}", step.payload_len);
+    println!("  DURATION TIME: {:?}", Duration::from_millis(step.duration_ms));
+    if !step.namespaces.is_empty() {
+        println!("  NAMESPACES:    {}", step.namespaces.len());
+    }
+    if step.concurrency.is_none() {
+        println!("  MISSED SLEEP:  {} ({:02}%)", step.missed_sleep, (step.missed_sleep as f64) * 100.0 / (step.scheduled.max(1) as f64));
+    }
+    println!("  PANICKED:      {}", step.panicked);
+    println!("  IN FLIGHT:     peak {}", step.usage.peak_in_flight);
+    if let (Some(rss), Some(peak)) = (step.usage.rss_bytes, step.usage.peak_rss_bytes) {
+        println!("  MEMORY:        rss {:.1}MB, peak {:.1}MB, stats {:.1}MB", rss as f64 / 1e6, peak as f64 / 1e6, step.usage.stats_bytes as f64 / 1e6);
+    }
+    println!("  POOL:          {} handlers, {} checkouts, {} reused", step.pool.size, step.pool.checkouts, step.pool.reuses);
+    println!("  SETUP:         mean {}µs, max {}µs", step.pool.setup_mean_us, step.pool.setup_max_us);
+    if step.stale_reads > 0 {
+        println!("  STALE READS:   {}", step.stale_reads);
+    }
+    if step.partial_writes > 0 {
+        println!("  PARTIAL WRITE: {}", step.partial_writes);
+    }
+    if step.content_mismatches > 0 || step.torn_reads > 0 {
+        println!("  MISMATCHES:    {}", step.content_mismatches);
+        println!("  TORN READS:    {}", step.torn_reads);
+    }
+    if let Some(readback) = &step.readback {
+        println!("  READBACK:      {} ok, {} missing, {} unreadable, {} corrupted, {} torn", readback.ok, readback.missing, readback.unreadable, readback.corrupted, readback.torn);
+        for problem in &readback.problems {
+            println!("    {}", problem);
+        }
+    }
+    for (summary, (_, histogram)) in step.metrics.iter().zip(&histograms.metrics) {
+        let name = summary.metric.name().to_uppercase();
+        println!("  {} HISTOGRAM:", name);
+        print_histogram(histogram);
+        println!("  {} ERRORS: {} ({:.2}%)", name, summary.errors, summary.error_rate * 100.0);
+        if !summary.errors_by_class.is_empty() {
+            let classes: Vec<String> = summary.errors_by_class.iter()
+                .map(|(class, errors)| format!("{}={}", class.name(), errors))
+                .collect();
+            println!("  {} ERRORS BY CLASS: {}", name, classes.join(" "));
+        }
+        println!("  {} TIMEOUTS: {} ({:.2}%)", name, summary.timeouts, summary.timeout_rate * 100.0);
+        if let Some(overhead) = step.overhead.iter().find(|overhead| overhead.metric == summary.metric) {
+            let subtracted = if step.overhead_subtracted { ", subtracted" } else { "" };
+            println!("  {} OVERHEAD: p50 {}µs, p99 {}µs{}", name, overhead.p50_us, overhead.p99_us, subtracted);
+        }
+    }
+    for namespace in &step.namespaces {
+        for summary in &namespace.metrics {
+            println!("  {} {}: p50 {}µs, p99 {}µs, {} errors, {} timeouts",
+                namespace.name.to_uppercase(),
+                summary.metric.name().to_uppercase(),
+                summary.p50_us,
+                summary.p99_us,
+                summary.errors,
+                summary.timeouts,
+            );
+        }
+    }
+    if let Some(priority) = &step.priority {
+        println!("  PRIORITY:      {:.1}% of requests", priority.ratio * 100.0);
+        for high in &priority.high {
+            let Some(bulk) = priority.bulk.iter().find(|bulk| bulk.metric == high.metric) else {
+                continue;
+            };
+            println!("  PRIORITY {}: p50 {}µs, p99 {}µs, bulk p50 {}µs, p99 {}µs ({:.2}x)",
+                high.metric.name().to_uppercase(),
+                high.p50_us,
+                high.p99_us,
+                bulk.p50_us,
+                bulk.p99_us,
+                high.p99_us as f64 / bulk.p99_us.max(1) as f64,
+            );
+        }
+    }
+    let metric = |metric| step.metrics.iter().find(|summary| summary.metric == metric);
+    if let (Some(version), Some(latest)) = (metric(Metric::ReadVersion), metric(Metric::Op(Op::Read))) {
+        println!("  READ VERSION:  p50 {}µs, p99 {}µs, latest p50 {}µs, p99 {}µs ({:.2}x)",
+            version.p50_us,
+            version.p99_us,
+            latest.p50_us,
+            latest.p99_us,
+            version.p99_us as f64 / latest.p99_us.max(1) as f64,
+        );
+    }
+    for size in &step.sizes {
+        for summary in size.metrics.iter().filter(|summary| matches!(summary.metric, Metric::Op(_))) {
+            println!("  SIZE <={} {}: p50 {}µs, p99 {}µs, {} ops",
+                format_len(size.len),
+                summary.metric.name().to_uppercase(),
+                summary.p50_us,
+                summary.p99_us,
+                summary.count,
+            );
+        }
+    }
+}
+
+pub fn print_histogram(histogram: &Histogram) {
+    let sum = histogram.count();
+    if sum == 0 {
+        println!("    (no successful operations)");
+        return;
+    }
+
+    println!("    {}", "-".repeat(10 + 1 + 100 + 1 + 10));
+    let mut before = 0;
+    for bar in histogram.buckets().clone().into_iter().step_by(4) {
+        let dots_num = (((bar.1 - before) * 100 + sum - 1) / sum) as usize;
+        let spaces_num = 100 - dots_num;
+        println!("    {:10} {}{} {}",
+            format!("{:?}", Duration::from_micros(bar.0 as u64)),
+            ".".repeat(dots_num),
+            " ".repeat(spaces_num),
+            bar.1 - before,
+        );
+        before = bar.1;
+    }
+    println!("    {}", "-".repeat(10 + 1 + 100 + 1 + 10));
+}
@@ -0,0 +1,111 @@
+use std::{fs::{self, File}, path::{Path, PathBuf}, sync::{Arc, Mutex, PoisonError}};
+
+use arrow::{array::{ArrayRef, StringArray, UInt64Array}, record_batch::RecordBatch};
+use parquet::arrow::ArrowWriter;
+
+use iotest_core::{Error, Interval, RequestInfo, Reporter, Result, TestReport, TestResult, TimeSeries};
+
+/// ParquetReporter writes every operation of every request, like the
+/// `RequestLogReporter`, and the per-second aggregates of every metric as
+/// Parquet files into a directory at the end, to load into DuckDB, pandas or
+/// Spark:
+///
+/// - `requests.parquet`: `timestamp_us`, `qps`, `namespace`, `op`, `key`,
+///   `len`, `latency_us` (null if failed) and `outcome`.
+/// - `intervals.parquet`: `second`, `op`, `count`, `p50_us`, `p99_us` and
+///   `max_us`.
+///
+/// The requests are kept in memory until the end.
+pub struct ParquetReporter {
+    pub dir: PathBuf,
+    requests: Mutex<Requests>,
+}
+
+/// Requests are the columns of the requests.
+#[derive(Default)]
+struct Requests {
+    timestamp_us: Vec<u64>,
+    qps: Vec<u64>,
+    namespace: Vec<u64>,
+    op: Vec<&'static str>,
+    key: Vec<String>,
+    len: Vec<u64>,
+    latency_us: Vec<Option<u64>>,
+    outcome: Vec<&'static str>,
+}
+
+impl ParquetReporter {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir, requests: Mutex::new(Requests::default()) }
+    }
+}
+
+impl Reporter for ParquetReporter {
+    fn request(&self, request: &RequestInfo, result: &TestResult) {
+        let mut requests = self.requests.lock().unwrap_or_else(PoisonError::into_inner);
+        for (metric, latency) in result {
+            requests.timestamp_us.push(request.timestamp.as_micros() as u64);
+            requests.qps.push(request.qps);
+            requests.namespace.push(request.namespace as u64);
+            requests.op.push(metric.name());
+            requests.key.push(request.key.clone());
+            requests.len.push(request.len as u64);
+            match latency {
+                Ok(latency) => {
+                    requests.latency_us.push(Some(latency.as_micros() as u64));
+                    requests.outcome.push("ok");
+                }
+                Err(err) => {
+                    requests.latency_us.push(None);
+                    requests.outcome.push(err.class().name());
+                }
+            }
+        }
+    }
+
+    fn finish(&self, report: &TestReport) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|err| Error::from_io_error(&format!("create {}", self.dir.display()), err))?;
+        let requests = std::mem::take(&mut *self.requests.lock().unwrap_or_else(PoisonError::into_inner));
+        write(&self.dir.join("requests.parquet"), vec![
+            ("timestamp_us", Arc::new(UInt64Array::from(requests.timestamp_us)) as ArrayRef),
+            ("qps", Arc::new(UInt64Array::from(requests.qps))),
+            ("namespace", Arc::new(UInt64Array::from(requests.namespace))),
+            ("op", Arc::new(StringArray::from(requests.op))),
+            ("key", Arc::new(StringArray::from(requests.key))),
+            ("len", Arc::new(UInt64Array::from(requests.len))),
+            ("latency_us", Arc::new(UInt64Array::from(requests.latency_us))),
+            ("outcome", Arc::new(StringArray::from(requests.outcome))),
+        ])?;
+        write_intervals(&self.dir.join("intervals.parquet"), &report.timeseries)?;
+        println!("PARQUET:");
+        println!("  See also: {}", self.dir.display());
+        Ok(())
+    }
+}
+
+/// Write the per-second aggregates of the time series.
+fn write_intervals(path: &Path, timeseries: &TimeSeries) -> Result<()> {
+    let intervals = timeseries.intervals();
+    let column = |f: fn(&Interval) -> u64| Arc::new(UInt64Array::from_iter_values(intervals.iter().map(f))) as ArrayRef;
+    write(path, vec![
+        ("second", column(|interval| interval.second)),
+        ("op", Arc::new(StringArray::from_iter_values(intervals.iter().map(|interval| interval.metric.name())))),
+        ("count", column(|interval| interval.count)),
+        ("p50_us", column(|interval| interval.p50_us)),
+        ("p99_us", column(|interval| interval.p99_us)),
+        ("max_us", column(|interval| interval.max_us)),
+    ])
+}
+
+/// Write the columns as a Parquet file of one row group.
+fn write(path: &Path, columns: Vec<(&str, ArrayRef)>) -> Result<()> {
+    let failed = |err: &dyn std::fmt::Display| Error::new(format!("write {}: {}", path.display(), err));
+    let batch = RecordBatch::try_from_iter(columns).map_err(|err| failed(&err))?;
+    let file = File::create(path)
+        .map_err(|err| Error::from_io_error(&format!("create {}", path.display()), err))?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None).map_err(|err| failed(&err))?;
+    writer.write(&batch).map_err(|err| failed(&err))?;
+    writer.close().map_err(|err| failed(&err))?;
+    Ok(())
+}
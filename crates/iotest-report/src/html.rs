@@ -0,0 +1,126 @@
+use std::{fs::{self, create_dir_all}, path::{Path, PathBuf}};
+
+use iotest_core::{Error, Reporter, Result, TestReport};
+use serde_json::{json, Value};
+
+/// HtmlReporter writes the charts as one interactive HTML page into a
+/// directory at the end, drawn by vega-lite in the browser: the histograms
+/// can be zoomed into their tails, and every point tells its value.
+pub struct HtmlReporter {
+    pub dir: PathBuf,
+}
+
+impl Reporter for HtmlReporter {
+    fn finish(&self, report: &TestReport) -> Result<()> {
+        let path = write_html(report, &self.dir)?;
+        println!("HTML CHARTS:");
+        println!("  See also: {}", path.display());
+        Ok(())
+    }
+}
+
+/// Write the time series and the histograms of every step as
+/// `report.html` into the directory.
+pub fn write_html(report: &TestReport, dir: &Path) -> Result<PathBuf> {
+    let mut specs = vec![timeseries_spec(report)];
+    let sweep = !report.summary.metadata.options.size_sweep.is_empty();
+    for histograms in &report.histograms {
+        let mut title = format!("latency at QPS {}", histograms.qps);
+        if sweep {
+            title += &format!(", payload length {}", histograms.payload_len);
+        }
+        let mut values = vec![];
+        for (metric, histogram) in &histograms.metrics {
+            let sum = histogram.count();
+            if sum == 0 {
+                continue;
+            }
+            let mut before = 0;
+            for (bound, count) in histogram.buckets() {
+                // The overflow bucket has no bound to put on a log scale.
+                if !bound.is_finite() {
+                    continue;
+                }
+                values.push(json!({
+                    "metric": metric.name(),
+                    "latency_us": bound,
+                    "percent": (count - before) as f64 * 100.0 / sum as f64,
+                }));
+                before = count;
+            }
+        }
+        specs.push(json!({
+            "title": title,
+            "width": 960,
+            "height": 400,
+            "data": { "values": values },
+            "mark": { "type": "line", "point": true, "tooltip": true },
+            "encoding": {
+                "x": { "field": "latency_us", "type": "quantitative", "scale": { "type": "log" }, "title": "latency (µs, upper bound of the bucket)" },
+                "y": { "field": "percent", "type": "quantitative", "title": "percent" },
+                "color": { "field": "metric", "type": "nominal" },
+            },
+            "params": [{ "name": "zoom", "select": "interval", "bind": "scales" }],
+        }));
+    }
+
+    create_dir_all(dir).map_err(|err| Error::from_io_error(&format!("mkdir {}", dir.display()), err))?;
+    let path = dir.join("report.html");
+    let title = report.summary.metadata.label();
+    // Keep `</script>` in the data from closing the script.
+    let specs = Value::Array(specs).to_string().replace("</", "<\\/");
+    let html = format!(r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>iotest {title}</title>
+<script src="https://cdn.jsdelivr.net/npm/vega@5"></script>
+<script src="https://cdn.jsdelivr.net/npm/vega-lite@5"></script>
+<script src="https://cdn.jsdelivr.net/npm/vega-embed@6"></script>
+</head>
+<body>
+<h1>iotest {title}</h1>
+<p>Drag to pan and scroll to zoom, double click to reset.</p>
+<div id="charts"></div>
+<script>
+const specs = {specs};
+for (const spec of specs) {{
+  const div = document.createElement("div");
+  document.getElementById("charts").appendChild(div);
+  vegaEmbed(div, spec);
+}}
+</script>
+</body>
+</html>
+"#, title = html_escape(title), specs = specs);
+    fs::write(&path, html).map_err(|err| Error::from_io_error(&format!("write {}", path.display()), err))?;
+    Ok(path)
+}
+
+/// The p50 and p99 latencies of every second, by metric.
+fn timeseries_spec(report: &TestReport) -> Value {
+    let values: Vec<_> = report.timeseries.intervals().iter()
+        .flat_map(|interval| [("p50", interval.p50_us), ("p99", interval.p99_us)].map(|(percentile, us)| json!({
+            "second": interval.second,
+            "series": format!("{} {}", interval.metric.name(), percentile),
+            "latency_ms": us as f64 / 1000.0,
+        })))
+        .collect();
+    json!({
+        "title": "latency over time",
+        "width": 960,
+        "height": 400,
+        "data": { "values": values },
+        "mark": { "type": "line", "tooltip": true },
+        "encoding": {
+            "x": { "field": "second", "type": "quantitative", "title": "time (s)" },
+            "y": { "field": "latency_ms", "type": "quantitative", "title": "latency (ms)" },
+            "color": { "field": "series", "type": "nominal" },
+        },
+        "params": [{ "name": "zoom", "select": "interval", "bind": "scales" }],
+    })
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
@@ -0,0 +1,21 @@
+//! iotest-report prints and draws what a test of `iotest-core` results in:
+//! the histograms and the summary table on the console, the charts as PNG (or
+//! as an interactive HTML page), the summary as JSON and every request as CSV
+//! (or as Parquet with the `parquet` feature), each also as a `Reporter` the
+//! tester calls as it goes.
+
+mod chart;
+mod compare;
+mod console;
+#[cfg(feature = "parquet")]
+mod export;
+mod html;
+mod reporter;
+
+pub use chart::{draw, draw_concurrency_sweep, draw_health, draw_histogram, draw_ops, draw_size_sweep, draw_sizes, draw_space, draw_timeseries};
+pub use compare::{compare_backends, compare_cold_warm};
+pub use console::{print, print_concurrency_sweep, print_histogram, print_size_sweep, print_step};
+#[cfg(feature = "parquet")]
+pub use export::ParquetReporter;
+pub use html::{write_html, HtmlReporter};
+pub use reporter::{ChartReporter, ConsoleReporter, JsonReporter, RequestLogReporter};
@@ -0,0 +1,139 @@
+use std::{fs::File, io::{self, BufWriter, Write}, path::PathBuf, sync::{Mutex, PoisonError}};
+
+use iotest_core::{Error, RequestInfo, Reporter, Result, StepHistograms, StepSummary, TestReport, TestResult};
+
+use super::{chart, console};
+
+/// ConsoleReporter prints the results of every step after it, and the summary
+/// table at the end.
+pub struct ConsoleReporter;
+
+impl Reporter for ConsoleReporter {
+    fn step_started(&self, qps: u64) {
+        println!("TEST:");
+        println!("  QPS:           {}", qps);
+    }
+
+    fn concurrency_step_started(&self, workers: usize) {
+        println!("TEST:");
+        println!("  WORKERS:       {}", workers);
+    }
+
+    fn step_finished(&self, step: &StepSummary, histograms: &StepHistograms) {
+        console::print_step(step, histograms);
+    }
+
+    fn finish(&self, report: &TestReport) -> Result<()> {
+        report.summary.print_table();
+        if !report.summary.metadata.options.size_sweep.is_empty() {
+            console::print_size_sweep(&report.summary);
+        }
+        if !report.summary.metadata.options.concurrency_sweep.is_empty() {
+            console::print_concurrency_sweep(&report.summary);
+        }
+        Ok(())
+    }
+}
+
+/// ChartReporter draws the histograms and the time series into a directory
+/// at the end.
+pub struct ChartReporter {
+    pub dir: PathBuf,
+}
+
+impl Reporter for ChartReporter {
+    fn finish(&self, report: &TestReport) -> Result<()> {
+        chart::draw(report, &self.dir)
+    }
+}
+
+/// JsonReporter writes the summary as JSON to a path at the end.
+pub struct JsonReporter {
+    pub path: PathBuf,
+}
+
+impl Reporter for JsonReporter {
+    fn finish(&self, report: &TestReport) -> Result<()> {
+        report.summary.write_to(&self.path)?;
+        println!("SUMMARY JSON:");
+        println!("  See also: {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// RequestLogReporter writes every operation of every request to a CSV file,
+/// for analyses beyond the buckets of the histograms, a line each:
+///
+/// ```text
+/// timestamp_us,qps,namespace,op,key,len,latency_us,outcome
+/// 1700000000000000,100,0,write,/tmp/iotest_42/0,1048640,1250,ok
+/// ```
+///
+/// The outcome is `ok`, or the class of the error, e.g. `timeout`; failed
+/// operations have no latency.
+pub struct RequestLogReporter {
+    path: PathBuf,
+    log: Mutex<RequestLog>,
+}
+
+struct RequestLog {
+    writer: BufWriter<File>,
+    /// The first failed write, reported at the end.
+    error: Option<io::Error>,
+}
+
+impl RequestLogReporter {
+    pub fn create(path: PathBuf) -> Result<Self> {
+        let file = File::create(&path)
+            .map_err(|err| Error::from_io_error(&format!("create {}", path.display()), err))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "timestamp_us,qps,namespace,op,key,len,latency_us,outcome")
+            .map_err(|err| Error::from_io_error(&format!("write {}", path.display()), err))?;
+        Ok(Self { path, log: Mutex::new(RequestLog { writer, error: None }) })
+    }
+}
+
+impl Reporter for RequestLogReporter {
+    fn request(&self, request: &RequestInfo, result: &TestResult) {
+        let mut log = self.log.lock().unwrap_or_else(PoisonError::into_inner);
+        if log.error.is_some() {
+            return;
+        }
+        for (metric, latency) in result {
+            let (latency_us, outcome) = match latency {
+                Ok(latency) => (latency.as_micros().to_string(), "ok"),
+                Err(err) => (String::new(), err.class().name()),
+            };
+            let written = writeln!(
+                log.writer, "{},{},{},{},{},{},{},{}",
+                request.timestamp.as_micros(), request.qps, request.namespace, metric.name(),
+                csv_field(&request.key), request.len, latency_us, outcome,
+            );
+            if let Err(err) = written {
+                log.error = Some(err);
+                return;
+            }
+        }
+    }
+
+    fn finish(&self, _report: &TestReport) -> Result<()> {
+        let mut log = self.log.lock().unwrap_or_else(PoisonError::into_inner);
+        let flushed = match log.error.take() {
+            Some(err) => Err(err),
+            None => log.writer.flush(),
+        };
+        flushed.map_err(|err| Error::from_io_error(&format!("write {}", self.path.display()), err))?;
+        println!("REQUEST LOG:");
+        println!("  See also: {}", self.path.display());
+        Ok(())
+    }
+}
+
+/// Quote the field if it has a comma, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
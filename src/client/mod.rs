@@ -1 +0,0 @@
-pub mod localfs;
\ No newline at end of file
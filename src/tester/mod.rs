@@ -1,5 +0,0 @@
-mod tester;
-mod client;
-
-pub use client::{TestClient, TestClientHandler, Result, Error};
-pub use tester::Tester;
\ No newline at end of file